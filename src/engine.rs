@@ -0,0 +1,226 @@
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use crate::interpreter::{self, builtin, util};
+use crate::interpreter::module::Modules;
+use crate::parser;
+use crate::value::oran_value::{OranValue, NativeFunction};
+use crate::value::oran_variable::{OranVariable, OranVariableValue};
+use crate::value::oran_string::OranString;
+use crate::value::oran_error::{OranError, ErrorKind};
+use crate::value::oran_warning::OranWarning;
+use crate::value::scope::Scope;
+use crate::value::var_type::{FunctionOrValueType, VarType};
+use crate::vm::Vm;
+
+/// Which implementation runs the programs.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Backend {
+    /// The tree-walking interpreter.
+    Tree,
+    /// The bytecode compiler and the stack-based VM.
+    Vm,
+}
+
+/// The globals of the programs that an engine has run.
+enum Session {
+    Tree(Scope),
+    Vm(Box<Vm>),
+}
+
+/// Runs oran programs. Globals are kept between evaluations,
+/// so a program can use the variables and functions of the programs before it.
+///
+/// ```
+/// use oran::{Engine, OranValue};
+///
+/// let mut engine = Engine::new();
+/// engine.register_fn("double", |args| {
+///     match args.first() {
+///         Some(OranValue::Float(x)) => Ok(OranValue::Float(x * 2.0)),
+///         _ => Ok(OranValue::Null),
+///     }
+/// }).unwrap();
+/// engine.set_global("base", OranValue::Float(20.0));
+/// engine.eval("let answer = 2 + double(base);").unwrap();
+/// assert_eq!(engine.get_global("answer"), Some(OranValue::Float(42.0)));
+/// assert_eq!(engine.eval("answer - 2").unwrap(), OranValue::Float(40.0));
+/// ```
+pub struct Engine {
+    backend: Backend,
+    session: Session,
+    /// The search path, the registered functions and the imported modules.
+    modules: Rc<Modules>,
+    warnings: Vec<OranWarning>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::new()
+    }
+}
+
+impl Engine {
+    /// Creates an engine that runs programs with the tree-walking interpreter.
+    pub fn new() -> Self {
+        Engine::with_backend(Backend::Tree)
+    }
+
+    pub fn with_backend(backend: Backend) -> Self {
+        let modules = Rc::new(Modules::new());
+        Engine {
+            backend,
+            session: Engine::new_session(backend, &modules),
+            modules,
+            warnings: vec![],
+        }
+    }
+
+    fn new_session(backend: Backend, modules: &Rc<Modules>) -> Session {
+        match backend {
+            Backend::Tree => Session::Tree(Scope::new(modules.clone())),
+            Backend::Vm => Session::Vm(Box::new(Vm::new(modules.clone()))),
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Runs a program and returns the value of its last expression if it does not end with `;`,
+    /// and `OranValue::Null` otherwise.
+    pub fn eval(&mut self, source: &str) -> Result<OranValue, OranError> {
+        self.eval_named("<eval>", source)
+    }
+
+    /// Same as `eval`, but errors are located in the given file name.
+    pub fn eval_named(&mut self, filename: &str, source: &str) -> Result<OranValue, OranError> {
+        self.warnings.clear();
+        let (ast, is_trailing_expr) = parser::parse(filename, source)?;
+        self.warnings = parser::warnings(&ast);
+        let val = match self.session {
+            Session::Tree(ref scope) => interpreter::interp_program(scope, &ast)?,
+            Session::Vm(ref mut vm) => vm.run(&ast)?,
+        };
+        if is_trailing_expr {
+            Ok(util::unwrap_variable(val))
+        } else {
+            Ok(OranValue::Null)
+        }
+    }
+
+    pub fn eval_file<P: AsRef<Path>>(&mut self, path: P) -> Result<OranValue, OranError> {
+        let path = path.as_ref();
+        let filename = path.to_string_lossy();
+        let source = fs::read_to_string(path).map_err(|e| {
+            OranError::new(ErrorKind::Io, &format!("Unable to read {}: {}", filename, e))
+        })?;
+        self.eval_named(&filename, &source)
+    }
+
+    /// Adds a directory where `import` searches for modules that are not next to the file importing them.
+    /// Each engine has its own search path and its own imported modules.
+    pub fn add_search_path<P: AsRef<Path>>(&mut self, dir: P) {
+        self.modules.add_search_path(dir);
+    }
+
+    /// The warnings about the last program that was evaluated. They are found before it runs,
+    /// so they are kept even when it fails.
+    pub fn warnings(&self) -> &[OranWarning] {
+        &self.warnings
+    }
+
+    /// Whether the source ends in the middle of an expression, so that more lines should be read before running it.
+    pub fn is_incomplete(source: &str) -> bool {
+        parser::is_incomplete(source)
+    }
+
+    /// Returns the value of a global variable.
+    pub fn get_global(&self, name: &str) -> Option<OranValue> {
+        let val = match self.session {
+            Session::Tree(ref scope) => scope.get(FunctionOrValueType::Value, name),
+            Session::Vm(ref vm) => vm.get_global(FunctionOrValueType::Value, name),
+        };
+        val.map(util::unwrap_variable)
+    }
+
+    /// Defines a global variable like `let mut`, so that programs can change it.
+    pub fn set_global(&mut self, name: &str, val: OranValue) {
+        match self.session {
+            Session::Tree(ref scope) => {
+                scope.define(FunctionOrValueType::Value, name, OranValue::Variable(OranVariable {
+                    var_type: VarType::VariableFirstAssigned,
+                    name: OranString::from(name),
+                    value: OranVariableValue::from(&val),
+                }));
+            }
+            Session::Vm(ref mut vm) => {
+                let val = OranValue::from(&OranVariableValue::from(&val));
+                vm.define_global(FunctionOrValueType::Value, name, VarType::VariableFirstAssigned, val);
+            }
+        }
+    }
+
+    /// The global variables, sorted by name.
+    pub fn globals(&self) -> Vec<(String, OranValue)> {
+        match self.session {
+            Session::Tree(ref scope) => scope.local_bindings(FunctionOrValueType::Value).into_iter()
+                .map(|(name, val)| (String::from(name), util::unwrap_variable(val)))
+                .collect(),
+            Session::Vm(ref vm) => vm.globals(FunctionOrValueType::Value),
+        }
+    }
+
+    /// The names of the global functions, including the registered ones, sorted by name.
+    pub fn functions(&self) -> Vec<String> {
+        match self.session {
+            Session::Tree(ref scope) => scope.local_bindings(FunctionOrValueType::Function).into_iter()
+                .map(|(name, _)| String::from(name))
+                .collect(),
+            Session::Vm(ref vm) => vm.globals(FunctionOrValueType::Function).into_iter()
+                .map(|(name, _)| name)
+                .collect(),
+        }
+    }
+
+    /// Makes a Rust function callable from programs as a global function, in the modules they import too.
+    /// It gets the evaluated arguments. Its errors are located where it was called.
+    /// It can't take the name of a builtin function like `println`.
+    pub fn register_fn<F>(&mut self, name: &str, func: F) -> Result<(), OranError>
+    where
+        F: Fn(&[OranValue]) -> Result<OranValue, OranError> + 'static,
+    {
+        if builtin::is_builtin(name) {
+            return Err(OranError::new(ErrorKind::Syntax, &format!("\"{}\" is a builtin function and cannot be registered.", name)));
+        }
+        let native = NativeFunction {
+            name: OranString::from(name),
+            func: Rc::new(func),
+        };
+        self.define_native(native.clone());
+        self.modules.add_native(native);
+        Ok(())
+    }
+
+    fn define_native(&mut self, native: NativeFunction) {
+        let name = String::from(&native.name);
+        match self.session {
+            Session::Tree(ref scope) => {
+                scope.define(FunctionOrValueType::Function, &name, OranValue::Native(native));
+            }
+            Session::Vm(ref mut vm) => {
+                vm.define_global(FunctionOrValueType::Function, &name, VarType::Constant, OranValue::Native(native));
+            }
+        }
+    }
+
+    /// Forgets every global variable and function, and the imported modules, so that they run again when imported.
+    /// Registered functions and the search path are kept.
+    pub fn reset(&mut self) {
+        self.modules.clear();
+        self.session = Engine::new_session(self.backend, &self.modules);
+        for native in self.modules.natives() {
+            self.define_native(native);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::cell::RefCell;
+use crate::value::oran_value::{self, OranValue, OranList, OranMap, OranEnum};
+use crate::value::oran_string::OranString;
+use crate::value::oran_error::{OranError, ErrorKind};
+use num_traits::Signed;
+use rust_decimal::RoundingStrategy;
+use super::util;
+
+/// Functions that are always available. Scripts cannot define functions with these names.
+pub const BUILTINS: [&str; 13] = ["print", "println", "len", "push", "pop", "keys", "values", "has_key", "remove", "bigint", "decimal", "get", "parse_number"];
+
+pub fn is_builtin(name: &str) -> bool {
+    BUILTINS.contains(&name)
+}
+
+/// Whether the builtin function changes the list or the map that is its first argument,
+/// which it can't do when the list or the map is in a constant.
+pub fn changes_first_arg(name: &str) -> bool {
+    name == "push" || name == "pop" || name == "remove"
+}
+
+/// Methods of strings, numbers, options and results, called like `"abc".upper()`, `x.round()` and `found.is_some()`.
+pub const METHODS: [&str; 12] = ["upper", "lower", "trim", "round", "floor", "ceil", "abs", "is_some", "is_none", "is_ok", "is_err", "unwrap_or"];
+
+/// Whether a value that is not a struct has the method. The builtin functions but `print` and `println`
+/// are also methods of their first argument, so `xs.push(1)` is `push(xs, 1)`.
+pub fn is_method(name: &str) -> bool {
+    METHODS.contains(&name) || (is_builtin(name) && name != "print" && name != "println")
+}
+
+/// Calls a builtin method with the value it is called on and the arguments, which are already evaluated.
+pub fn call_method(location: &(String, usize, usize), name: &str, receiver: &OranValue, args: &[OranValue]) -> Result<OranValue, OranError> {
+    let receiver = util::unwrap_variable(receiver.clone());
+    let val = match name {
+        "upper" | "lower" | "trim" => {
+            let s = match receiver {
+                OranValue::Str(ref s) => s.val_str.as_ref(),
+                _ => return Err(method_of(location, name, "strings", &receiver)),
+            };
+            let s = match name {
+                "upper" => s.to_uppercase(),
+                "lower" => s.to_lowercase(),
+                _ => s.trim().to_owned(),
+            };
+            OranValue::Str(OranString::from(s))
+        },
+        "round" | "floor" | "ceil" | "abs" => match receiver {
+            OranValue::Int(i) if name == "abs" => i.checked_abs().map(OranValue::Int)
+                .ok_or_else(|| OranError::located(ErrorKind::Arithmetic, location, &format!("Integer overflow: abs({})", i)))?,
+            OranValue::Int(i) => OranValue::Int(i),
+            OranValue::BigInt(b) if name == "abs" => OranValue::BigInt(b.abs()),
+            OranValue::BigInt(b) => OranValue::BigInt(b),
+            OranValue::Decimal(d) => OranValue::Decimal(match name {
+                "round" => d.round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero),
+                "floor" => d.floor(),
+                "ceil" => d.ceil(),
+                _ => d.abs(),
+            }),
+            OranValue::Float(fl) => OranValue::Float(match name {
+                "round" => fl.round(),
+                "floor" => fl.floor(),
+                "ceil" => fl.ceil(),
+                _ => fl.abs(),
+            }),
+            _ => return Err(method_of(location, name, "numbers", &receiver)),
+        },
+        "is_some" | "is_none" => {
+            let option = prelude_arg(location, name, "Option", "options", &receiver)?;
+            OranValue::Boolean((option.variant_name() == "Some") == (name == "is_some"))
+        },
+        "is_ok" | "is_err" => {
+            let result = prelude_arg(location, name, "Result", "results", &receiver)?;
+            OranValue::Boolean((result.variant_name() == "Ok") == (name == "is_ok"))
+        },
+        "unwrap_or" => {
+            let default = arg(location, name, args, 0)?;
+            let val = match Option::<OranEnum>::from(&receiver) {
+                Some(ref val) if is_prelude(val, "Option") || is_prelude(val, "Result") => val.clone(),
+                _ => return Err(method_of(location, name, "options and results", &receiver)),
+            };
+            match val.variant_name() {
+                "Some" | "Ok" => val.values[0].clone(),
+                _ => default.clone(),
+            }
+        },
+        _ => {
+            let mut args_with_receiver = Vec::with_capacity(args.len() + 1);
+            args_with_receiver.push(receiver);
+            args_with_receiver.extend_from_slice(args);
+            return call(location, name, &args_with_receiver);
+        }
+    };
+    Ok(val)
+}
+
+/// Calls a builtin function with arguments that are already evaluated.
+/// Both the tree-walking interpreter and the VM go through here.
+pub fn call(location: &(String, usize, usize), name: &str, args: &[OranValue]) -> Result<OranValue, OranError> {
+    let val = match name {
+        "print" => {
+            print!("{}", concat(args));
+            io::stdout().flush().unwrap();
+            OranValue::Null
+        },
+        "println" => {
+            println!("{}", concat(args));
+            OranValue::Null
+        },
+        "len" => {
+            let val = arg(location, name, args, 0)?;
+            if let Some(list) = Option::<OranList>::from(val) {
+                return Ok(OranValue::Int(list.borrow().len() as i64));
+            }
+            if let Some(map) = Option::<OranMap>::from(val) {
+                return Ok(OranValue::Int(map.borrow().len() as i64));
+            }
+            OranValue::Int(val.to_string().chars().count() as i64)
+        },
+        "push" => {
+            let list = list_arg(location, name, args)?;
+            let val = oran_value::copy_value(arg(location, name, args, 1)?);
+            list.borrow_mut().push(val);
+            OranValue::Null
+        },
+        "pop" => {
+            let list = list_arg(location, name, args)?;
+            let popped = list.borrow_mut().pop();
+            popped.unwrap_or(OranValue::Null)
+        },
+        "keys" => {
+            let map = map_arg(location, name, args)?;
+            let keys = map.borrow().keys().map(OranValue::from).collect();
+            OranValue::List(Rc::new(RefCell::new(keys)))
+        },
+        "values" => {
+            let map = map_arg(location, name, args)?;
+            let values = map.borrow().values().cloned().collect();
+            OranValue::List(Rc::new(RefCell::new(values)))
+        },
+        "has_key" => {
+            let map = map_arg(location, name, args)?;
+            let key = util::map_key(location, arg(location, name, args, 1)?)?;
+            let has_key = map.borrow().contains_key(&key);
+            OranValue::Boolean(has_key)
+        },
+        "remove" => {
+            let map = map_arg(location, name, args)?;
+            let key = util::map_key(location, arg(location, name, args, 1)?)?;
+            let removed = map.borrow_mut().shift_remove(&key);
+            removed.unwrap_or(OranValue::Null)
+        },
+        "bigint" => {
+            let val = arg(location, name, args, 0)?;
+            oran_value::to_bigint(val).map_err(|e| e.at(location))?
+        },
+        "decimal" => {
+            let val = arg(location, name, args, 0)?;
+            oran_value::to_decimal(val).map_err(|e| e.at(location))?
+        },
+        "get" => {
+            // Like `xs[i]`, but gives `None` instead of failing when there is no such element.
+            let target = arg(location, name, args, 0)?;
+            let index = arg(location, name, args, 1)?;
+            match util::get_index(location, target, index) {
+                Ok(val) => OranValue::some(val),
+                Err(e) if e.kind == ErrorKind::Index => OranValue::none(),
+                Err(e) => return Err(e),
+            }
+        },
+        "parse_number" => {
+            let val = util::unwrap_variable(arg(location, name, args, 0)?.clone());
+            match val {
+                OranValue::Int(_) | OranValue::BigInt(_) | OranValue::Decimal(_) | OranValue::Float(_) => OranValue::ok(val),
+                _ => {
+                    let text = String::from(&val);
+                    let text = text.trim();
+                    if let Ok(int) = text.parse::<i64>() {
+                        OranValue::ok(OranValue::Int(int))
+                    } else {
+                        match text.parse::<f64>() {
+                            Ok(fl) if fl.is_finite() => OranValue::ok(OranValue::Float(fl)),
+                            _ => OranValue::err(OranValue::Str(OranString::from(format!("Not a number: {}", text)))),
+                        }
+                    }
+                }
+            }
+        },
+        _ => unreachable!("{} is not a builtin function", name)
+    };
+    Ok(val)
+}
+
+/// Joins the arguments as strings, the way `print` and `println` show them.
+pub fn concat(args: &[OranValue]) -> String {
+    let mut text = "".to_owned();
+    for val in args {
+        text.push_str(&String::from(val))
+    }
+    text
+}
+
+fn method_of(location: &(String, usize, usize), name: &str, kind: &str, receiver: &OranValue) -> OranError {
+    OranError::located(ErrorKind::Type, location, &format!("\"{}\" is a method of {}: {}", name, kind, receiver))
+}
+
+fn is_prelude(val: &OranEnum, enum_name: &str) -> bool {
+    oran_value::prelude_enum(enum_name).is_some_and(|define| Rc::ptr_eq(&val.define, &define))
+}
+
+fn prelude_arg(location: &(String, usize, usize), name: &str, enum_name: &str, kind: &str, receiver: &OranValue) -> Result<OranEnum, OranError> {
+    match Option::<OranEnum>::from(receiver) {
+        Some(val) if is_prelude(&val, enum_name) => Ok(val),
+        _ => Err(method_of(location, name, kind, receiver)),
+    }
+}
+
+fn arg<'a>(location: &(String, usize, usize), name: &str, args: &'a [OranValue], i: usize) -> Result<&'a OranValue, OranError> {
+    match args.get(i) {
+        Some(val) => Ok(val),
+        None => Err(OranError::located(ErrorKind::MissingArgument, location, &format!("Argument is necessary but not supplied to \"{}\".", name))),
+    }
+}
+
+fn list_arg(location: &(String, usize, usize), name: &str, args: &[OranValue]) -> Result<OranList, OranError> {
+    let val = arg(location, name, args, 0)?;
+    match Option::<OranList>::from(val) {
+        Some(list) => Ok(list),
+        None => Err(OranError::located(ErrorKind::Type, location, &format!("The first argument of \"{}\" must be a list: {}", name, val))),
+    }
+}
+
+fn map_arg(location: &(String, usize, usize), name: &str, args: &[OranValue]) -> Result<OranMap, OranError> {
+    let val = arg(location, name, args, 0)?;
+    match Option::<OranMap>::from(val) {
+        Some(map) => Ok(map),
+        None => Err(OranError::located(ErrorKind::Type, location, &format!("The first argument of \"{}\" must be a map: {}", name, val))),
+    }
+}
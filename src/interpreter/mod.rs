@@ -1,376 +1,637 @@
-use crate::parser::astnode::{AstNode, CalcOp, LogicalOperatorType, ComparisonlOperatorType};
-use crate::value::oran_value::{OranValue, FunctionDefine};
+use crate::parser::astnode::{AstNode, Accessor, ComparisonlOperatorType, LogicalOperatorType, Pattern};
+use crate::value::oran_value::{self, OranValue, FunctionDefine, StructDefine, EnumDefine, ModuleGlobals};
 use crate::value::oran_variable::{OranVariable, OranVariableValue};
 use crate::value::oran_string::OranString;
-use crate::value::var_type::FunctionOrValueType;
-use colored::*;
-use std::collections::HashMap;
-use std::io::{self, Write};
-use std::borrow::Cow;
-use num_traits::Pow;
-use std::process;
-mod util;
+use crate::value::var_type::{FunctionOrValueType, VarType};
+use crate::value::scope::Scope;
+use crate::value::oran_error::OranError;
+use std::rc::Rc;
+use std::cell::RefCell;
+use indexmap::IndexMap;
+pub mod builtin;
+pub mod util;
+pub mod module;
 
-pub fn interp_expr<'a, 'b:'a>(
-    scope: usize, 
-    env : &mut HashMap<(
-            usize,
-            FunctionOrValueType,
-            OranString<'b>
-        ),
-        OranValue<'b>
-    >, 
-    reduced_expr: &'b AstNode
-    ) -> OranValue<'a> {
+/// Non-local exits that unwind through `interp_expr` until something
+/// handles them: loops consume `Break`/`Continue`, function calls consume `Return`.
+/// `Error` and `Throw` go up to the nearest `try` with `catch`, or to `interp_program`.
+#[derive(Debug)]
+pub enum Interrupt {
+    Break((String, usize, usize)),
+    Continue((String, usize, usize)),
+    Return(OranValue),
+    Error(OranError),
+    /// A value thrown with `throw`, as the `Error` instance that `catch` gets.
+    Throw(OranValue),
+}
+
+impl From<OranError> for Interrupt {
+    fn from(e: OranError) -> Self {
+        Interrupt::Error(e)
+    }
+}
+
+pub fn interp_expr(
+    scope: &Scope,
+    reduced_expr: &AstNode
+    ) -> Result<OranValue, Interrupt> {
 
     match reduced_expr {
-        AstNode::Number(_location, double) => OranValue::Float(*double),
-        AstNode::Calc (verb, lhs, rhs) => {
-            match verb {
-                CalcOp::Plus => { interp_expr(scope, env, lhs) + interp_expr(scope, env, rhs) }
-                CalcOp::Minus => { interp_expr(scope, env, lhs) - interp_expr(scope, env, rhs) }
-                CalcOp::Times => { interp_expr(scope, env, lhs) * interp_expr(scope, env, rhs) }
-                CalcOp::Divide => { interp_expr(scope, env, lhs) / interp_expr(scope, env, rhs) }
-                CalcOp::Modulus => { interp_expr(scope, env, lhs) % interp_expr(scope, env, rhs) }
-                CalcOp::Power => {Pow::pow(interp_expr(scope, env, lhs), interp_expr(scope, env, rhs))}
-            }
+        AstNode::Int(_location, int) => Ok(OranValue::Int(*int)),
+        AstNode::BigInt(_location, int) => Ok(OranValue::BigInt(int.clone())),
+        AstNode::Decimal(_location, decimal) => Ok(OranValue::Decimal(*decimal)),
+        AstNode::Number(_location, double) => Ok(OranValue::Float(*double)),
+        AstNode::Calc (location, verb, lhs, rhs) => {
+            let lhs = interp_expr(scope, lhs)?;
+            let rhs = interp_expr(scope, rhs)?;
+            Ok(util::calc(location, *verb, lhs, rhs)?)
         }
         AstNode::Ident(location, ident) => {
-            let val = &*env.get(
-                &(
-                    scope,
-                    FunctionOrValueType::Value,
-                    OranString::from(ident)
-                )
-            ).unwrap_or_else(
-                || {     
-                    println!("{}\n{}\nLine number: {}, column number:{}: The variable \"{}\" is not defined.",
-                        "Error!".red().bold(),    
-                        location.0,    
-                        location.1,
-                        location.2,
-                        ident
-                    );
-                    process::exit(1);
-                }
-            );
-            val.clone()
+            // A function name without a call is the function itself.
+            match scope.lookup(FunctionOrValueType::Value, ident) {
+                Some(val) => Ok(val),
+                None => Err(util::undefined_variable(location, ident).into()),
+            }
         }
         AstNode::Assign(location, variable_type, ident, expr) => {
-            util::is_mutable(location.clone(), scope, env, ident, variable_type);
+            util::is_mutable(location, scope, ident, variable_type)?;
             let oran_val = OranValue::Variable(OranVariable {
                 var_type: *variable_type,
-                name: ident,
-                value: OranVariableValue::from(&interp_expr(scope, env, expr)),
+                name: OranString::from(ident),
+                value: OranVariableValue::from(&oran_value::copy_value(&interp_expr(scope, expr)?)),
             });
-            env.insert((scope, FunctionOrValueType::Value, OranString::from(ident)), oran_val.clone());
-            OranValue::Null
+            match variable_type {
+                VarType::VariableReAssigned => { scope.assign(FunctionOrValueType::Value, ident, oran_val); }
+                _ => scope.define(FunctionOrValueType::Value, ident, oran_val),
+            }
+            Ok(OranValue::Null)
         }
         AstNode::FunctionCall(location, name, arg_values) => {
+            // A variable or a function takes the place of the builtin function of the same name.
+            let callee = scope.lookup(FunctionOrValueType::Function, name);
             match name.as_ref() {
-                "print" => {
-                    let mut text = "".to_owned();
-                    for str in arg_values {
-                        text.push_str(&String::from(&interp_expr(scope, env, &str)))
+                name if callee.is_none() && builtin::is_builtin(name) => {
+                    if builtin::changes_first_arg(name) && arg_values.first().map(|first| receiver_type(scope, first)) == Some(VarType::Constant) {
+                        return Err(util::constant_element_change(location).into());
                     }
-                    print!("{}", text);
-                    io::stdout().flush().unwrap();
-                    OranValue::Null
-                },
-                "println" => {
-                    let mut text = "".to_owned();
-                    for str in arg_values {
-                        text.push_str(&String::from(&interp_expr(scope, env, &str)))
+                    let mut args = Vec::with_capacity(arg_values.len());
+                    for arg in arg_values {
+                        args.push(interp_expr(scope, arg)?);
                     }
-                    println!("{}", text);
-                    OranValue::Null
+                    Ok(builtin::call(location, name, &args)?)
                 },
                 _ => {
-                    //Try to get function definition from current scope.
-                    let func = *&env.get(&(scope, FunctionOrValueType::Function, OranString::from(name)));
-                    let func = match func {
-                        None => {
-                            //If not found, then try to get function definition from top level.
-                            let func_defined_on_top = *&env.get(&(0, FunctionOrValueType::Function, OranString::from(name)));
-                            let func_defined_on_top = match func_defined_on_top {
-                                None => {
-                                    println!("{}\n{}\nLine number: {}, column number:{}: Function \"{}\" is not defined.",
-                                        "Error!".red().bold(),    
-                                        location.0,    
-                                        location.1,
-                                        location.2,
-                                        name
-                                    );
-                                    process::exit(1);
-                                },
-                                _ => func_defined_on_top.unwrap()
-                            };
-                            func_defined_on_top
-                        },
-                        _ => func.unwrap()
+                    // Variables holding functions can be called like functions.
+                    let func = match callee.map(util::unwrap_variable) {
+                        Some(OranValue::Function(func)) => func,
+                        Some(OranValue::Native(native)) => {
+                            let mut args = Vec::with_capacity(arg_values.len());
+                            for arg in arg_values {
+                                args.push(util::unwrap_variable(interp_expr(scope, arg)?));
+                            }
+                            return Ok(util::call_native(location, &native, &args)?);
+                        }
+                        Some(_) => return Err(util::not_a_function(location, name).into()),
+                        None => return Err(util::undefined_function(location, name).into()),
                     };
-                    let func = FunctionDefine::from(func);
-                    for i in 0..func.args.len() {
-                        let arg_name = interp_expr(scope+1, env, func.args.into_iter().nth(i).unwrap());
-                        let arg_ast = arg_values.into_iter().nth(i).unwrap_or_else(||
-                            {
-                                println!("{}\n{}\nLine number: {}, column number:{}: Argument is necessary but not supplied.",
-                                    "Error!".red().bold(),    
-                                    location.0,    
-                                    location.1,
-                                    location.2,
-                                );
-                                process::exit(1);
-                            });
-                        let val = interp_expr(scope, env, arg_ast);
-                        env.insert((scope+1, FunctionOrValueType::Value, OranString::from(arg_name)), val);
+                    call_function(scope, location, &func, None, arg_values)
+                }
+            }
+        }
+        AstNode::MethodCall(location, receiver_ast, name, arg_values) => {
+            let receiver = interp_expr(scope, receiver_ast)?;
+            if let Some(func) = util::module_function(location, &receiver, name)? {
+                return match func {
+                    OranValue::Function(func) => call_function(scope, location, &func, None, arg_values),
+                    _ => Err(util::not_a_function(location, name).into()),
+                };
+            }
+            match util::find_method(location, &receiver, name)? {
+                Some(OranValue::Function(func)) => {
+                    let receiver_type = receiver_type(scope, receiver_ast);
+                    call_function(scope, location, &func, Some((receiver, receiver_type)), arg_values)
+                }
+                Some(_) => Err(util::not_a_function(location, name).into()),
+                None => {
+                    let mut args = Vec::with_capacity(arg_values.len());
+                    for arg in arg_values {
+                        args.push(interp_expr(scope, arg)?);
                     }
-                    let mut returned_val = OranValue::Null;
-                    for body in func.body {
-                        returned_val = interp_expr(scope+1, env, &body);
-                        match returned_val {
-                            OranValue::Null => {}
-                            _ => { break }
-                        }
+                    if builtin::changes_first_arg(name) && receiver_type(scope, receiver_ast) == VarType::Constant {
+                        return Err(util::constant_element_change(location).into());
                     }
-                    match returned_val {
-                        OranValue::Null => {
-                            returned_val = interp_expr(scope+1, env, func.fn_return);
-                        }
-                        _ => {}
+                    Ok(builtin::call_method(location, name, &receiver, &args)?)
+                }
+            }
+        }
+        AstNode::Call(location, callee, arg_values) => {
+            match util::unwrap_variable(interp_expr(scope, callee)?) {
+                OranValue::Function(func) => call_function(scope, location, &func, None, arg_values),
+                OranValue::Native(native) => {
+                    let mut args = Vec::with_capacity(arg_values.len());
+                    for arg in arg_values {
+                        args.push(util::unwrap_variable(interp_expr(scope, arg)?));
                     }
-                    // delete unnecessary data when exiting a scope
-                    // TODO garbage colloctor
-                    env.retain(|(s, __k, _label), _orn_val| *s != scope+1);
-                    returned_val
+                    Ok(util::call_native(location, &native, &args)?)
                 }
+                other => Err(util::not_callable(location, &other).into()),
             }
         }
         AstNode::FunctionDefine(_location, func_name, args, astnodes, fn_return) => {
             let val = OranValue::Function(FunctionDefine {
-                name: func_name,
-                args: args,
-                body: astnodes,
-                fn_return: fn_return
+                name: OranString::from(func_name),
+                args: args.clone(),
+                body: astnodes.clone(),
+                fn_return: fn_return.clone(),
+                closure: scope.clone(),
             });
-            env.insert((scope, FunctionOrValueType::Function, OranString::from(func_name)), val.clone());
-            val
+            scope.define(FunctionOrValueType::Function, func_name, val.clone());
+            Ok(val)
+        }
+        AstNode::Impl(location, name, methods) => {
+            let define = scope.get(FunctionOrValueType::Value, name);
+            for method in methods {
+                if let AstNode::FunctionDefine(_, method_name, args, body, fn_return) = method {
+                    let val = OranValue::Function(FunctionDefine {
+                        name: OranString::from(method_name),
+                        args: args.clone(),
+                        body: body.clone(),
+                        fn_return: fn_return.clone(),
+                        closure: scope.clone(),
+                    });
+                    util::define_method(location, name, define.clone(), method_name, val)?;
+                }
+            }
+            Ok(OranValue::Null)
+        }
+        AstNode::AnonymousFunction(_location, args, astnodes, fn_return) => {
+            Ok(OranValue::Function(FunctionDefine {
+                name: OranString::from(""),
+                args: args.clone(),
+                body: astnodes.clone(),
+                fn_return: fn_return.clone(),
+                closure: scope.clone(),
+            }))
         }
         AstNode::Argument(_location, argument_name, val) => {
-            let val = interp_expr(scope, env, val);
-            env.insert((scope, FunctionOrValueType::Value, OranString::from(argument_name)), val);
-            OranValue::Str(OranString::from(argument_name))
+            let val = interp_expr(scope, val)?;
+            scope.define(FunctionOrValueType::Value, argument_name, val);
+            Ok(OranValue::Str(OranString::from(argument_name)))
         }
         AstNode::Str (_location, str_val) => {
-            OranValue::Str(OranString::from(str_val))
+            Ok(OranValue::Str(OranString::from(str_val)))
         }
         AstNode::Strs (_location, strs) => {
             let mut text = "".to_owned();
             for str in strs {
-                text.push_str(&String::from(interp_expr(scope, env, &str)))
+                text.push_str(&String::from(interp_expr(scope, str)?))
             }
-            OranValue::Str(OranString {
-                val_str: Cow::from(text)
-            })
+            Ok(OranValue::Str(OranString::from(text)))
         }
         AstNode::Condition (c, e, o) => {
-            let e = interp_expr(scope, env, e);
-            let o = interp_expr(scope, env, o);
-            match c {
-                ComparisonlOperatorType::AND => {
-                    if bool::from(e) && bool::from(o) {
-                        return OranValue::Boolean(true);
-                    }
-                    OranValue::Boolean(false)
-                }
-                ComparisonlOperatorType::OR => {
-                    if bool::from(e) || bool::from(o) {
-                        return OranValue::Boolean(true);
-                    }
-                    OranValue::Boolean(false)
-                }
+            // The right side is evaluated only when the left side does not decide the result.
+            let e = bool::from(interp_expr(scope, e)?);
+            let is_decided = match c {
+                ComparisonlOperatorType::And => !e,
+                ComparisonlOperatorType::Or => e,
+            };
+            if is_decided {
+                return Ok(OranValue::Boolean(e));
             }
+            Ok(OranValue::Boolean(bool::from(interp_expr(scope, o)?)))
         }
         AstNode::Comparison (location, e, c, o) => {
-            let e = interp_expr(scope, env, e);
-            let o = interp_expr(scope, env, o);
-
-            let is_num_e  = match Result::<f64, String>::from(&e) {
-                Ok(_v) => true,
-                Err(_e) => false
-            };
-            let is_num_o = match Result::<f64, String>::from(&o) {
-                Ok(_v) => true,
-                Err(_e) => false
-            };
-
-            if !is_num_e || !is_num_o {
-                match c {
-                    LogicalOperatorType::Equal => {
-                        if e.to_string() == o.to_string() {
-                            return OranValue::Boolean(true);
-                        }
-                        OranValue::Boolean(false)
-                    },
-                    _ => {
-                        println!("{}\n{}\nLine number: {}, column number:{}: One of these are not number: {}, {}",
-                            "Error!".red().bold(),    
-                            location.0,    
-                            location.1,
-                            location.2,
-                            e,
-                            o
-                        );
-                        process::exit(1);
-                    },
+            let e = interp_expr(scope, e)?;
+            let o = interp_expr(scope, o)?;
+            Ok(util::compare(location, &e, *c, &o)?)
+        }
+        AstNode::Not(_location, val) => {
+            let val = interp_expr(scope, val)?;
+            Ok(OranValue::Boolean(!bool::from(val)))
+        }
+        AstNode::Negate(location, val) => {
+            let val = interp_expr(scope, val)?;
+            Ok(util::negate(location, val)?)
+        }
+        AstNode::UnaryPlus(location, val) => {
+            let val = interp_expr(scope, val)?;
+            Ok(util::unary_plus(location, val)?)
+        }
+        AstNode::BitNot(location, val) => {
+            let val = interp_expr(scope, val)?;
+            Ok(util::bit_not(location, val)?)
+        }
+        AstNode::IF(_location, if_conditions, body, else_if_bodies_conditions, else_bodies) => {
+            // if
+            let condition_result = interp_expr(scope, if_conditions)?;
+            if bool::from(condition_result) {
+                return interp_block(scope, body);
+            }
+            // else if
+            for (conditions, else_if_body) in else_if_bodies_conditions {
+                for c in conditions {
+                    let result = interp_expr(scope, c)?;
+                    if bool::from(result) {
+                        return interp_block(scope, else_if_body);
+                    }
                 }
-            } else {
-                match c {
-                    LogicalOperatorType::Equal => {
-                        if e == o {
-                            return OranValue::Boolean(true);
+            }
+            // else
+            interp_block(scope, else_bodies)
+        }
+        AstNode::Match(location, value, arms) => {
+            let val = interp_expr(scope, value)?;
+            for arm in arms {
+                for pattern in &arm.patterns {
+                    // The names that a variant pattern gives to the values of the variant.
+                    let mut bound = vec![];
+                    let is_matched = match pattern {
+                        Pattern::Literal(literal) => {
+                            let literal = interp_expr(scope, literal)?;
+                            bool::from(util::compare(location, &val, LogicalOperatorType::Equal, &literal)?)
                         }
-                        OranValue::Boolean(false)
-                    },
-                    LogicalOperatorType::BiggerThan => {
-                        if e > o {
-                            return OranValue::Boolean(true);
+                        Pattern::Range(first, last, is_inclusive) => {
+                            let first = interp_expr(scope, first)?;
+                            let last = interp_expr(scope, last)?;
+                            util::in_range(location, &val, &first, &last, *is_inclusive)?
                         }
-                        OranValue::Boolean(false)
-                    },
-                    LogicalOperatorType::SmallerThan => {
-                        if e < o {
-                            return OranValue::Boolean(true);
+                        Pattern::Wildcard => true,
+                        Pattern::Variant(pattern) => {
+                            let found = scope.get(FunctionOrValueType::Value, &pattern.enum_name);
+                            let define = util::enum_define(location, &pattern.enum_name, found)?;
+                            let bindings = pattern.bindings.as_ref().map(Vec::len);
+                            match util::match_variant(location, &val, &define, &pattern.variant, bindings)? {
+                                Some(values) => {
+                                    let names = pattern.bindings.iter().flatten();
+                                    bound = names.zip(values.iter()).filter_map(|(name, val)| name.clone().map(|name| (name, val.clone()))).collect();
+                                    true
+                                }
+                                None => false,
+                            }
                         }
-                        OranValue::Boolean(false)
-                    },
-                    LogicalOperatorType::EbiggerThan => {
-                        if e >= o {
-                            return OranValue::Boolean(true);
+                    };
+                    if is_matched {
+                        let block_scope = scope.new_child();
+                        for (name, val) in bound {
+                            block_scope.define(FunctionOrValueType::Value, &name, OranValue::Variable(OranVariable {
+                                var_type: VarType::Constant,
+                                name: OranString::from(&name),
+                                value: OranVariableValue::from(&val),
+                            }));
                         }
-                        OranValue::Boolean(false)
-                    },
-                    LogicalOperatorType::EsmallerThan => {
-                        if e <= o {
-                            return OranValue::Boolean(true);
+                        for stmt in &arm.body {
+                            interp_expr(&block_scope, stmt)?;
                         }
-                        OranValue::Boolean(false)
-                    },
+                        return interp_expr(&block_scope, &arm.value);
+                    }
                 }
             }
+            Ok(OranValue::Null)
         }
-        AstNode::IF(_location, if_conditions, body, else_if_bodies_conditions, else_bodies) => {
-            // if
-            let condition_result = interp_expr(scope, env, if_conditions);
-            if bool::from(condition_result) {
-                let mut returned_val =  OranValue::Null;
-                for astnode in body {
-                    returned_val = interp_expr(scope, env, &astnode);
-                }
-                return returned_val;
+        AstNode::Bool (_location, b) => {
+            Ok(OranValue::Boolean(*b))
+        }
+        AstNode::List (_location, items) => {
+            let mut list = Vec::with_capacity(items.len());
+            for item in items {
+                let val = interp_expr(scope, item)?;
+                list.push(OranValue::from(&OranVariableValue::from(&val)));
             }
-            // else if
-            let mut _is_all_false = true;
-            if !else_if_bodies_conditions.is_empty() {
-                for (conditions, else_if_body) in else_if_bodies_conditions {
-                    for c in conditions {
-                        let result = interp_expr(scope, env, &c);
-                        if bool::from(result) {
-                            _is_all_false = false;
-                            let mut returned_val =  OranValue::Null;
-                            for astnode in else_if_body {
-                                returned_val = interp_expr(scope, env, &astnode);
-                            }
-                            return returned_val;
-                        }
+            Ok(OranValue::List(Rc::new(RefCell::new(list))))
+        }
+        AstNode::Map (location, entries) => {
+            let mut map = IndexMap::with_capacity(entries.len());
+            for (key, val) in entries {
+                let key = interp_expr(scope, key)?;
+                let key = util::map_key(location, &key)?;
+                let val = interp_expr(scope, val)?;
+                map.insert(key, OranValue::from(&OranVariableValue::from(&val)));
+            }
+            Ok(OranValue::Map(Rc::new(RefCell::new(map))))
+        }
+        AstNode::Index (location, target, index) => {
+            let target = interp_expr(scope, target)?;
+            let index = interp_expr(scope, index)?;
+            Ok(util::get_index(location, &target, &index)?)
+        }
+        AstNode::IndexAssign (location, ident, accessors, compound, expr) => {
+            let mut target = match scope.get(FunctionOrValueType::Value, ident) {
+                Some(OranValue::Variable(v)) if v.var_type == VarType::Constant => {
+                    return Err(util::constant_element_assignment(location, &accessors[0]).into());
+                },
+                Some(target) => target,
+                None => return Err(util::undefined_variable(location, ident).into()),
+            };
+            let (last, accessors) = accessors.split_last().unwrap();
+            for accessor in accessors {
+                target = match accessor {
+                    Accessor::Index(index) => {
+                        let index = interp_expr(scope, index)?;
+                        util::get_index(location, &target, &index)?
                     }
-                    
+                    Accessor::Field(field) => util::get_field(location, &target, field)?,
+                };
+            }
+            // `xs[i] += v` reads the element with the index evaluated once.
+            let combine = |current: OranValue| -> Result<OranValue, Interrupt> {
+                let val = interp_expr(scope, expr)?;
+                match compound {
+                    Some((op_location, verb)) => Ok(util::calc(op_location, *verb, current, val)?),
+                    None => Ok(val),
                 }
-                if _is_all_false == false {
-                    return OranValue::Null;
+            };
+            match last {
+                Accessor::Index(index) => {
+                    let index = interp_expr(scope, index)?;
+                    let current = match compound {
+                        Some(_) => util::get_index(location, &target, &index)?,
+                        None => OranValue::Null,
+                    };
+                    let val = combine(current)?;
+                    util::set_index(location, &target, &index, OranValue::from(&OranVariableValue::from(&val)))?;
+                }
+                Accessor::Field(field) => {
+                    let current = match compound {
+                        Some(_) => util::get_field(location, &target, field)?,
+                        None => OranValue::Null,
+                    };
+                    let val = combine(current)?;
+                    util::set_field(location, &target, field, OranValue::from(&OranVariableValue::from(&val)))?;
                 }
             }
-            // else
-            if !else_bodies.is_empty() {
-                let mut returned_val =  OranValue::Null;
-                for astnode in else_bodies {
-                    returned_val = interp_expr(scope, env, astnode);
+            Ok(OranValue::Null)
+        }
+        AstNode::Field (location, target, field) => {
+            let target = interp_expr(scope, target)?;
+            Ok(util::get_field(location, &target, field)?)
+        }
+        AstNode::StructDefine (_location, name, fields) => {
+            let define = OranValue::StructDefine(Rc::new(StructDefine {
+                name: OranString::from(name),
+                fields: fields.clone(),
+                methods: RefCell::new(IndexMap::new()),
+            }));
+            scope.define(FunctionOrValueType::Value, name, OranValue::Variable(OranVariable {
+                var_type: VarType::Constant,
+                name: OranString::from(name),
+                value: OranVariableValue::from(&define),
+            }));
+            Ok(OranValue::Null)
+        }
+        AstNode::Import (location, path, name) => {
+            // A module runs in its own global scope, and its globals are its members.
+            let modules = scope.modules();
+            let module = modules.import(location, path, |ast| {
+                let module_scope = Scope::new(modules.clone());
+                for native in modules.natives() {
+                    let name = String::from(&native.name);
+                    module_scope.define(FunctionOrValueType::Function, &name, OranValue::Native(native));
                 }
-                return returned_val;
+                interp_program(&module_scope, ast)?;
+                Ok(ModuleGlobals::Tree(module_scope))
+            })?;
+            scope.define(FunctionOrValueType::Value, name, OranValue::Variable(OranVariable {
+                var_type: VarType::Constant,
+                name: OranString::from(name),
+                value: OranVariableValue::Module(module),
+            }));
+            Ok(OranValue::Null)
+        }
+        AstNode::EnumDefine (_location, name, variants) => {
+            let define = OranValue::EnumDefine(Rc::new(EnumDefine {
+                name: OranString::from(name),
+                variants: variants.clone(),
+            }));
+            scope.define(FunctionOrValueType::Value, name, OranValue::Variable(OranVariable {
+                var_type: VarType::Constant,
+                name: OranString::from(name),
+                value: OranVariableValue::from(&define),
+            }));
+            Ok(OranValue::Null)
+        }
+        AstNode::Variant (location, enum_name, variant, args) => {
+            let mut values = Vec::with_capacity(args.len());
+            for arg in args {
+                let val = interp_expr(scope, arg)?;
+                values.push(OranValue::from(&OranVariableValue::from(&val)));
             }
-            OranValue::Null
+            let found = scope.get(FunctionOrValueType::Value, enum_name);
+            let define = util::enum_define(location, enum_name, found)?;
+            Ok(util::make_variant(location, define, variant, values)?)
         }
-        AstNode::Bool (_location, b) => {
-            OranValue::Boolean(*b)
-        }
-        AstNode::ForLoop(_location, is_inclusive, var_type, i, first, last, stmts) => {
-            let first = interp_expr(scope, env, first);
-            let first = f64::from(first).round() as i64;
-            let last = interp_expr(scope, env, last);
-            let last = f64::from(last).round() as i64;
-            let i_name = OranString::from(i);
-            match is_inclusive {
-                true => {
-                    for num in first..=last {
-                        &env.insert(
-                            (scope, FunctionOrValueType::Value, i_name.clone()),
-                            OranValue::Variable(OranVariable {
-                                var_type: *var_type,
-                                name: i,
-                                value: OranVariableValue::Float(num as f64)
-                            })
-                        );
-                        let mut returned_val: OranValue;
-                        for stmt in stmts {
-                            returned_val = interp_expr(scope, env, stmt);
-                            match returned_val {
-                                OranValue::Null => {},
-                                _ => { return returned_val }
-                            }
-                        }
-                    }
-                    // remove variable "i"
-                    env.remove(
-                        &(
-                            scope,
-                            FunctionOrValueType::Value,
-                            OranString::from(i)
-                        )
+        AstNode::StructLiteral (location, name, fields) => {
+            let mut values = Vec::with_capacity(fields.len());
+            for (field, val) in fields {
+                let val = interp_expr(scope, val)?;
+                values.push((field.clone(), OranValue::from(&OranVariableValue::from(&val))));
+            }
+            let define = scope.get(FunctionOrValueType::Value, name);
+            Ok(util::make_struct(location, name, define, values)?)
+        }
+        AstNode::ForLoop(location, is_inclusive, var_type, i, first, last, stmts) => {
+            let first = interp_expr(scope, first)?;
+            let first = util::range_bound(location, &first)?;
+            let last = interp_expr(scope, last)?;
+            let last = util::range_bound(location, &last)?;
+            let nums: Box<dyn Iterator<Item = i64>> = if *is_inclusive { Box::new(first..=last) } else { Box::new(first..last) };
+            for num in nums {
+                // Each iteration gets its own frame for the loop variable.
+                let loop_scope = scope.new_child();
+                loop_scope.define(
+                    FunctionOrValueType::Value,
+                    i,
+                    OranValue::Variable(OranVariable {
+                        var_type: *var_type,
+                        name: OranString::from(i),
+                        value: OranVariableValue::Int(num)
+                    })
+                );
+                if !interp_loop_body(&loop_scope, stmts)? {
+                    break;
+                }
+            }
+            Ok(OranValue::Null)
+        }
+        AstNode::ForEach(location, var_type, first_name, second_name, iterable, stmts) => {
+            let iterable = interp_expr(scope, iterable)?;
+            for (first, second) in util::loop_items(location, &iterable, second_name.is_some(), *var_type != VarType::Constant)? {
+                let loop_scope = scope.new_child();
+                loop_scope.define(
+                    FunctionOrValueType::Value,
+                    first_name,
+                    OranValue::Variable(OranVariable {
+                        var_type: *var_type,
+                        name: OranString::from(first_name),
+                        value: OranVariableValue::from(&first)
+                    })
+                );
+                if let (Some(second_name), Some(second)) = (second_name, second) {
+                    loop_scope.define(
+                        FunctionOrValueType::Value,
+                        second_name,
+                        OranValue::Variable(OranVariable {
+                            var_type: *var_type,
+                            name: OranString::from(second_name),
+                            value: OranVariableValue::from(&second)
+                        })
                     );
                 }
-                false => {
-                    for num in first..last {
-                        &env.insert(
-                            (scope, FunctionOrValueType::Value, i_name.clone()),
-                            OranValue::Variable(OranVariable {
-                                var_type: *var_type,
-                                name: i,
-                                value: OranVariableValue::Float(num as f64)
-                            })
-                        );
-                        let mut returned_val: OranValue;
-                        for stmt in stmts {
-                            returned_val = interp_expr(scope, env, stmt);
-                            match returned_val {
-                                OranValue::Null => {},
-                                _ => { return returned_val }
-                            }
-                        }
-                        // remove variable "i"
-                        env.remove(
-                            &(
-                                scope,
-                                FunctionOrValueType::Value,
-                                OranString::from(i)
-                            )
-                        );
-                    }
+                if !interp_loop_body(&loop_scope, stmts)? {
+                    break;
                 }
             }
-            
-            OranValue::Null
+            Ok(OranValue::Null)
         }
-        AstNode::Null => OranValue::Null,
+        AstNode::WhileLoop(_location, condition, stmts) => {
+            while bool::from(interp_expr(scope, condition)?) {
+                if !interp_loop_body(&scope.new_child(), stmts)? {
+                    break;
+                }
+            }
+            Ok(OranValue::Null)
+        }
+        AstNode::Break(location) => Err(Interrupt::Break(location.clone())),
+        AstNode::Continue(location) => Err(Interrupt::Continue(location.clone())),
+        AstNode::Return(_location, val) => {
+            let val = interp_expr(scope, val)?;
+            Err(Interrupt::Return(val))
+        }
+        AstNode::Throw(location, val) => {
+            let val = util::unwrap_variable(interp_expr(scope, val)?);
+            Err(Interrupt::Throw(util::thrown_error(location, val)))
+        }
+        AstNode::Try(_location, body, catch, finally) => {
+            let mut result = interp_block(scope, body);
+            if let Some((name, catch_body)) = catch {
+                let error = match result {
+                    Err(Interrupt::Error(ref e)) => Some(util::caught_error(e)),
+                    Err(Interrupt::Throw(ref val)) => Some(val.clone()),
+                    _ => None,
+                };
+                if let Some(error) = error {
+                    let catch_scope = scope.new_child();
+                    catch_scope.define(FunctionOrValueType::Value, name, OranValue::Variable(OranVariable {
+                        var_type: VarType::Constant,
+                        name: OranString::from(name),
+                        value: OranVariableValue::from(&error),
+                    }));
+                    result = interp_block(&catch_scope, catch_body);
+                }
+            }
+            // finally runs however the blocks ended. An error or a jump out of it replaces theirs.
+            if let Some(finally) = finally {
+                interp_block(scope, finally)?;
+            }
+            result.map(|_| OranValue::Null)
+        }
+        AstNode::Null => Ok(OranValue::Null),
         //_ => unreachable!("{:?}", reduced_expr)
     }
 }
+
+/// Whether the value that a method is called on may be changed through `self`.
+/// A value in a constant can't, even when it is reached through a field or an index.
+fn receiver_type(scope: &Scope, receiver: &AstNode) -> VarType {
+    match receiver {
+        AstNode::Ident(_, name) => match scope.get(FunctionOrValueType::Value, name) {
+            Some(OranValue::Variable(v)) if v.var_type == VarType::Constant => VarType::Constant,
+            _ => VarType::VariableFirstAssigned,
+        },
+        AstNode::Index(_, target, _) | AstNode::Field(_, target, _) => receiver_type(scope, target),
+        _ => VarType::VariableFirstAssigned,
+    }
+}
+
+/// Calls a function defined in the script. The arguments are evaluated in the caller's scope one by one.
+/// A method gets the value it is called on as its first argument, `self`, which is a constant when the value is in one.
+fn call_function(
+    scope: &Scope,
+    location: &(String, usize, usize),
+    func: &FunctionDefine,
+    receiver: Option<(OranValue, VarType)>,
+    arg_values: &[AstNode]
+    ) -> Result<OranValue, Interrupt> {
+    // The body sees the scope where the function was defined, not the caller's.
+    let call_scope = func.closure.new_child();
+    let skipped = receiver.is_some() as usize;
+    let mut receiver = receiver;
+    for (i, arg) in func.args.iter().enumerate() {
+        let arg_name = String::from(arg);
+        let (var_type, val) = match receiver.take() {
+            Some((receiver, var_type)) => (var_type, receiver),
+            None => match arg_values.get(i - skipped) {
+                Some(arg_ast) => (VarType::Constant, interp_expr(scope, arg_ast)?),
+                None => return Err(util::missing_argument(location).into()),
+            },
+        };
+        call_scope.define(FunctionOrValueType::Value, &arg_name, OranValue::Variable(OranVariable {
+            var_type,
+            name: OranString::from(arg_name.as_str()),
+            value: OranVariableValue::from(&val),
+        }));
+    }
+    for body in func.body.iter() {
+        match interp_expr(&call_scope, body) {
+            Ok(_) => {}
+            Err(Interrupt::Return(val)) => return Ok(val),
+            Err(Interrupt::Break(location)) | Err(Interrupt::Continue(location)) => {
+                return Err(util::loop_control_outside_loop(&location).into());
+            }
+            Err(interrupt) => return Err(interrupt),
+        }
+    }
+    interp_expr(&call_scope, &func.fn_return)
+}
+
+/// Runs the statements in a new frame and returns the value of the last one.
+fn interp_block(
+    scope: &Scope,
+    stmts: &[AstNode]
+    ) -> Result<OranValue, Interrupt> {
+    let block_scope = scope.new_child();
+    let mut returned_val = OranValue::Null;
+    for stmt in stmts {
+        returned_val = interp_expr(&block_scope, stmt)?;
+    }
+    Ok(returned_val)
+}
+
+/// Runs one pass over a loop body.
+/// Returns `Ok(false)` when the body asked the loop to stop with `break`.
+fn interp_loop_body(
+    scope: &Scope,
+    stmts: &[AstNode]
+    ) -> Result<bool, Interrupt> {
+    for stmt in stmts {
+        match interp_expr(scope, stmt) {
+            Ok(_) => {}
+            Err(Interrupt::Break(_)) => return Ok(false),
+            Err(Interrupt::Continue(_)) => return Ok(true),
+            Err(interrupt) => return Err(interrupt),
+        }
+    }
+    Ok(true)
+}
+
+/// Runs a program and returns the value of the last statement if it is an expression.
+pub fn interp_program(
+    scope: &Scope,
+    ast: &[AstNode]
+    ) -> Result<OranValue, OranError> {
+    let mut returned_val = OranValue::Null;
+    for reduced_expr in ast {
+        match interp_expr(scope, reduced_expr) {
+            Ok(val) => {
+                returned_val = if reduced_expr.is_expression() { val } else { OranValue::Null };
+            }
+            // "return" on top level ends the program.
+            Err(Interrupt::Return(val)) => return Ok(val),
+            Err(Interrupt::Break(location)) | Err(Interrupt::Continue(location)) => {
+                return Err(util::loop_control_outside_loop(&location));
+            }
+            Err(Interrupt::Error(e)) => return Err(e),
+            Err(Interrupt::Throw(val)) => return Err(util::uncaught_error(&val)),
+        }
+    }
+    Ok(returned_val)
+}
@@ -0,0 +1,105 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use crate::parser;
+use crate::parser::astnode::AstNode;
+use crate::value::oran_value::{OranModule, ModuleGlobals, NativeFunction};
+use crate::value::oran_string::OranString;
+use crate::value::oran_error::{OranError, ErrorKind};
+
+/// What the modules of an engine share: where `import` searches for them, the functions the host registered,
+/// and the modules imported so far. Each engine has its own, so engines don't see each other's modules.
+#[derive(Debug, Default)]
+pub struct Modules {
+    /// The directories where modules are searched for when they are not next to the file that imports them.
+    search_path: RefCell<Vec<PathBuf>>,
+    /// The functions registered with `Engine::register_fn`, which modules can call like the programs of the engine.
+    natives: RefCell<Vec<NativeFunction>>,
+    /// The modules that have been imported, by their canonical paths.
+    /// A module runs only once, and every file that imports it gets the same module.
+    cache: RefCell<HashMap<PathBuf, Rc<OranModule>>>,
+    /// The modules that are running their imports now, with the paths they were imported with.
+    loading: RefCell<Vec<(PathBuf, String)>>,
+}
+
+impl Modules {
+    pub fn new() -> Self {
+        Modules::default()
+    }
+
+    /// Adds a directory where `import` searches for modules.
+    pub fn add_search_path<P: AsRef<Path>>(&self, dir: P) {
+        self.search_path.borrow_mut().push(dir.as_ref().to_path_buf());
+    }
+
+    /// Adds a function that modules get as a global function. It replaces the one of the same name.
+    pub fn add_native(&self, native: NativeFunction) {
+        let mut natives = self.natives.borrow_mut();
+        natives.retain(|registered| registered.name != native.name);
+        natives.push(native);
+    }
+
+    pub fn natives(&self) -> Vec<NativeFunction> {
+        self.natives.borrow().clone()
+    }
+
+    /// Forgets the imported modules, so that they run again when they are imported.
+    pub fn clear(&self) {
+        self.cache.borrow_mut().clear();
+    }
+
+    /// Imports the module at the path, which is relative to the file of the location or to a directory of the search path.
+    /// The first import parses the module and runs it with `run`, which returns its globals.
+    pub fn import<F>(&self, location: &(String, usize, usize), path: &str, run: F) -> Result<Rc<OranModule>, OranError>
+    where
+        F: FnOnce(&[AstNode]) -> Result<ModuleGlobals, OranError>,
+    {
+        let file = self.find(location, path)?;
+        let filename = file.to_string_lossy().into_owned();
+        let canonical = fs::canonicalize(&file).map_err(|e| unable_to_read(location, &filename, e))?;
+        if let Some(module) = self.cache.borrow().get(&canonical).cloned() {
+            return Ok(module);
+        }
+        let cycle = {
+            let loading = self.loading.borrow();
+            loading.iter().position(|(loaded, _)| *loaded == canonical).map(|start| {
+                let mut names: Vec<&str> = loading[start..].iter().map(|(_, name)| name.as_str()).collect();
+                names.push(&filename);
+                names.join(" -> ")
+            })
+        };
+        if let Some(cycle) = cycle {
+            return Err(OranError::located(ErrorKind::Import, location, &format!("The modules import each other: {}", cycle)));
+        }
+        let source = fs::read_to_string(&file).map_err(|e| unable_to_read(location, &filename, e))?;
+        let (ast, _) = parser::parse(&filename, &source)?;
+        self.loading.borrow_mut().push((canonical.clone(), filename.clone()));
+        let globals = run(&ast);
+        self.loading.borrow_mut().pop();
+        let globals = globals?;
+        let name = file.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let module = Rc::new(OranModule {
+            name: OranString::from(name),
+            path: filename,
+            globals,
+        });
+        self.cache.borrow_mut().insert(canonical, module.clone());
+        Ok(module)
+    }
+
+    fn find(&self, location: &(String, usize, usize), path: &str) -> Result<PathBuf, OranError> {
+        let importer_dir = Path::new(&location.0).parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+        let mut dirs = vec![importer_dir];
+        dirs.extend(self.search_path.borrow().iter().cloned());
+        dirs.iter()
+            .map(|dir| dir.join(path))
+            .find(|file| file.is_file())
+            .ok_or_else(|| OranError::located(ErrorKind::Import, location, &format!("The module \"{}\" is not found.", path)))
+    }
+}
+
+fn unable_to_read(location: &(String, usize, usize), filename: &str, e: std::io::Error) -> OranError {
+    OranError::located(ErrorKind::Io, location, &format!("Unable to read {}: {}", filename, e))
+}
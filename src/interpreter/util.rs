@@ -1,45 +1,468 @@
-use std::collections::HashMap;
-use std::process;
-use colored::*;
-use crate::value::{oran_string::OranString, oran_value::OranValue, oran_variable::OranVariable, var_type::{FunctionOrValueType, VarType}};
-
-pub fn is_mutable<'a> (
-    location: (String, usize, usize),
-    scope: usize, 
-    env : &HashMap<(usize, FunctionOrValueType, OranString<'a>), OranValue<'a>>,
+use std::convert::TryFrom;
+use num_traits::Pow;
+use std::rc::Rc;
+use std::cell::RefCell;
+use crate::parser::astnode::{Accessor, CalcOp, LogicalOperatorType};
+use super::builtin;
+use crate::value::oran_string::OranString;
+use crate::value::oran_error::{OranError, ErrorKind};
+use crate::value::{oran_value::{self, OranValue, OranList, OranMap, MapKey, OranStruct, StructDefine, OranEnum, EnumDefine, OranModule, NativeFunction}, oran_variable::{OranVariable, OranVariableValue}, scope::Scope, var_type::{FunctionOrValueType, VarType}};
+
+pub fn is_mutable(
+    location: &(String, usize, usize),
+    scope: &Scope,
     ident: &str,
-    variable_type: &VarType) -> bool {
-
-    let val = env.get(
-        &(
-            scope,
-            FunctionOrValueType::Value,
-            OranString::from(ident)
-        )
-    );
+    variable_type: &VarType) -> Result<(), OranError> {
+
+    if *variable_type != VarType::VariableReAssigned {
+        return Ok(());
+    }
+    match scope.get(FunctionOrValueType::Value, ident) {
+        Some(v) if OranVariable::from(&v).var_type == VarType::Constant => {
+            Err(constant_assignment(location))
+        },
+        Some(_) => Ok(()),
+        None => Err(assignment_without_let(location)),
+    }
+}
+
+pub fn constant_assignment(location: &(String, usize, usize)) -> OranError {
+    OranError::located(ErrorKind::ConstantAssignment, location, "You can't assign value twice to a constant variable.")
+}
+
+/// The error for `xs[0] = 1` or `p.x = 1` when `xs` or `p` is a constant. The accessor is the first one after the variable.
+pub fn constant_element_assignment(location: &(String, usize, usize), accessor: &Accessor) -> OranError {
+    let message = match accessor {
+        Accessor::Index(_) => "You can't change an element of a constant variable.",
+        Accessor::Field(_) => "You can't change a field of a constant variable.",
+    };
+    OranError::located(ErrorKind::ConstantAssignment, location, message)
+}
+
+/// The error for `push(xs, 1)`, `pop(xs)` or `remove(m, "k")` when `xs` or `m` is a constant.
+pub fn constant_element_change(location: &(String, usize, usize)) -> OranError {
+    OranError::located(ErrorKind::ConstantAssignment, location, "You can't change an element of a constant variable.")
+}
+
+pub fn assignment_without_let(location: &(String, usize, usize)) -> OranError {
+    OranError::located(ErrorKind::AssignmentWithoutLet, location, "You can't assign value without \"let\".")
+}
+
+pub fn loop_control_outside_loop(location: &(String, usize, usize)) -> OranError {
+    OranError::located(ErrorKind::LoopControl, location, "\"break\" and \"continue\" can be used only inside of loops.")
+}
+
+pub fn undefined_variable(location: &(String, usize, usize), ident: &str) -> OranError {
+    OranError::located(ErrorKind::UndefinedVariable, location, &format!("The variable \"{}\" is not defined.", ident))
+}
+
+pub fn undefined_struct(location: &(String, usize, usize), name: &str) -> OranError {
+    OranError::located(ErrorKind::UndefinedVariable, location, &format!("The struct \"{}\" is not defined.", name))
+}
+
+pub fn undefined_function(location: &(String, usize, usize), name: &str) -> OranError {
+    OranError::located(ErrorKind::UndefinedFunction, location, &format!("Function \"{}\" is not defined.", name))
+}
+
+pub fn not_a_function(location: &(String, usize, usize), name: &str) -> OranError {
+    OranError::located(ErrorKind::Type, location, &format!("The variable \"{}\" is not a function.", name))
+}
+
+pub fn not_callable(location: &(String, usize, usize), val: &OranValue) -> OranError {
+    OranError::located(ErrorKind::Type, location, &format!("This value is not a function: {}", val))
+}
+
+pub fn missing_argument(location: &(String, usize, usize)) -> OranError {
+    OranError::located(ErrorKind::MissingArgument, location, "Argument is necessary but not supplied.")
+}
+
+/// Takes the value out of a variable, so that native functions get plain values.
+pub fn unwrap_variable(val: OranValue) -> OranValue {
     match val {
-        Some(v) => {
-            if *variable_type == VarType::VariableReAssigned && OranVariable::from(v).var_type == VarType::Constant {
-                println!("{}\n{}\nLine number: {}, column number:{}: You can't assign value twice to a constant variable.",
-                    "Error!".red().bold(),    
-                    location.0,    
-                    location.1,
-                    location.2
-                );
-                process::exit(1);
+        OranValue::Variable(v) => OranValue::from(&v.value),
+        val => val,
+    }
+}
+
+/// Calls a function registered by the host. Its errors happen where it was called.
+pub fn call_native(location: &(String, usize, usize), native: &NativeFunction, args: &[OranValue]) -> Result<OranValue, OranError> {
+    (native.func)(args).map_err(|e| e.at(location))
+}
+
+/// Converts an index value to a position in a list of `len` elements.
+pub fn list_position(location: &(String, usize, usize), index: &OranValue, len: usize) -> Result<usize, OranError> {
+    if let OranValue::Int(int) = unwrap_variable(index.clone()) {
+        return match usize::try_from(int) {
+            Ok(position) if position < len => Ok(position),
+            _ => Err(index_out_of_range(location, len, index)),
+        };
+    }
+    let index = match Result::<f64, OranError>::from(index) {
+        Ok(index) => index,
+        Err(_e) => return Err(OranError::located(ErrorKind::Type, location, &format!("The index of a list must be a number: {}", index))),
+    };
+    if index.fract() != 0.0 || index < 0.0 || index >= len as f64 {
+        return Err(index_out_of_range(location, len, &OranValue::Float(index)));
+    }
+    Ok(index as usize)
+}
+
+fn index_out_of_range(location: &(String, usize, usize), len: usize, index: &OranValue) -> OranError {
+    OranError::located(ErrorKind::Index, location, &format!("The index is out of range: the length is {} but the index is {}.", len, index))
+}
+
+/// Converts a value to a key of a map. Only strings, numbers and booleans can be keys.
+pub fn map_key(location: &(String, usize, usize), key: &OranValue) -> Result<MapKey, OranError> {
+    Option::<MapKey>::from(key)
+        .ok_or_else(|| OranError::located(ErrorKind::Type, location, &format!("This value cannot be used as a key of a map: {}", key)))
+}
+
+pub fn get_index(location: &(String, usize, usize), target: &OranValue, index: &OranValue) -> Result<OranValue, OranError> {
+    if let Some(list) = Option::<OranList>::from(target) {
+        let list = list.borrow();
+        let position = list_position(location, index, list.len())?;
+        return Ok(list[position].clone());
+    }
+    if let Some(map) = Option::<OranMap>::from(target) {
+        let key = map_key(location, index)?;
+        return match map.borrow().get(&key) {
+            Some(val) => Ok(val.clone()),
+            None => Err(OranError::located(ErrorKind::Index, location, &format!("The key {} is not found in the map.", key))),
+        };
+    }
+    Err(not_indexable(location, target))
+}
+
+/// Stores a copy of the value at the index of a list or the key of a map.
+pub fn set_index(location: &(String, usize, usize), target: &OranValue, index: &OranValue, val: OranValue) -> Result<(), OranError> {
+    let val = oran_value::copy_value(&val);
+    if let Some(list) = Option::<OranList>::from(target) {
+        let mut list = list.borrow_mut();
+        let position = list_position(location, index, list.len())?;
+        list[position] = val;
+        return Ok(());
+    }
+    if let Some(map) = Option::<OranMap>::from(target) {
+        let key = map_key(location, index)?;
+        map.borrow_mut().insert(key, val);
+        return Ok(());
+    }
+    Err(not_indexable(location, target))
+}
+
+/// Makes an instance of the struct with the given name. Every declared field must be given.
+pub fn make_struct(location: &(String, usize, usize), name: &str, define: Option<OranValue>, fields: Vec<(String, OranValue)>) -> Result<OranValue, OranError> {
+    let define = struct_define(location, name, define)?;
+    let mut values: Vec<Option<OranValue>> = vec![None; define.fields.len()];
+    for (field, val) in fields {
+        let position = field_position(location, &define, &field)?;
+        values[position] = Some(val);
+    }
+    if let Some(missing) = values.iter().position(Option::is_none) {
+        let message = format!("The field \"{}\" of {} is not given.", define.fields[missing], define.name);
+        return Err(OranError::located(ErrorKind::MissingArgument, location, &message));
+    }
+    Ok(OranValue::Struct(OranStruct {
+        define,
+        fields: Rc::new(RefCell::new(values.into_iter().map(Option::unwrap).collect())),
+    }))
+}
+
+/// Adds a method to the struct with the given name, which is what each function in `impl` does.
+pub fn define_method(location: &(String, usize, usize), name: &str, define: Option<OranValue>, method_name: &str, method: OranValue) -> Result<(), OranError> {
+    let define = struct_define(location, name, define)?;
+    define.methods.borrow_mut().insert(method_name.to_owned(), method);
+    Ok(())
+}
+
+/// Finds the method that `receiver.name()` calls. An instance of a struct has the methods of its `impl` blocks.
+/// `None` means a builtin method of strings, numbers, lists and maps, which `builtin::call_method` runs.
+pub fn find_method(location: &(String, usize, usize), receiver: &OranValue, name: &str) -> Result<Option<OranValue>, OranError> {
+    if let Some(instance) = Option::<OranStruct>::from(receiver) {
+        return match instance.define.methods.borrow().get(name) {
+            Some(method) => Ok(Some(method.clone())),
+            None => Err(OranError::located(ErrorKind::UndefinedFunction, location, &format!("{} has no method \"{}\".", instance.define.name, name))),
+        };
+    }
+    if builtin::is_method(name) {
+        return Ok(None);
+    }
+    Err(OranError::located(ErrorKind::UndefinedFunction, location, &format!("The method \"{}\" is not defined for this value: {}", name, receiver)))
+}
+
+fn struct_define(location: &(String, usize, usize), name: &str, define: Option<OranValue>) -> Result<Rc<StructDefine>, OranError> {
+    match define {
+        Some(define) => Option::<Rc<StructDefine>>::from(&define)
+            .ok_or_else(|| OranError::located(ErrorKind::Type, location, &format!("\"{}\" is not a struct.", name))),
+        None => Err(undefined_struct(location, name)),
+    }
+}
+
+/// The enum with the given name. `found` is the value of the name in the scope,
+/// and `Option` and `Result` are used when the program does not declare the name.
+pub fn enum_define(location: &(String, usize, usize), name: &str, found: Option<OranValue>) -> Result<Rc<EnumDefine>, OranError> {
+    match found {
+        Some(define) => Option::<Rc<EnumDefine>>::from(&define)
+            .ok_or_else(|| OranError::located(ErrorKind::Type, location, &format!("\"{}\" is not an enum.", name))),
+        None => oran_value::prelude_enum(name)
+            .ok_or_else(|| OranError::located(ErrorKind::UndefinedVariable, location, &format!("The enum \"{}\" is not defined.", name))),
+    }
+}
+
+fn variant_position(location: &(String, usize, usize), define: &EnumDefine, variant: &str) -> Result<usize, OranError> {
+    define.variant_position(variant)
+        .ok_or_else(|| OranError::located(ErrorKind::UndefinedVariable, location, &format!("{} has no variant \"{}\".", define.name, variant)))
+}
+
+/// Makes a value of the variant, like `Shape::Circle(1)`. Every value the variant declares must be given.
+pub fn make_variant(location: &(String, usize, usize), define: Rc<EnumDefine>, variant: &str, values: Vec<OranValue>) -> Result<OranValue, OranError> {
+    let position = variant_position(location, &define, variant)?;
+    let expected = define.variants[position].1.len();
+    if values.len() < expected {
+        let message = format!("The number of values of {}::{} is {}, but {} are given.", define.name, variant, expected, values.len());
+        return Err(OranError::located(ErrorKind::MissingArgument, location, &message));
+    }
+    if values.len() > expected {
+        let message = format!("The number of values of {}::{} is {}, but {} are given.", define.name, variant, expected, values.len());
+        return Err(OranError::located(ErrorKind::Type, location, &message));
+    }
+    Ok(OranValue::Enum(OranEnum { define, variant: position, values: Rc::new(values) }))
+}
+
+/// Whether the value is of the variant, with the values of the variant when it is.
+/// `bindings` is the number of names in the pattern, which must be the number of the values,
+/// or `None` when the pattern has no parentheses.
+pub fn match_variant(location: &(String, usize, usize), val: &OranValue, define: &Rc<EnumDefine>, variant: &str, bindings: Option<usize>) -> Result<Option<Rc<Vec<OranValue>>>, OranError> {
+    let position = variant_position(location, define, variant)?;
+    let expected = define.variants[position].1.len();
+    if let Some(bindings) = bindings {
+        if bindings != expected {
+            let message = format!("The number of values of {}::{} is {}, but the pattern has {}.", define.name, variant, expected, bindings);
+            return Err(OranError::located(ErrorKind::Type, location, &message));
+        }
+    }
+    match Option::<OranEnum>::from(val) {
+        Some(val) if Rc::ptr_eq(&val.define, define) && val.variant == position => Ok(Some(val.values)),
+        _ => Ok(None),
+    }
+}
+
+/// The value that `catch` gets for an error of the language, like an undefined variable.
+pub fn caught_error(error: &OranError) -> OranValue {
+    error_value(&error.kind.to_string(), &error.message, OranValue::Null, error.location.as_ref())
+}
+
+/// The value that `throw val` raises. A caught error is thrown again as it is, so it keeps where it happened.
+pub fn thrown_error(location: &(String, usize, usize), val: OranValue) -> OranValue {
+    if let Some(instance) = Option::<OranStruct>::from(&val) {
+        if Rc::ptr_eq(&instance.define, &oran_value::error_define()) {
+            return OranValue::Struct(instance);
+        }
+    }
+    let message = String::from(&val);
+    error_value(&ErrorKind::Thrown.to_string(), &message, val, Some(location))
+}
+
+fn error_value(kind: &str, message: &str, val: OranValue, location: Option<&(String, usize, usize)>) -> OranValue {
+    let (file, line, column) = match location {
+        Some((file, line, column)) => (OranValue::Str(file.into()), OranValue::Int(*line as i64), OranValue::Int(*column as i64)),
+        None => (OranValue::Null, OranValue::Null, OranValue::Null),
+    };
+    OranValue::Struct(OranStruct {
+        define: oran_value::error_define(),
+        fields: Rc::new(RefCell::new(vec![OranValue::Str(kind.into()), OranValue::Str(message.into()), val, file, line, column])),
+    })
+}
+
+/// The error that ends the program when a thrown value is not caught.
+/// An error of the language that was caught and thrown again has its own kind again.
+pub fn uncaught_error(val: &OranValue) -> OranError {
+    let instance = match Option::<OranStruct>::from(val) {
+        Some(instance) => instance,
+        None => return OranError::new(ErrorKind::Thrown, &String::from(val)),
+    };
+    let fields = instance.fields.borrow();
+    let kind = ErrorKind::from_name(&String::from(&fields[0])).unwrap_or(ErrorKind::Thrown);
+    let location = match (&fields[3], &fields[4], &fields[5]) {
+        (OranValue::Str(file), OranValue::Int(line), OranValue::Int(column)) => {
+            Some((file.val_str.as_ref().to_string(), *line as usize, *column as usize))
+        }
+        _ => None,
+    };
+    OranError { kind, message: String::from(&fields[1]), location }
+}
+
+/// The function that `module.name()` calls, or `None` when the receiver is not a module.
+/// Unlike a method, it does not get the module as its first argument.
+pub fn module_function(location: &(String, usize, usize), receiver: &OranValue, name: &str) -> Result<Option<OranValue>, OranError> {
+    let module = match Option::<Rc<OranModule>>::from(receiver) {
+        Some(module) => module,
+        None => return Ok(None),
+    };
+    match module.member(FunctionOrValueType::Function, name).or_else(|| module.member(FunctionOrValueType::Value, name)) {
+        Some(func) => Ok(Some(unwrap_variable(func))),
+        None => Err(OranError::located(ErrorKind::UndefinedFunction, location, &format!("The module {} has no function \"{}\".", module.name, name))),
+    }
+}
+
+pub fn get_field(location: &(String, usize, usize), target: &OranValue, field: &str) -> Result<OranValue, OranError> {
+    if let Some(module) = Option::<Rc<OranModule>>::from(target) {
+        return match module.member(FunctionOrValueType::Value, field).or_else(|| module.member(FunctionOrValueType::Function, field)) {
+            Some(val) => Ok(unwrap_variable(val)),
+            None => Err(OranError::located(ErrorKind::UndefinedVariable, location, &format!("The module {} has no variable \"{}\".", module.name, field))),
+        };
+    }
+    let instance = struct_of(location, target)?;
+    let position = field_position(location, &instance.define, field)?;
+    let val = instance.fields.borrow()[position].clone();
+    Ok(val)
+}
+
+/// Stores a copy of the value in a field of a struct instance.
+pub fn set_field(location: &(String, usize, usize), target: &OranValue, field: &str, val: OranValue) -> Result<(), OranError> {
+    let val = oran_value::copy_value(&val);
+    let instance = struct_of(location, target)?;
+    let position = field_position(location, &instance.define, field)?;
+    instance.fields.borrow_mut()[position] = val;
+    Ok(())
+}
+
+fn struct_of(location: &(String, usize, usize), target: &OranValue) -> Result<OranStruct, OranError> {
+    Option::<OranStruct>::from(target)
+        .ok_or_else(|| OranError::located(ErrorKind::Type, location, &format!("This value has no fields: {}", target)))
+}
+
+fn field_position(location: &(String, usize, usize), define: &StructDefine, field: &str) -> Result<usize, OranError> {
+    define.fields.iter().position(|declared| declared == field)
+        .ok_or_else(|| OranError::located(ErrorKind::Type, location, &format!("{} has no field \"{}\".", define.name, field)))
+}
+
+fn not_indexable(location: &(String, usize, usize), target: &OranValue) -> OranError {
+    OranError::located(ErrorKind::Type, location, &format!("This value cannot be indexed: {}", target))
+}
+
+pub fn calc(location: &(String, usize, usize), verb: CalcOp, lhs: OranValue, rhs: OranValue) -> Result<OranValue, OranError> {
+    let result = match verb {
+        CalcOp::Plus => { lhs + rhs }
+        CalcOp::Minus => { lhs - rhs }
+        CalcOp::Times => { lhs * rhs }
+        CalcOp::Divide => { lhs / rhs }
+        CalcOp::Modulus => { lhs % rhs }
+        CalcOp::Power => { Pow::pow(lhs, rhs) }
+        CalcOp::BitAnd => { lhs & rhs }
+        CalcOp::BitOr => { lhs | rhs }
+        CalcOp::BitXor => { lhs ^ rhs }
+        // `<<` concatenated strings before `++` was added, and still does when either side is a string.
+        CalcOp::ShiftLeft if is_str(&lhs) || is_str(&rhs) => { Ok(concat(lhs, rhs)) }
+        CalcOp::ShiftLeft => { lhs << rhs }
+        CalcOp::ShiftRight => { lhs >> rhs }
+        CalcOp::Concat => { Ok(concat(lhs, rhs)) }
+    };
+    result.map_err(|e| e.at(location))
+}
+
+pub fn negate(location: &(String, usize, usize), val: OranValue) -> Result<OranValue, OranError> {
+    (-val).map_err(|e| e.at(location))
+}
+
+/// `+x` gives the number `x` is, so it fails for anything but numbers and strings of numbers.
+pub fn unary_plus(location: &(String, usize, usize), val: OranValue) -> Result<OranValue, OranError> {
+    oran_value::to_number(&val).map_err(|e| e.at(location))
+}
+
+/// Inverts the bits of an integer, which is `~`.
+pub fn bit_not(location: &(String, usize, usize), val: OranValue) -> Result<OranValue, OranError> {
+    (!val).map_err(|e| e.at(location))
+}
+
+fn is_str(val: &OranValue) -> bool {
+    match val {
+        OranValue::Str(_) => true,
+        OranValue::Variable(v) => matches!(v.value, OranVariableValue::Str(_)),
+        _ => false,
+    }
+}
+
+fn concat(lhs: OranValue, rhs: OranValue) -> OranValue {
+    let mut text = String::from(lhs);
+    text.push_str(&String::from(rhs));
+    OranValue::Str(OranString::from(text))
+}
+
+/// Compares two values. Anything but `==` and `!=` needs numbers on both sides.
+pub fn compare(location: &(String, usize, usize), e: &OranValue, c: LogicalOperatorType, o: &OranValue) -> Result<OranValue, OranError> {
+    let is_num_e = Result::<f64, OranError>::from(e).is_ok();
+    let is_num_o = Result::<f64, OranError>::from(o).is_ok();
+
+    if !is_num_e || !is_num_o {
+        match c {
+            LogicalOperatorType::Equal => {
+                Ok(OranValue::Boolean(e.to_string() == o.to_string()))
+            },
+            LogicalOperatorType::NotEqual => {
+                Ok(OranValue::Boolean(e.to_string() != o.to_string()))
+            },
+            _ => Err(OranError::located(ErrorKind::Type, location, &format!("One of these are not number: {}, {}", e, o))),
+        }
+    } else {
+        let result = match c {
+            LogicalOperatorType::Equal => e == o,
+            LogicalOperatorType::NotEqual => e != o,
+            LogicalOperatorType::BiggerThan => e > o,
+            LogicalOperatorType::SmallerThan => e < o,
+            LogicalOperatorType::EbiggerThan => e >= o,
+            LogicalOperatorType::EsmallerThan => e <= o,
+        };
+        Ok(OranValue::Boolean(result))
+    }
+}
+
+/// Whether a value is in the range of a `match` pattern. Values that are not numbers are in no range.
+pub fn in_range(location: &(String, usize, usize), val: &OranValue, first: &OranValue, last: &OranValue, is_inclusive: bool) -> Result<bool, OranError> {
+    if Result::<f64, OranError>::from(val).is_err() {
+        return Ok(false);
+    }
+    let end = if is_inclusive { LogicalOperatorType::EsmallerThan } else { LogicalOperatorType::SmallerThan };
+    Ok(bool::from(compare(location, first, LogicalOperatorType::EsmallerThan, val)?)
+        && bool::from(compare(location, val, end, last)?))
+}
+
+/// The values a `for` loop binds on each iteration.
+pub type LoopItems = Vec<(OranValue, Option<OranValue>)>;
+
+/// Takes a snapshot of what `for` iterates over, so that the body can modify the list or the map.
+/// A single loop variable gets the elements of a list or the keys of a map.
+/// With two variables, the first one gets the index or the key and the second one the element.
+/// The variables of `for mut` get copies of the elements, which they can change without changing the list or the map.
+pub fn loop_items(location: &(String, usize, usize), iterable: &OranValue, with_second: bool, mutable: bool) -> Result<LoopItems, OranError> {
+    let element = |val: &OranValue| if mutable { oran_value::copy_value(val) } else { val.clone() };
+    if let Some(list) = Option::<OranList>::from(iterable) {
+        return Ok(list.borrow().iter().enumerate().map(|(i, val)| {
+            if with_second {
+                (OranValue::Int(i as i64), Some(element(val)))
+            } else {
+                (element(val), None)
             }
-        },
-        None => {
-            if *variable_type == VarType::VariableReAssigned {
-                println!("{}\n{}\nLine number: {}, column number:{}: You can't assign value without \"let\".",
-                    "Error!".red().bold(),    
-                    location.0,    
-                    location.1,
-                    location.2
-                );
-                process::exit(1);
+        }).collect());
+    }
+    if let Some(map) = Option::<OranMap>::from(iterable) {
+        return Ok(map.borrow().iter().map(|(key, val)| {
+            let key = OranValue::from(key);
+            if with_second {
+                (key, Some(element(val)))
+            } else {
+                (key, None)
             }
-        }
+        }).collect());
+    }
+    Err(OranError::located(ErrorKind::Type, location, &format!("This value cannot be iterated: {}", iterable)))
+}
+
+/// Converts a bound of a range to an integer. A float is rounded.
+pub fn range_bound(location: &(String, usize, usize), val: &OranValue) -> Result<i64, OranError> {
+    if let OranValue::Int(int) = unwrap_variable(val.clone()) {
+        return Ok(int);
     }
-    true
+    let bound = Result::<f64, OranError>::from(val).map_err(|e| e.at(location))?;
+    Ok(bound.round() as i64)
 }
@@ -0,0 +1,17 @@
+extern crate pest;
+#[macro_use]
+extern crate pest_derive;
+extern crate num_traits;
+
+mod interpreter;
+mod parser;
+mod vm;
+mod engine;
+pub mod value;
+
+pub use engine::{Engine, Backend};
+pub use value::oran_value::OranValue;
+pub use value::oran_error::{OranError, ErrorKind};
+pub use value::oran_warning::OranWarning;
+pub use num_bigint::BigInt;
+pub use rust_decimal::Decimal;
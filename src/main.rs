@@ -1,19 +1,13 @@
-extern crate pest;
-#[macro_use]
-extern crate pest_derive;
 extern crate clap;
-extern crate num_traits;
 
-mod interpreter;
-mod parser;
-mod value;
-use value::scope::MAIN_FUNCTION;
+mod repl;
+use oran::{Engine, Backend, OranError, OranWarning, ErrorKind};
+use colored::*;
 use clap::{Arg, App};
-use std::fs;
+use std::process;
 use std::time::Instant;
 
 fn main() {
-    use std::collections::HashMap;
     let matches = App::new("oran")
     .version("0.1.0")
     .author("shu nakanishi <shu845@gmail.com>")
@@ -22,8 +16,8 @@ fn main() {
          .short("f")
          .long("file")
          .value_name("FILE")
-         .help("Sets a oran file to parse")
-         .required(true)
+         .help("Sets a oran file to parse. Starts the REPL if omitted")
+         .required(false)
          .takes_value(true))
     .arg(Arg::with_name("time")
          .short("t")
@@ -32,16 +26,47 @@ fn main() {
          .help("Print the execution time")
          .required(false)
          .takes_value(false))
+    .arg(Arg::with_name("include")
+         .short("I")
+         .long("include")
+         .value_name("DIR")
+         .help("Adds a directory where modules are searched for by import")
+         .required(false)
+         .multiple(true)
+         .number_of_values(1)
+         .takes_value(true))
+    .arg(Arg::with_name("backend")
+         .short("b")
+         .long("backend")
+         .value_name("BACKEND")
+         .help("Runs the program with the tree-walking interpreter or with the bytecode VM")
+         .possible_values(&["tree", "vm"])
+         .default_value("tree")
+         .takes_value(true))
     .get_matches();
 
+    let backend = match matches.value_of("backend") {
+        Some("vm") => Backend::Vm,
+        _ => Backend::Tree,
+    };
+    let mut engine = Engine::with_backend(backend);
+    for dir in matches.values_of("include").into_iter().flatten() {
+        engine.add_search_path(dir);
+    }
+    let file = match matches.value_of("file") {
+        Some(file) => file,
+        None => {
+            repl::start(engine);
+            return;
+        }
+    };
     let start = Instant::now();
-    let file = matches.value_of("file");
-    // TODO: show error message without panicking
-    let string_in_file = fs::read_to_string(&file.unwrap()).expect("Unable to read file");
-    //println!("---{:?}---", ast);
-    let mut oran_env = HashMap::new();
-    for reduced_expr in &parser::parse(&file.unwrap(),&string_in_file).unwrap_or_else(|e| panic!("{}", e)) {
-        interpreter::interp_expr(MAIN_FUNCTION, &mut oran_env, reduced_expr);
+    let result = engine.eval_file(file);
+    print_warnings(engine.warnings());
+    if let Err(e) = result {
+        print_error(&e);
+        // Syntax errors exit with 2, runtime errors with 1.
+        process::exit(if e.kind == ErrorKind::Syntax { 2 } else { 1 });
     }
     if matches.is_present("time") {
         let execution_time = Instant::now().duration_since(start);
@@ -49,3 +74,19 @@ fn main() {
     }
 
 }
+
+/// Prints warnings to stderr, so that stdout has only what the program prints.
+pub fn print_warnings(warnings: &[OranWarning]) {
+    for warning in warnings {
+        eprintln!("{}\n{}", "Warning!".yellow().bold(), warning);
+    }
+}
+
+/// Prints an error. Syntax errors already look like pest's messages.
+pub fn print_error(e: &OranError) {
+    if e.kind == ErrorKind::Syntax {
+        eprintln!("{}", e);
+    } else {
+        eprintln!("{}\n{}", "Error!".red().bold(), e);
+    }
+}
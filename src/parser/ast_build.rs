@@ -1,38 +1,57 @@
+use pest::Span;
 use pest::iterators::Pairs;
-use pest::error::{Error, ErrorVariant};
-use std::process;
+use pest::error::{Error, ErrorVariant, LineColLocation};
 use std::collections::LinkedList;
+use std::rc::Rc;
+use rust_decimal::Decimal;
+use num_bigint::BigInt;
 use crate::value::var_type::VarType;
+use crate::interpreter::builtin;
+use crate::value::oran_error::{OranError, ErrorKind};
 use super::Rule;
-use super::astnode::AstNode;
+use super::astnode::{AstNode, Accessor, CalcOp, MatchArm, Pattern, VariantPattern};
 use super::function;
 use super::calculation;
+use super::string;
 
 pub fn get_pairs(filename: String, result: Result<Pairs<'_, Rule>, Error<Rule>>)
-    -> Option<Pairs<'_, Rule>> {
+    -> Result<Pairs<'_, Rule>, OranError> {
     match result {
         Ok(pairs) => {
-            return Some(pairs);
+            Ok(pairs)
         },
         Err(e) => {
             let e = e.renamed_rules(|rule| {
                 match *rule {
                     Rule::ident => "variable".to_owned(),
-                    Rule::escape_char 
-                    | Rule::escaped_escape_char 
-                    | Rule::escaped_quote
-                    | Rule::double_quote_char => "alphamumeric values".to_owned(),
+                    Rule::escape_sequence => "escape sequence".to_owned(),
+                    Rule::interpolation => "${...}".to_owned(),
+                    Rule::single_quote_char
+                    | Rule::double_quote_char
+                    | Rule::triple_quote_char => "alphamumeric values".to_owned(),
                     Rule::single_quote_string
                     | Rule::double_quote_string
-                    | Rule::string
-                    | Rule::concatenated_string => "string".to_owned(),
+                    | Rule::triple_quote_string
+                    | Rule::raw_string
+                    | Rule::string => "string".to_owned(),
                     Rule::two_equals => "==".to_owned(),
+                    Rule::not_equal => "!=".to_owned(),
                     Rule::bigger_than => ">".to_owned(),
                     Rule::smaller_than => "<".to_owned(),
-                    Rule::e_bigger_than => "=>".to_owned(),
-                    Rule::e_smaller_than => "=<".to_owned(),
+                    Rule::e_bigger_than => ">=".to_owned(),
+                    Rule::e_smaller_than => "<=".to_owned(),
+                    Rule::op_not => "!".to_owned(),
                     Rule::op_or => "||".to_owned(),
                     Rule::op_and => "&&".to_owned(),
+                    Rule::number
+                    | Rule::integer
+                    | Rule::radix_integer
+                    | Rule::decimal
+                    | Rule::big_integer
+                    | Rule::exact_decimal => "number".to_owned(),
+                    Rule::val_bool => "boolean".to_owned(),
+                    Rule::comparison => "comparison".to_owned(),
+                    Rule::condition => "condition".to_owned(),
                     Rule::bool_true => "true".to_owned(),
                     Rule::bool_false => "false".to_owned(),
                     Rule::plus => "+".to_owned(),
@@ -41,12 +60,33 @@ pub fn get_pairs(filename: String, result: Result<Pairs<'_, Rule>, Error<Rule>>)
                     Rule::divide => "/".to_owned(),
                     Rule::modulus => "%".to_owned(),
                     Rule::power => "^".to_owned(),
+                    Rule::unary_minus => "-".to_owned(),
+                    Rule::unary_plus => "+".to_owned(),
+                    Rule::unary => "variable/value".to_owned(),
+                    Rule::power_term => "variable/value".to_owned(),
+                    Rule::bit_and => "&".to_owned(),
+                    Rule::bit_or => "|".to_owned(),
+                    Rule::bit_xor => "xor".to_owned(),
+                    Rule::op_bit_not => "~".to_owned(),
+                    Rule::shift_left => "<<".to_owned(),
+                    Rule::shift_right => ">>".to_owned(),
+                    Rule::concat => "++".to_owned(),
                     Rule::assgmt_expr
-                    | Rule::re_assgmt_expr => "expression".to_owned(),
+                    | Rule::re_assgmt_expr
+                    | Rule::compound_assgmt_expr => "expression".to_owned(),
+                    Rule::plus_assign => "+=".to_owned(),
+                    Rule::minus_assign => "-=".to_owned(),
+                    Rule::times_assign => "*=".to_owned(),
+                    Rule::divide_assign => "/=".to_owned(),
+                    Rule::modulus_assign => "%=".to_owned(),
+                    Rule::power_assign => "^=".to_owned(),
+                    Rule::shift_left_assign => "<<=".to_owned(),
                     Rule::calc_term => "variable/value".to_owned(),
                     Rule::function_name => "funcation name".to_owned(),
-                    Rule::function_call => "function call".to_owned(),
+                    Rule::function_call
+                    | Rule::call => "function call".to_owned(),
                     Rule::function_define => "definition of function".to_owned(),
+                    Rule::anonymous_function => "anonymous function".to_owned(),
                     Rule::arguments_for_call
                     | Rule::argument
                     | Rule::arguments_for_define => "arguments of function".to_owned(),
@@ -57,12 +97,53 @@ pub fn get_pairs(filename: String, result: Result<Pairs<'_, Rule>, Error<Rule>>)
                     Rule::op_for => "for".to_owned(),
                     Rule::op_in => "in".to_owned(),
                     Rule::for_var_mut => "mut".to_owned(),
+                    Rule::for_second_var => "variable".to_owned(),
                     Rule::op_if => "if".to_owned(),
                     Rule::op_else => "else".to_owned(),
                     Rule::op_else_if => "else if".to_owned(),
                     Rule::if_expr => "expression for if statement".to_owned(),
                     Rule::else_if_expr => "expression for else-if statement".to_owned(),
                     Rule::else_expr => "expression for else statement".to_owned(),
+                    Rule::list => "list".to_owned(),
+                    Rule::index => "index of the list".to_owned(),
+                    Rule::indexed => "element of the list".to_owned(),
+                    Rule::index_assgmt_expr => "expression".to_owned(),
+                    Rule::map => "map".to_owned(),
+                    Rule::map_entry => "entry of the map".to_owned(),
+                    Rule::struct_name => "struct name".to_owned(),
+                    Rule::field_name => "field name".to_owned(),
+                    Rule::struct_define => "definition of struct".to_owned(),
+                    Rule::struct_literal => "struct".to_owned(),
+                    Rule::field_init => "field of the struct".to_owned(),
+                    Rule::field => "field".to_owned(),
+                    Rule::impl_block => "methods of struct".to_owned(),
+                    Rule::method_call => "method call".to_owned(),
+                    Rule::enum_define => "definition of enum".to_owned(),
+                    Rule::variant_define => "variant of the enum".to_owned(),
+                    Rule::variant_name => "variant name".to_owned(),
+                    Rule::variant
+                    | Rule::variant_path
+                    | Rule::prelude_variant => "variant".to_owned(),
+                    Rule::variant_pattern
+                    | Rule::variant_bindings => "pattern".to_owned(),
+                    Rule::if_let => "if let".to_owned(),
+                    Rule::import_stmt => "import".to_owned(),
+                    Rule::throw_stmt => "throw".to_owned(),
+                    Rule::try_expr => "expression for try statement".to_owned(),
+                    Rule::try_block => "block of try".to_owned(),
+                    Rule::catch_clause => "catch".to_owned(),
+                    Rule::finally_clause => "finally".to_owned(),
+                    Rule::module_alias => "as".to_owned(),
+                    Rule::op_while => "while".to_owned(),
+                    Rule::match_expr => "expression for match".to_owned(),
+                    Rule::match_arm => "arm of the match".to_owned(),
+                    Rule::match_patterns
+                    | Rule::pattern_range
+                    | Rule::wildcard => "pattern".to_owned(),
+                    Rule::match_block => "block of the arm".to_owned(),
+                    Rule::while_expr => "expression for while statement".to_owned(),
+                    Rule::op_break => "break".to_owned(),
+                    Rule::op_continue => "continue".to_owned(),
                     Rule::op_return => "return".to_owned(),
                     Rule::fn_return => "return {{function}}".to_owned(),
                     Rule::end_mark => "semicolon".to_owned(),
@@ -73,52 +154,62 @@ pub fn get_pairs(filename: String, result: Result<Pairs<'_, Rule>, Error<Rule>>)
                     }
                 }
             });
-            println!("{}{}", filename, e);
-            return None;
+            Err(syntax_error(&filename, e))
         },
     }
 }
 
+/// Converts a pest error to an `OranError` located where pest found the problem.
+pub fn syntax_error(filename: &str, error: Error<Rule>) -> OranError {
+    let (line, col) = match error.line_col {
+        LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+    };
+    OranError {
+        kind: ErrorKind::Syntax,
+        message: error.to_string(),
+        location: Some((filename.to_owned(), line, col)),
+    }
+}
+
+pub fn custom_error(filename: &str, message: String, span: Span) -> OranError {
+    syntax_error(filename, Error::new_from_span(ErrorVariant::CustomError { message }, span))
+}
+
+/// The location of the start of a pair, in the file of the given location.
+pub fn pair_location(location: &(String, usize, usize), pair: &pest::iterators::Pair<Rule>) -> (String, usize, usize) {
+    let (line, col) = pair.as_span().start_pos().line_col();
+    (location.0.clone(), line, col)
+}
+
 pub fn build_ast_from_expr(
         location:(String, usize, usize),
         pair: pest::iterators::Pair<Rule>
-    ) -> AstNode {
-    let location = location.clone();
-    match pair.as_rule() {
-        Rule::expr => build_ast_from_expr(location, pair.into_inner().next().unwrap()),
-        Rule::calc_term => {
-            calculation::into_calc_expression(location, pair)
+    ) -> Result<AstNode, OranError> {
+    let location = pair_location(&location, &pair);
+    let pair = operand_of(pair);
+    let node = match pair.as_rule() {
+        Rule::expr => build_ast_from_expr(location, pair.into_inner().next().unwrap())?,
+        Rule::calc_term | Rule::power_term => {
+            calculation::into_calc_expression(location, pair)?
+        },
+        Rule::comparison | Rule::condition => {
+            calculation::into_logical_expression(location, pair)?
+        },
+        Rule::not_expr => {
+            let operand = pair.into_inner().nth(1).unwrap();
+            AstNode::Not(location.clone(), Box::new(build_ast_from_expr(location, operand)?))
+        },
+        Rule::unary => build_unary(location, pair)?,
+        Rule::bit_not_expr => {
+            let operand = pair.into_inner().nth(1).unwrap();
+            AstNode::BitNot(location.clone(), Box::new(build_ast_from_expr(location, operand)?))
         },
         Rule::ident => {
             let str = &pair.as_str();
             AstNode::Ident(location, String::from(&str[..]))
         },
-        Rule::string => {
-            let mut text = "".to_owned();
-            let pairs = pair.into_inner();
-            for top_pair in pairs {
-                let pairs = top_pair.into_inner();
-                for pair in pairs {
-                    match pair.as_rule() {
-                        Rule::escaped_escape_char => {
-                            text.push_str(&String::from("\\"));
-                        }
-                        Rule::escaped_quote => {
-                            text.push_str(&String::from(pair.as_str().replace("\\", "")));
-                        }
-                        Rule::double_quote_char | Rule::single_quote_char => { 
-                            text.push_str(&String::from(pair.as_str()));
-                        }
-                        _ => {}
-                    }
-                }
-            }
-            AstNode::Str(location, text)
-        },
-        Rule::number | Rule::integer => {
-            let num = pair.as_str().parse::<f64>().unwrap_or_else(|e| panic!("{}", e));
-            AstNode::Number(location, f64::from(num))
-        },
+        Rule::string => string::build_string(location, pair)?,
+        Rule::number | Rule::integer | Rule::radix_integer | Rule::decimal | Rule::big_integer | Rule::exact_decimal => build_number(location, pair)?,
         Rule::val_bool => {
             match pair.into_inner().next().unwrap().as_rule() {
                 Rule::bool_true => AstNode::Bool(location, true),
@@ -126,9 +217,72 @@ pub fn build_ast_from_expr(
                 _ => unreachable!()
             }
         }
-        Rule::concatenated_string => {
-            let strs: Vec<AstNode> = pair.into_inner().map(|v| build_ast_from_expr(location.clone(), v)).collect();
-            AstNode::Strs(location, strs)
+        Rule::list => {
+            let items: Vec<AstNode> = pair.into_inner().map(|v| build_ast_from_expr(location.clone(), v)).collect::<Result<_, _>>()?;
+            AstNode::List(location, items)
+        },
+        Rule::map => {
+            let mut entries: Vec<(AstNode, AstNode)> = Vec::new();
+            for entry in pair.into_inner() {
+                let mut entry = entry.into_inner();
+                let key = build_ast_from_expr(location.clone(), entry.next().unwrap())?;
+                let val = build_ast_from_expr(location.clone(), entry.next().unwrap())?;
+                entries.push((key, val));
+            }
+            AstNode::Map(location, entries)
+        },
+        Rule::indexed => {
+            let mut pairs = pair.into_inner();
+            let mut target = build_ast_from_expr(location.clone(), pairs.next().unwrap())?;
+            for accessor in pairs {
+                let accessor_location = pair_location(&location, &accessor);
+                if accessor.as_rule() == Rule::method_call {
+                    let mut method = accessor.into_inner();
+                    let name = method.next().unwrap().as_str().to_owned();
+                    let args = function::parse_arguments(location.clone(), method.next().unwrap())?;
+                    target = AstNode::MethodCall(accessor_location, Box::new(target), name, args);
+                    continue;
+                }
+                if accessor.as_rule() == Rule::call {
+                    let args = function::parse_arguments(location.clone(), accessor.into_inner().next().unwrap())?;
+                    target = AstNode::Call(accessor_location, Box::new(target), args);
+                    continue;
+                }
+                target = match build_accessor(location.clone(), accessor)? {
+                    Accessor::Index(index) => AstNode::Index(location.clone(), Box::new(target), Box::new(index)),
+                    Accessor::Field(name) => AstNode::Field(accessor_location, Box::new(target), name),
+                };
+            }
+            target
+        },
+        Rule::struct_define => {
+            let mut pairs = pair.into_inner();
+            let name = pairs.next().unwrap().as_str().to_owned();
+            let mut fields: Vec<String> = Vec::new();
+            for field in pairs {
+                if fields.iter().any(|defined| defined == field.as_str()) {
+                    let message = format!("The field \"{}\" is declared twice.", field.as_str());
+                    return Err(custom_error(&location.0, message, field.as_span()));
+                }
+                fields.push(field.as_str().to_owned());
+            }
+            AstNode::StructDefine(location, name, fields)
+        },
+        Rule::struct_literal => {
+            let mut pairs = pair.into_inner();
+            let name = pairs.next().unwrap().as_str().to_owned();
+            let mut fields: Vec<(String, AstNode)> = Vec::new();
+            for field_init in pairs {
+                let mut field_init = field_init.into_inner();
+                let field = field_init.next().unwrap();
+                if fields.iter().any(|(given, _)| given == field.as_str()) {
+                    let message = format!("The field \"{}\" is given twice.", field.as_str());
+                    return Err(custom_error(&location.0, message, field.as_span()));
+                }
+                let val = build_ast_from_expr(location.clone(), field_init.next().unwrap())?;
+                fields.push((field.as_str().to_owned(), val));
+            }
+            AstNode::StructLiteral(location, name, fields)
         },
         Rule::assgmt_expr => {
             let mut pair = pair.into_inner();
@@ -138,20 +292,13 @@ pub fn build_ast_from_expr(
                 Rule::var_mut => VarType::VariableFirstAssigned,
                 _ => {
                     let mut message = "unknown variable type: ".to_owned();
-                    message.push_str(&var_prefix.as_str());
-                    let error: Error<Rule> = Error::new_from_span(
-                        ErrorVariant::CustomError{
-                            message: message
-                        },
-                        var_prefix.as_span()
-                    );
-                    println!("{}{}", location.0, error);
-                    process::exit(1);
+                    message.push_str(var_prefix.as_str());
+                    return Err(custom_error(&location.0, message, var_prefix.as_span()));
                 }
             };
             let ident = pair.next().unwrap();
             let expr = pair.next().unwrap();
-            let expr = build_ast_from_expr(location.clone(), expr);
+            let expr = build_ast_from_expr(location.clone(), expr)?;
             AstNode::Assign (
                 location,
                 var_type,
@@ -159,11 +306,30 @@ pub fn build_ast_from_expr(
                 Box::new(expr),
             )
         }
+        Rule::index_assgmt_expr => {
+            let mut pair = pair.into_inner();
+            let ident = pair.next().unwrap();
+            let mut accessors: Vec<Accessor> = Vec::new();
+            let mut expr = None;
+            for inner_pair in pair {
+                match inner_pair.as_rule() {
+                    Rule::index | Rule::field => accessors.push(build_accessor(location.clone(), inner_pair)?),
+                    _ => expr = Some(build_ast_from_expr(location.clone(), inner_pair)?),
+                }
+            }
+            AstNode::IndexAssign (
+                location,
+                String::from(ident.as_str()),
+                accessors,
+                None,
+                Box::new(expr.unwrap()),
+            )
+        },
         Rule::re_assgmt_expr => {
             let mut pair = pair.into_inner();
             let ident = pair.next().unwrap();
             let expr = pair.next().unwrap();
-            let expr = build_ast_from_expr(location.clone(), expr);
+            let expr = build_ast_from_expr(location.clone(), expr)?;
             AstNode::Assign (
                 location,
                 VarType::VariableReAssigned,
@@ -171,93 +337,135 @@ pub fn build_ast_from_expr(
                 Box::new(expr),
             )
         },
+        Rule::compound_assgmt_expr => {
+            // `x += 1` is built as `x = x + 1`, so it is checked like any other re-assignment.
+            let mut pair = pair.into_inner().peekable();
+            let ident = pair.next().unwrap();
+            let mut accessors: Vec<Accessor> = Vec::new();
+            while let Some(accessor) = pair.next_if(|inner_pair| matches!(inner_pair.as_rule(), Rule::index | Rule::field)) {
+                accessors.push(build_accessor(location.clone(), accessor)?);
+            }
+            let op = pair.next().unwrap();
+            let verb = match op.as_rule() {
+                Rule::plus_assign => CalcOp::Plus,
+                Rule::minus_assign => CalcOp::Minus,
+                Rule::times_assign => CalcOp::Times,
+                Rule::divide_assign => CalcOp::Divide,
+                Rule::modulus_assign => CalcOp::Modulus,
+                Rule::power_assign => CalcOp::Power,
+                Rule::shift_left_assign => CalcOp::ShiftLeft,
+                _ => unreachable!(),
+            };
+            let expr = build_ast_from_expr(location.clone(), pair.next().unwrap())?;
+            if !accessors.is_empty() {
+                return Ok(AstNode::IndexAssign (
+                    location.clone(),
+                    String::from(ident.as_str()),
+                    accessors,
+                    Some((pair_location(&location, &op), verb)),
+                    Box::new(expr),
+                ));
+            }
+            let current = AstNode::Ident(pair_location(&location, &ident), String::from(ident.as_str()));
+            AstNode::Assign (
+                location.clone(),
+                VarType::VariableReAssigned,
+                String::from(ident.as_str()),
+                Box::new(AstNode::calculation(pair_location(&location, &op), verb, current, expr)),
+            )
+        },
         Rule::function_call => {
             let mut pair = pair.into_inner();
             let function_name = pair.next().unwrap();
             let function_args = pair.next();
             match function_args {
                 None => {
-                    function::function_call(location, function_name, vec![AstNode::Null])
+                    function::function_call(location, function_name, vec![])
                 },
                 _ => {
                     let expr = function_args.unwrap();
-                    let args: Vec<AstNode> = expr.into_inner().map(|v| build_ast_from_expr(location.clone(), v)).collect();
+                    let args: Vec<AstNode> = expr.into_inner().map(|v| build_ast_from_expr(location.clone(), v)).collect::<Result<_, _>>()?;
                     function::function_call(location, function_name, args)
                 }
             }
         },       
-        Rule::function_define => {
-            let mut function_name = String::from("");
-            let mut arguments: Vec<AstNode> = Vec::new();
-            let mut fn_return: Box<AstNode> = Box::new(AstNode::Null);
-            let mut body: Vec<AstNode> = Vec::new();
-            //let mut is_public = false;
-
-            for inner_pair in pair.into_inner() {
-                match inner_pair.as_rule() {
-                    Rule::function_name => {
-                        function_name = String::from(inner_pair.as_str());
-                        let default_funcs = vec!["print","println"];
-                        if default_funcs.iter().any(|&i| i==function_name) {
-                            let mut message = "You cannot define this function name that is same as one of default functions: ".to_owned();
-                            message.push_str(&function_name);
-                            let error: Error<Rule> = Error::new_from_span(
-                                ErrorVariant::CustomError{
-                                    message: message
-                                },
-                                inner_pair.as_span()
-                            );
-                            println!("{}{}", location.0, error);
-                            process::exit(1);
-                        }
-                    },
-                    Rule::arguments_for_define => arguments = function::parse_arguments(location.clone(), inner_pair),
-                    Rule::stmt_in_function => {
-                        for body_stmt in inner_pair.into_inner() {
-                            body.push(build_ast_from_expr(location.clone(), body_stmt))
-                        }
-                    },
-                    Rule::fn_return | Rule::last_stmt_in_function => {
-                        let fn_return_stmt = inner_pair.into_inner().next().unwrap();
-                        fn_return =  Box::new(build_ast_from_expr(location.clone(), fn_return_stmt));
-                    }
-                    _ => {}
+        Rule::function_define | Rule::anonymous_function => build_function(location, pair, false)?,
+        Rule::enum_define => {
+            let mut pairs = pair.into_inner();
+            let name = pairs.next().unwrap().as_str().to_owned();
+            let mut variants: Vec<(String, Vec<String>)> = Vec::new();
+            for variant in pairs {
+                let mut variant_pairs = variant.into_inner();
+                let variant_name = variant_pairs.next().unwrap();
+                if variants.iter().any(|(defined, _)| defined == variant_name.as_str()) {
+                    let message = format!("The variant \"{}\" is declared twice.", variant_name.as_str());
+                    return Err(custom_error(&location.0, message, variant_name.as_span()));
                 }
+                let fields = variant_pairs.map(|field| field.as_str().to_owned()).collect();
+                variants.push((variant_name.as_str().to_owned(), fields));
             }
-            AstNode::FunctionDefine(location, function_name, arguments, body, fn_return)
+            AstNode::EnumDefine(location, name, variants)
+        },
+        Rule::variant => {
+            let mut pairs = pair.into_inner();
+            let (enum_name, variant) = build_variant_path(pairs.next().unwrap());
+            let args = match pairs.next() {
+                Some(args) => function::parse_arguments(location.clone(), args)?,
+                None => vec![],
+            };
+            AstNode::Variant(location, enum_name, variant, args)
+        },
+        Rule::import_stmt => build_import(location, pair)?,
+        Rule::throw_stmt => {
+            let val = build_ast_from_expr(location.clone(), pair.into_inner().next().unwrap())?;
+            AstNode::Throw(location, Box::new(val))
+        },
+        Rule::try_expr => build_try(location, pair)?,
+        Rule::impl_block => {
+            let mut pairs = pair.into_inner();
+            let name = pairs.next().unwrap().as_str().to_owned();
+            let methods = pairs.map(|method| build_function(location.clone(), method, true)).collect::<Result<_, _>>()?;
+            AstNode::Impl(location, name, methods)
         },
         Rule::argument => {
             AstNode::Argument(location, pair.as_str().to_string(), Box::new(AstNode::Null))
         }
+        Rule::if_expr if has_if_let(&pair) => build_if_let(location, pair)?,
         Rule::if_expr => {
             let mut pairs = pair.into_inner();
-            let conditions = calculation::into_logical_expression(location.clone(), pairs.next().unwrap());
+            let conditions = calculation::into_logical_expression(location.clone(), pairs.next().unwrap())?;
             let mut body: Vec<AstNode> = Vec::new();
             let mut else_if_bodies_conditions: LinkedList<(Vec<AstNode>, Vec<AstNode>)> = LinkedList::new();
             let mut else_body: Vec<AstNode> = Vec::new();
             let location_for_inner_scope = location.clone();
             for inner_pair in pairs {
                 match inner_pair.as_rule() {
-                    Rule::stmt_in_function | Rule::fn_return => {
+                    Rule::stmt_in_function => {
                         for p in inner_pair.into_inner() {
-                            body.push(build_ast_from_expr(location_for_inner_scope.clone(), p));
+                            body.push(build_ast_from_expr(location_for_inner_scope.clone(), p)?);
                         }
                     },
+                    Rule::fn_return => {
+                        body.push(build_return(location_for_inner_scope.clone(), inner_pair)?);
+                    },
                     Rule::else_if_expr => {
                         let else_if_pairs = inner_pair.into_inner();
                         let mut else_if_condition: Vec<AstNode> = Vec::new();
                         let mut else_if_body: Vec<AstNode> = Vec::new();
                         for else_if_pair in else_if_pairs {
                             match else_if_pair.as_rule() {
-                                Rule::condition | Rule::bool_operation => {
-                                    else_if_condition.push(calculation::into_logical_expression(location_for_inner_scope.clone(), else_if_pair));
+                                Rule::condition => {
+                                    else_if_condition.push(calculation::into_logical_expression(location_for_inner_scope.clone(), else_if_pair)?);
                                 },
-                                Rule::stmt_in_function | Rule::fn_return => {
+                                Rule::stmt_in_function => {
                                     let else_if_pairs = else_if_pair.into_inner();
                                     for else_if_inner_pair in else_if_pairs {
-                                        else_if_body.push(build_ast_from_expr(location_for_inner_scope.clone(), else_if_inner_pair));
+                                        else_if_body.push(build_ast_from_expr(location_for_inner_scope.clone(), else_if_inner_pair)?);
                                     }
                                 },
+                                Rule::fn_return => {
+                                    else_if_body.push(build_return(location_for_inner_scope.clone(), else_if_pair)?);
+                                },
                                 _ => {}
                             }
                         }
@@ -267,11 +475,14 @@ pub fn build_ast_from_expr(
                         let else_pairs = inner_pair.into_inner();
                         for else_pair in else_pairs {
                             match else_pair.as_rule() {
-                                Rule::stmt_in_function | Rule::fn_return => {
+                                Rule::stmt_in_function => {
                                     for p in else_pair.into_inner() {
-                                        else_body.push(build_ast_from_expr(location.clone(), p));
+                                        else_body.push(build_ast_from_expr(location.clone(), p)?);
                                     }
                                 },
+                                Rule::fn_return => {
+                                    else_body.push(build_return(location.clone(), else_pair)?);
+                                },
                                 _ => {}
                             }
                         } 
@@ -292,22 +503,463 @@ pub fn build_ast_from_expr(
                 var_type = VarType::VariableFirstAssigned;
                 ident = pairs.next().unwrap().as_str();
             }
-            let mut range = pairs.next().unwrap().into_inner();
+            let mut iterable = pairs.next().unwrap();
+            let mut second_ident = None;
+            if iterable.as_rule() == Rule::for_second_var {
+                second_ident = Some(iterable.as_str().to_string());
+                iterable = pairs.next().unwrap();
+            }
+            if iterable.as_rule() != Rule::range {
+                let iterable = build_ast_from_expr(location.clone(), iterable)?;
+                let stmt_in_function = build_loop_body(location.clone(), pairs)?;
+                return Ok(AstNode::ForEach(location, var_type, ident.to_string(), second_ident, Box::new(iterable), stmt_in_function));
+            }
+            if second_ident.is_some() {
+                let message = "A range can be iterated with only one variable.".to_owned();
+                return Err(custom_error(&location.0, message, iterable.as_span()));
+            }
+            let mut range = iterable.into_inner();
             let test = range.next().unwrap();
-            let first_elemnt = build_ast_from_expr(location.clone(), test.into_inner().next().unwrap());
+            let first_elemnt = build_ast_from_expr(location.clone(), test.into_inner().next().unwrap())?;
             let is_inclusive = match range.next().unwrap().as_rule() {
                 Rule::op_dots => false,
                 Rule::op_dots_inclusive => true,
                 unknown_expr => panic!("Unexpected expression: {:?}", unknown_expr),
             };
-            let last_elemnt = build_ast_from_expr(location.clone(), range.next().unwrap().into_inner().next().unwrap());
-            let mut stmt_in_function: Vec<AstNode> = Vec::new();
-            for pair in pairs {
-                let pair = pair.into_inner().next().unwrap();
-                stmt_in_function.push(build_ast_from_expr(location.clone(), pair));
-            }
+            let last_elemnt = build_ast_from_expr(location.clone(), range.next().unwrap().into_inner().next().unwrap())?;
+            let stmt_in_function = build_loop_body(location.clone(), pairs)?;
             AstNode::ForLoop(location, is_inclusive, var_type, ident.to_string(), Box::new(first_elemnt), Box::new(last_elemnt), stmt_in_function)
         },
+        Rule::while_expr => {
+            let mut pairs = pair.into_inner();
+            let condition = calculation::into_logical_expression(location.clone(), pairs.next().unwrap())?;
+            let stmt_in_function = build_loop_body(location.clone(), pairs)?;
+            AstNode::WhileLoop(location, Box::new(condition), stmt_in_function)
+        },
+        Rule::match_expr => build_match(location, pair)?,
+        Rule::op_break => AstNode::Break(location),
+        Rule::op_continue => AstNode::Continue(location),
         unknown_expr => panic!("Unexpected expression: {:?}", unknown_expr),
+    };
+    Ok(node)
+}
+
+/// Builds `try { } catch e { } finally { }`, which has catch or finally or both.
+fn build_try(location: (String, usize, usize), pair: pest::iterators::Pair<Rule>) -> Result<AstNode, OranError> {
+    let mut pairs = pair.into_inner();
+    let body = build_if_body(location.clone(), pairs.next().unwrap().into_inner().collect())?;
+    let mut catch = None;
+    let mut finally = None;
+    for clause in pairs {
+        match clause.as_rule() {
+            Rule::catch_clause => {
+                let mut clause_pairs = clause.into_inner();
+                let name = clause_pairs.next().unwrap().as_str().to_owned();
+                let catch_body = build_if_body(location.clone(), clause_pairs.next().unwrap().into_inner().collect())?;
+                catch = Some((name, catch_body));
+            }
+            _ => {
+                let block = clause.into_inner().next().unwrap();
+                finally = Some(build_if_body(location.clone(), block.into_inner().collect())?);
+            }
+        }
+    }
+    Ok(AstNode::Try(location, body, catch, finally))
+}
+
+/// Builds `import util`, which is `import "util.orn"`. The module is named after its file unless `as` gives a name.
+fn build_import(location: (String, usize, usize), pair: pest::iterators::Pair<Rule>) -> Result<AstNode, OranError> {
+    let mut pairs = pair.into_inner();
+    let module = pairs.next().unwrap();
+    let alias = pairs.next().map(|alias| alias.into_inner().next().unwrap().as_str().to_owned());
+    if module.as_rule() == Rule::ident {
+        let name = module.as_str().to_owned();
+        return Ok(AstNode::Import(location, format!("{}.orn", name), alias.unwrap_or(name)));
+    }
+    let span = module.as_span();
+    let path = match string::build_string(location.clone(), module)? {
+        AstNode::Str(_, path) => path,
+        _ => {
+            let message = "The path of a module cannot have ${...}.".to_owned();
+            return Err(custom_error(&location.0, message, span));
+        }
+    };
+    let name = match alias {
+        Some(alias) => alias,
+        None => {
+            let stem = std::path::Path::new(&path).file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+            let is_ident = stem.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && stem.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !is_ident {
+                let message = format!("\"{}\" cannot be the name of the module. Name it with as.", stem);
+                return Err(custom_error(&location.0, message, span));
+            }
+            stem
+        }
+    };
+    Ok(AstNode::Import(location, path, name))
+}
+
+/// Builds a named function, an anonymous function or a method in `impl`.
+/// Methods can have the names of builtin functions, because they are called only on a value like `xs.len()`.
+fn build_function(location: (String, usize, usize), pair: pest::iterators::Pair<Rule>, is_method: bool) -> Result<AstNode, OranError> {
+    let is_anonymous = pair.as_rule() == Rule::anonymous_function;
+    let mut function_name = String::from("");
+    let mut arguments: Vec<AstNode> = Vec::new();
+    let mut fn_return: AstNode = AstNode::Null;
+    let mut body: Vec<AstNode> = Vec::new();
+    let mut ends_with_match = false;
+    //let mut is_public = false;
+
+    for inner_pair in pair.into_inner() {
+        match inner_pair.as_rule() {
+            Rule::function_name => {
+                function_name = String::from(inner_pair.as_str());
+                if !is_method && builtin::is_builtin(&function_name) {
+                    let mut message = "You cannot define this function name that is same as one of default functions: ".to_owned();
+                    message.push_str(&function_name);
+                    return Err(custom_error(&location.0, message, inner_pair.as_span()));
+                }
+            },
+            Rule::arguments_for_define => {
+                let span = inner_pair.as_span();
+                arguments = function::parse_arguments(location.clone(), inner_pair)?;
+                if is_method && arguments.first().map(String::from).as_deref() != Some("self") {
+                    let message = "The first argument of a method must be self.".to_owned();
+                    return Err(custom_error(&location.0, message, span));
+                }
+            },
+            Rule::stmt_in_function => {
+                ends_with_match = is_match_without_end_mark(&inner_pair);
+                for body_stmt in inner_pair.into_inner() {
+                    body.push(build_ast_from_expr(location.clone(), body_stmt)?)
+                }
+            },
+            Rule::fn_return | Rule::last_stmt_in_function => {
+                ends_with_match = false;
+                fn_return = match inner_pair.into_inner().next() {
+                    Some(fn_return_stmt) => build_ast_from_expr(location.clone(), fn_return_stmt)?,
+                    None => AstNode::Null,
+                };
+            }
+            _ => {}
+        }
+    }
+    if ends_with_match {
+        fn_return = body.pop().unwrap();
+    }
+    if is_anonymous {
+        Ok(AstNode::AnonymousFunction(location, Rc::new(arguments), Rc::new(body), Rc::new(fn_return)))
+    } else {
+        Ok(AstNode::FunctionDefine(location, function_name, Rc::new(arguments), Rc::new(body), Rc::new(fn_return)))
+    }
+}
+
+/// The operand that a level of the expressions consists of, like the number of `-5`, when the level has no operator.
+fn operand_of(pair: pest::iterators::Pair<Rule>) -> pest::iterators::Pair<Rule> {
+    match pair.as_rule() {
+        Rule::condition | Rule::comparison | Rule::calc_term | Rule::power_term | Rule::indexed => {
+            let mut inner = pair.clone().into_inner();
+            match (inner.next(), inner.next()) {
+                (Some(operand), None) => operand_of(operand),
+                _ => pair,
+            }
+        }
+        _ => pair,
+    }
+}
+
+/// Builds `-x` or `+x`. A number right after the sign is built as a literal with the sign,
+/// so that `-9223372036854775808` is an integer.
+fn build_unary(location: (String, usize, usize), pair: pest::iterators::Pair<Rule>) -> Result<AstNode, OranError> {
+    let mut pairs = pair.into_inner();
+    let op = pairs.next().unwrap();
+    let operand = operand_of(pairs.next().unwrap());
+    let is_minus = op.as_rule() == Rule::unary_minus;
+    if operand.as_rule() == Rule::number {
+        return build_signed_number(location, operand, is_minus);
+    }
+    let operand = Box::new(build_ast_from_expr(location.clone(), operand)?);
+    if is_minus {
+        Ok(AstNode::Negate(location, operand))
+    } else {
+        Ok(AstNode::UnaryPlus(location, operand))
+    }
+}
+
+/// Builds a number literal. Integers that do not fit in 64 bits are errors instead of becoming floats.
+pub fn build_number(location: (String, usize, usize), pair: pest::iterators::Pair<Rule>) -> Result<AstNode, OranError> {
+    build_signed_number(location, pair, false)
+}
+
+fn build_signed_number(location: (String, usize, usize), pair: pest::iterators::Pair<Rule>, is_negative: bool) -> Result<AstNode, OranError> {
+    let pair = match pair.as_rule() {
+        Rule::number => pair.into_inner().next().unwrap(),
+        _ => pair,
+    };
+    let sign = if is_negative { "-" } else { "" };
+    // Underscores only separate the digits.
+    let text = format!("{}{}", sign, pair.as_str().replace('_', ""));
+    let literal = format!("{}{}", sign, pair.as_str());
+    match pair.as_rule() {
+        Rule::integer | Rule::radix_integer => {
+            let (radix, digits) = split_radix(&text);
+            match i64::from_str_radix(&digits, radix) {
+                Ok(int) => Ok(AstNode::Int(location, int)),
+                Err(_) => {
+                    let message = format!("This integer is out of range: {}. Integers must be between {} and {}.", literal, i64::MIN, i64::MAX);
+                    Err(custom_error(&location.0, message, pair.as_span()))
+                }
+            }
+        }
+        Rule::big_integer => {
+            let (radix, digits) = split_radix(text.trim_end_matches('n'));
+            Ok(AstNode::BigInt(location, BigInt::parse_bytes(digits.as_bytes(), radix).unwrap()))
+        }
+        Rule::exact_decimal => match Decimal::from_str_exact(text.trim_end_matches('d')) {
+            Ok(decimal) => Ok(AstNode::Decimal(location, decimal)),
+            Err(_) => {
+                let message = format!("This decimal is out of range: {}. Decimals must be between {} and {} with at most 28 decimal places.", literal, Decimal::MIN, Decimal::MAX);
+                Err(custom_error(&location.0, message, pair.as_span()))
+            }
+        },
+        _ => match text.parse::<f64>() {
+            Ok(float) if float.is_finite() => Ok(AstNode::Number(location, float)),
+            _ => {
+                let message = format!("This float is out of range: {}. Floats must be between {:e} and {:e}.", literal, f64::MIN, f64::MAX);
+                Err(custom_error(&location.0, message, pair.as_span()))
+            }
+        },
+    }
+}
+
+/// Splits an integer literal into its base and its digits with the sign, so "-0xFF" becomes (16, "-FF").
+fn split_radix(text: &str) -> (u32, String) {
+    let (sign, unsigned) = match text.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", text),
+    };
+    let (radix, digits) = match unsigned.get(..2) {
+        Some("0x") => (16, &unsigned[2..]),
+        Some("0o") => (8, &unsigned[2..]),
+        Some("0b") => (2, &unsigned[2..]),
+        _ => (10, unsigned),
+    };
+    (radix, format!("{}{}", sign, digits))
+}
+
+fn build_return(location: (String, usize, usize), pair: pest::iterators::Pair<Rule>) -> Result<AstNode, OranError> {
+    let location = pair_location(&location, &pair);
+    let returned = match pair.into_inner().next() {
+        Some(returned) => build_ast_from_expr(location.clone(), returned)?,
+        None => AstNode::Null,
+    };
+    Ok(AstNode::Return(location, Box::new(returned)))
+}
+
+fn build_match(location: (String, usize, usize), pair: pest::iterators::Pair<Rule>) -> Result<AstNode, OranError> {
+    let mut pairs = pair.into_inner();
+    let value = build_ast_from_expr(location.clone(), pairs.next().unwrap())?;
+    let mut arms = vec![];
+    for arm in pairs {
+        let mut arm_pairs = arm.into_inner();
+        let patterns_pair = arm_pairs.next().unwrap();
+        let patterns: Vec<Pattern> = patterns_pair.clone().into_inner()
+            .map(|pattern| build_pattern(&location, pattern))
+            .collect::<Result<_, _>>()?;
+        let binds = patterns.iter().any(|pattern| matches!(pattern, Pattern::Variant(VariantPattern { bindings: Some(bindings), .. }) if bindings.iter().any(Option::is_some)));
+        if binds && patterns.len() > 1 {
+            let message = "A pattern that binds values cannot be joined with |.".to_owned();
+            return Err(custom_error(&location.0, message, patterns_pair.as_span()));
+        }
+        let result = arm_pairs.next().unwrap();
+        let (body, value) = match result.as_rule() {
+            Rule::match_block => build_block_with_value(location.clone(), result.into_inner())?,
+            _ => (vec![], build_ast_from_expr(location.clone(), result)?),
+        };
+        arms.push(MatchArm { patterns, body, value });
+    }
+    Ok(AstNode::Match(location, Box::new(value), arms))
+}
+
+/// Builds a pattern of `match`. Values can only be matched against literals, and ranges must be of numbers.
+fn build_pattern(location: &(String, usize, usize), pair: pest::iterators::Pair<Rule>) -> Result<Pattern, OranError> {
+    match pair.as_rule() {
+        Rule::wildcard => Ok(Pattern::Wildcard),
+        Rule::variant_pattern => Ok(Pattern::Variant(build_variant_pattern(pair))),
+        Rule::pattern_range => {
+            let mut pairs = pair.into_inner();
+            let first = build_pattern_literal(location, pairs.next().unwrap(), true)?;
+            let is_inclusive = pairs.next().unwrap().as_rule() == Rule::op_dots_inclusive;
+            let last = build_pattern_literal(location, pairs.next().unwrap(), true)?;
+            Ok(Pattern::Range(first, last, is_inclusive))
+        }
+        _ => Ok(Pattern::Literal(build_pattern_literal(location, pair, false)?)),
+    }
+}
+
+/// The enum and the variant of `Shape::Circle`, or of `Some`, `None`, `Ok` and `Err`, which are of `Option` and `Result`.
+fn build_variant_path(pair: pest::iterators::Pair<Rule>) -> (String, String) {
+    let mut pairs = pair.into_inner();
+    let first = pairs.next().unwrap();
+    match pairs.next() {
+        Some(variant) => (first.as_str().to_owned(), variant.as_str().to_owned()),
+        None => {
+            let enum_name = match first.as_str() {
+                "Some" | "None" => "Option",
+                _ => "Result",
+            };
+            (enum_name.to_owned(), first.as_str().to_owned())
+        }
+    }
+}
+
+fn build_variant_pattern(pair: pest::iterators::Pair<Rule>) -> VariantPattern {
+    let mut pairs = pair.into_inner();
+    let (enum_name, variant) = build_variant_path(pairs.next().unwrap());
+    let bindings = pairs.next().map(|bindings| {
+        bindings.into_inner().map(|binding| match binding.as_rule() {
+            Rule::wildcard => None,
+            _ => Some(binding.as_str().to_owned()),
+        }).collect()
+    });
+    VariantPattern { enum_name, variant, bindings }
+}
+
+fn has_if_let(pair: &pest::iterators::Pair<Rule>) -> bool {
+    pair.clone().into_inner().any(|inner| match inner.as_rule() {
+        Rule::if_let => true,
+        Rule::else_if_expr => inner.into_inner().next().unwrap().as_rule() == Rule::if_let,
+        _ => false,
+    })
+}
+
+/// Builds `if` with `if let` in it as `match`, one for each `if let`, and `if` for the other conditions.
+/// `if let Some(x) = a { A } else if b { B } else { C }` becomes
+/// `match a { Some(x) => { A }, _ => { if b { B } else { C } } }`.
+fn build_if_let(location: (String, usize, usize), pair: pest::iterators::Pair<Rule>) -> Result<AstNode, OranError> {
+    // The conditions with their locations and bodies, and the body of else.
+    let mut clauses = Vec::new();
+    let mut else_body: Vec<AstNode> = Vec::new();
+    let mut pairs = pair.into_inner();
+    let condition = pairs.next().unwrap();
+    let mut body = Vec::new();
+    let mut rest = Vec::new();
+    for inner_pair in pairs {
+        match inner_pair.as_rule() {
+            Rule::stmt_in_function | Rule::fn_return => body.push(inner_pair),
+            _ => rest.push(inner_pair),
+        }
+    }
+    clauses.push((location.clone(), condition, build_if_body(location.clone(), body)?));
+    for inner_pair in rest {
+        let clause_location = pair_location(&location, &inner_pair);
+        let is_else_if = inner_pair.as_rule() == Rule::else_if_expr;
+        let mut inner_pairs = inner_pair.into_inner();
+        if is_else_if {
+            let condition = inner_pairs.next().unwrap();
+            clauses.push((clause_location, condition, build_if_body(location.clone(), inner_pairs.collect())?));
+        } else {
+            else_body = build_if_body(location.clone(), inner_pairs.collect())?;
+        }
+    }
+    for (clause_location, condition, body) in clauses.into_iter().rev() {
+        let node = if condition.as_rule() == Rule::if_let {
+            let mut condition_pairs = condition.into_inner();
+            let pattern = build_variant_pattern(condition_pairs.next().unwrap());
+            let value = build_ast_from_expr(clause_location.clone(), condition_pairs.next().unwrap())?;
+            let arms = vec![
+                MatchArm { patterns: vec![Pattern::Variant(pattern)], body, value: AstNode::Null },
+                MatchArm { patterns: vec![Pattern::Wildcard], body: else_body, value: AstNode::Null },
+            ];
+            AstNode::Match(clause_location, Box::new(value), arms)
+        } else {
+            let condition = calculation::into_logical_expression(clause_location.clone(), condition)?;
+            AstNode::IF(clause_location, Box::new(condition), body, LinkedList::new(), else_body)
+        };
+        else_body = vec![node];
+    }
+    Ok(else_body.pop().unwrap())
+}
+
+fn build_if_body(location: (String, usize, usize), pairs: Vec<pest::iterators::Pair<Rule>>) -> Result<Vec<AstNode>, OranError> {
+    let mut body = Vec::new();
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::fn_return => body.push(build_return(location.clone(), pair)?),
+            _ => {
+                for stmt in pair.into_inner() {
+                    body.push(build_ast_from_expr(location.clone(), stmt)?);
+                }
+            }
+        }
+    }
+    Ok(body)
+}
+
+fn build_pattern_literal(location: &(String, usize, usize), pair: pest::iterators::Pair<Rule>, is_bound: bool) -> Result<AstNode, OranError> {
+    let span = pair.as_span();
+    let literal = build_ast_from_expr(location.clone(), pair)?;
+    let is_number = matches!(literal, AstNode::Int(..) | AstNode::BigInt(..) | AstNode::Decimal(..) | AstNode::Number(..));
+    if is_bound && !is_number {
+        let message = "A range in a pattern must be between numbers.".to_owned();
+        return Err(custom_error(&location.0, message, span));
+    }
+    if !is_number && !matches!(literal, AstNode::Str(..) | AstNode::Bool(..)) {
+        let message = "A pattern must be a number, a string, a boolean, a range of numbers or _.".to_owned();
+        return Err(custom_error(&location.0, message, span));
+    }
+    Ok(literal)
+}
+
+/// Builds the statements of a block and the value it ends with, which is `null` when there is none.
+fn build_block_with_value(location: (String, usize, usize), pairs: Pairs<Rule>) -> Result<(Vec<AstNode>, AstNode), OranError> {
+    let mut body = vec![];
+    let mut value = AstNode::Null;
+    let mut ends_with_match = false;
+    for pair in pairs {
+        ends_with_match = false;
+        match pair.as_rule() {
+            Rule::stmt_in_function => {
+                ends_with_match = is_match_without_end_mark(&pair);
+                for stmt in pair.into_inner() {
+                    body.push(build_ast_from_expr(location.clone(), stmt)?);
+                }
+            }
+            Rule::fn_return => body.push(build_return(location.clone(), pair)?),
+            _ => value = build_ast_from_expr(location.clone(), pair.into_inner().next().unwrap())?,
+        }
+    }
+    if ends_with_match {
+        value = body.pop().unwrap();
+    }
+    Ok((body, value))
+}
+
+/// Whether a statement is a `match` without `;`, whose value is the value of the block when it is the last statement.
+pub fn is_match_without_end_mark(pair: &pest::iterators::Pair<Rule>) -> bool {
+    let is_match = pair.clone().into_inner().next().map(|inner| inner.as_rule()) == Some(Rule::match_expr);
+    is_match && !pair.as_str().trim_end().ends_with(';')
+}
+
+/// Builds `[index]` or `.field`.
+fn build_accessor(location: (String, usize, usize), pair: pest::iterators::Pair<Rule>) -> Result<Accessor, OranError> {
+    match pair.as_rule() {
+        Rule::field => Ok(Accessor::Field(pair.into_inner().next().unwrap().as_str().to_owned())),
+        _ => Ok(Accessor::Index(build_ast_from_expr(location, pair.into_inner().next().unwrap())?)),
+    }
+}
+
+fn build_loop_body(location: (String, usize, usize), pairs: Pairs<Rule>) -> Result<Vec<AstNode>, OranError> {
+    let mut stmts: Vec<AstNode> = Vec::new();
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::fn_return => stmts.push(build_return(location.clone(), pair)?),
+            _ => {
+                let pair = pair.into_inner().next().unwrap();
+                stmts.push(build_ast_from_expr(location.clone(), pair)?);
+            }
+        }
     }
+    Ok(stmts)
 }
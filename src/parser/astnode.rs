@@ -1,22 +1,57 @@
 use crate::value::var_type::VarType;
 use std::collections::LinkedList;
+use std::rc::Rc;
+use num_bigint::BigInt;
+use rust_decimal::Decimal;
 
 #[derive(PartialEq, Debug)]
 pub enum AstNode {
     Assign((String, usize, usize),VarType, String, Box<AstNode>),
-    FunctionDefine((String, usize, usize), String, Vec<AstNode>, Vec<AstNode>, Box<AstNode>),
+    FunctionDefine((String, usize, usize), String, Rc<Vec<AstNode>>, Rc<Vec<AstNode>>, Rc<AstNode>),
+    AnonymousFunction((String, usize, usize), Rc<Vec<AstNode>>, Rc<Vec<AstNode>>, Rc<AstNode>),
     FunctionCall((String, usize, usize), String, Vec<AstNode>),
     Ident((String, usize, usize), String),
     Argument((String, usize, usize), String, Box<AstNode>),
     Str((String, usize, usize), String),
     Strs((String, usize, usize), Vec<AstNode>),
+    Int((String, usize, usize), i64),
+    BigInt((String, usize, usize), BigInt),
+    Decimal((String, usize, usize), Decimal),
     Number((String, usize, usize), f64),
-    Calc(CalcOp, Box<AstNode>, Box<AstNode>),
+    Calc((String, usize, usize), CalcOp, Box<AstNode>, Box<AstNode>),
     Bool((String, usize, usize), bool),
+    List((String, usize, usize), Vec<AstNode>),
+    Map((String, usize, usize), Vec<(AstNode, AstNode)>),
+    Index((String, usize, usize), Box<AstNode>, Box<AstNode>),
+    /// `xs[i] = v` or `p.x = v`. `xs[i] += v` has the operator and its location, and reads the element once.
+    IndexAssign((String, usize, usize), String, Vec<Accessor>, Option<((String, usize, usize), CalcOp)>, Box<AstNode>),
+    Field((String, usize, usize), Box<AstNode>, String),
+    StructDefine((String, usize, usize), String, Vec<String>),
+    StructLiteral((String, usize, usize), String, Vec<(String, AstNode)>),
+    Impl((String, usize, usize), String, Vec<AstNode>),
+    EnumDefine((String, usize, usize), String, Vec<(String, Vec<String>)>),
+    Variant((String, usize, usize), String, String, Vec<AstNode>),
+    Import((String, usize, usize), String, String),
+    MethodCall((String, usize, usize), Box<AstNode>, String, Vec<AstNode>),
+    /// Calls the value of an expression, like `make_adder(1)(2)` or `handlers[0]()`.
+    Call((String, usize, usize), Box<AstNode>, Vec<AstNode>),
     IF((String, usize, usize), Box<AstNode>, Vec<AstNode>, LinkedList<(Vec<AstNode>, Vec<AstNode>)>, Vec<AstNode>),
     Condition(ComparisonlOperatorType, Box<AstNode>, Box<AstNode>),
     Comparison((String, usize, usize), Box<AstNode>, LogicalOperatorType, Box<AstNode>),
+    Not((String, usize, usize), Box<AstNode>),
+    BitNot((String, usize, usize), Box<AstNode>),
+    Negate((String, usize, usize), Box<AstNode>),
+    UnaryPlus((String, usize, usize), Box<AstNode>),
     ForLoop((String, usize, usize), bool, VarType, String, Box<AstNode>, Box<AstNode>, Vec<AstNode>),
+    ForEach((String, usize, usize), VarType, String, Option<String>, Box<AstNode>, Vec<AstNode>),
+    WhileLoop((String, usize, usize), Box<AstNode>, Vec<AstNode>),
+    Break((String, usize, usize)),
+    Continue((String, usize, usize)),
+    Return((String, usize, usize), Box<AstNode>),
+    Throw((String, usize, usize), Box<AstNode>),
+    /// The try block, the name and the block of `catch`, and the block of `finally`.
+    Try((String, usize, usize), Vec<AstNode>, Option<(String, Vec<AstNode>)>, Option<Vec<AstNode>>),
+    Match((String, usize, usize), Box<AstNode>, Vec<MatchArm>),
     Null
 }
 
@@ -25,23 +60,89 @@ impl Clone for AstNode {
         match self {
             AstNode::Assign(loc, v, s, b) => AstNode::Assign(loc.clone(), *v, s.clone(), b.clone()),
             AstNode::FunctionDefine(loc, s, va, va2, b) =>  AstNode::FunctionDefine(loc.clone(), s.clone(), va.clone(), va2.clone(), b.clone()),
+            AstNode::AnonymousFunction(loc, va, va2, b) => AstNode::AnonymousFunction(loc.clone(), va.clone(), va2.clone(), b.clone()),
             AstNode::FunctionCall(loc, s, va) => AstNode::FunctionCall(loc.clone(), s.clone(), va.clone()),
             AstNode::Ident(loc, s) => AstNode::Ident(loc.clone(), s.clone()),
             AstNode::Argument(loc, s, b) => AstNode::Argument(loc.clone(), s.clone(), b.clone()),
             AstNode::Str(loc, s) => AstNode::Str(loc.clone(), s.clone()),
             AstNode::Strs(loc, va) => AstNode::Strs(loc.clone(), va.clone()),
+            AstNode::Int(loc, i) => AstNode::Int(loc.clone(), *i),
+            AstNode::BigInt(loc, b) => AstNode::BigInt(loc.clone(), b.clone()),
+            AstNode::Decimal(loc, d) => AstNode::Decimal(loc.clone(), *d),
             AstNode::Number(loc, f) => AstNode::Number(loc.clone(), *f),
-            AstNode::Calc(c, ba, ba2) => AstNode::Calc(*c, ba.clone(), ba2.clone()),
+            AstNode::Calc(loc, c, ba, ba2) => AstNode::Calc(loc.clone(), *c, ba.clone(), ba2.clone()),
             AstNode::Bool(loc, b) => AstNode::Bool(loc.clone(), *b),
+            AstNode::List(loc, va) => AstNode::List(loc.clone(), va.clone()),
+            AstNode::Map(loc, va) => AstNode::Map(loc.clone(), va.clone()),
+            AstNode::Index(loc, ba, ba2) => AstNode::Index(loc.clone(), ba.clone(), ba2.clone()),
+            AstNode::IndexAssign(loc, s, va, op, ba) => AstNode::IndexAssign(loc.clone(), s.clone(), va.clone(), op.clone(), ba.clone()),
+            AstNode::Field(loc, ba, s) => AstNode::Field(loc.clone(), ba.clone(), s.clone()),
+            AstNode::StructDefine(loc, s, vs) => AstNode::StructDefine(loc.clone(), s.clone(), vs.clone()),
+            AstNode::StructLiteral(loc, s, va) => AstNode::StructLiteral(loc.clone(), s.clone(), va.clone()),
+            AstNode::Impl(loc, s, va) => AstNode::Impl(loc.clone(), s.clone(), va.clone()),
+            AstNode::EnumDefine(loc, s, vs) => AstNode::EnumDefine(loc.clone(), s.clone(), vs.clone()),
+            AstNode::Variant(loc, s, s2, va) => AstNode::Variant(loc.clone(), s.clone(), s2.clone(), va.clone()),
+            AstNode::Import(loc, s, s2) => AstNode::Import(loc.clone(), s.clone(), s2.clone()),
+            AstNode::MethodCall(loc, ba, s, va) => AstNode::MethodCall(loc.clone(), ba.clone(), s.clone(), va.clone()),
+            AstNode::Call(loc, ba, va) => AstNode::Call(loc.clone(), ba.clone(), va.clone()),
             AstNode::IF(loc, ba, va, llist, va2) => AstNode::IF(loc.clone(), ba.clone(), va.clone(), llist.clone(), va2.clone()),
             AstNode::Condition(c, ba, ba2) => AstNode::Condition(*c, ba.clone(), ba2.clone()),
             AstNode::Comparison(loc, ba, lot, ba2) => AstNode::Comparison(loc.clone(), ba.clone(), *lot, ba2.clone()),
+            AstNode::Not(loc, ba) => AstNode::Not(loc.clone(), ba.clone()),
+            AstNode::BitNot(loc, ba) => AstNode::BitNot(loc.clone(), ba.clone()),
+            AstNode::Negate(loc, ba) => AstNode::Negate(loc.clone(), ba.clone()),
+            AstNode::UnaryPlus(loc, ba) => AstNode::UnaryPlus(loc.clone(), ba.clone()),
             AstNode::ForLoop(loc, b, vt, s, ba, ba2, va) => AstNode::ForLoop(loc.clone(), *b, *vt, s.clone(), ba.clone(), ba2.clone(), va.clone()),
+            AstNode::ForEach(loc, vt, s, s2, ba, va) => AstNode::ForEach(loc.clone(), *vt, s.clone(), s2.clone(), ba.clone(), va.clone()),
+            AstNode::WhileLoop(loc, ba, va) => AstNode::WhileLoop(loc.clone(), ba.clone(), va.clone()),
+            AstNode::Break(loc) => AstNode::Break(loc.clone()),
+            AstNode::Continue(loc) => AstNode::Continue(loc.clone()),
+            AstNode::Return(loc, ba) => AstNode::Return(loc.clone(), ba.clone()),
+            AstNode::Throw(loc, ba) => AstNode::Throw(loc.clone(), ba.clone()),
+            AstNode::Try(loc, va, catch, finally) => AstNode::Try(loc.clone(), va.clone(), catch.clone(), finally.clone()),
+            AstNode::Match(loc, ba, arms) => AstNode::Match(loc.clone(), ba.clone(), arms.clone()),
             AstNode::Null => AstNode::Null
         }
     }
 }
 
+/// A step into the value of a variable in the target of an assignment, like `[0]` and `.x` in `p.points[0].x = 1`.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Accessor {
+    Index(AstNode),
+    Field(String),
+}
+
+/// An arm of `match`. When the value matches one of the patterns, the body runs and then
+/// the value of the arm becomes the value of the `match`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct MatchArm {
+    pub patterns: Vec<Pattern>,
+    pub body: Vec<AstNode>,
+    pub value: AstNode,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Pattern {
+    /// Matches the values that are `==` to the literal.
+    Literal(AstNode),
+    /// Matches the numbers from the first literal to the second one, which is included with `..=`.
+    Range(AstNode, AstNode, bool),
+    /// `_`, which matches any value.
+    Wildcard,
+    /// Matches the values of a variant of an enum, like `Shape::Circle(r)` or `None`.
+    Variant(VariantPattern),
+}
+
+/// The variant a pattern matches. The bindings are the names the values of the variant are given,
+/// where `None` is `_`, and they are `None` when the pattern has no parentheses.
+#[derive(PartialEq, Debug, Clone)]
+pub struct VariantPattern {
+    pub enum_name: String,
+    pub variant: String,
+    pub bindings: Option<Vec<Option<String>>>,
+}
+
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum CalcOp {
     Plus,
@@ -49,18 +150,25 @@ pub enum CalcOp {
     Times,
     Divide,
     Modulus,
-    Power
+    Power,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    Concat
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum ComparisonlOperatorType {
-    AND,
-    OR
+    And,
+    Or
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum LogicalOperatorType {
     Equal,
+    NotEqual,
     BiggerThan,
     SmallerThan,
     EbiggerThan,
@@ -71,7 +179,7 @@ impl From<AstNode> for String {
     fn from(val: AstNode) -> Self {
         match val {
             AstNode::FunctionDefine(ref _loc, ref _name, ref arg, ref _body, ref _fn_return) => {
-                String::from(arg.into_iter().nth(0).unwrap().clone())
+                String::from(arg.first().unwrap().clone())
             }
             AstNode::Argument(ref _loc, ref s, ref _a) => {
                 s.to_string()
@@ -79,6 +187,15 @@ impl From<AstNode> for String {
             AstNode::Str(ref _loc, ref s) => {
                 s.to_string()
             }
+            AstNode::Int(ref _loc, ref n) => {
+                n.to_string()
+            }
+            AstNode::BigInt(ref _loc, ref n) => {
+                n.to_string()
+            }
+            AstNode::Decimal(ref _loc, ref n) => {
+                n.to_string()
+            }
             AstNode::Number(ref _loc, ref n) => {
                 n.to_string()
             }
@@ -94,7 +211,7 @@ impl From<&AstNode> for String {
     fn from(val: &AstNode) -> Self {
         match val {
             AstNode::FunctionDefine(ref _loc, ref _name, ref arg, ref _body, ref _fn_return) => {
-                String::from(arg.into_iter().nth(0).unwrap().clone())
+                String::from(arg.first().unwrap().clone())
             }
             AstNode::Argument(ref _loc, ref s, ref _a) => {
                 s.to_string()
@@ -102,6 +219,15 @@ impl From<&AstNode> for String {
             AstNode::Str(ref _loc, ref s) => {
                 s.to_string()
             }
+            AstNode::Int(ref _loc, ref n) => {
+                n.to_string()
+            }
+            AstNode::BigInt(ref _loc, ref n) => {
+                n.to_string()
+            }
+            AstNode::Decimal(ref _loc, ref n) => {
+                n.to_string()
+            }
             AstNode::Number(ref _loc, ref n) => {
                 n.to_string()
             }
@@ -125,12 +251,129 @@ impl From<AstNode> for f64 {
 }
 
 impl AstNode {
-    pub fn calculation<L, R>(op: CalcOp, lhs: L, rhs: R) -> Self
+    /// Whether the node produces a value, as opposed to a statement like an assignment or a loop.
+    pub fn is_expression(&self) -> bool {
+        !matches!(self,
+            AstNode::Assign(..)
+            | AstNode::FunctionDefine(..)
+            | AstNode::IndexAssign(..)
+            | AstNode::StructDefine(..)
+            | AstNode::Impl(..)
+            | AstNode::EnumDefine(..)
+            | AstNode::Import(..)
+            | AstNode::IF(..)
+            | AstNode::ForLoop(..)
+            | AstNode::ForEach(..)
+            | AstNode::WhileLoop(..)
+            | AstNode::Break(..)
+            | AstNode::Continue(..)
+            | AstNode::Return(..)
+            | AstNode::Throw(..)
+            | AstNode::Try(..)
+        )
+    }
+
+    /// Whether a function, named or anonymous, is defined anywhere in the node.
+    pub fn defines_function(&self) -> bool {
+        matches!(self, AstNode::FunctionDefine(..) | AstNode::AnonymousFunction(..))
+            || self.children().into_iter().any(AstNode::defines_function)
+    }
+
+    /// The nodes right inside this node, like the operands of a calculation or the statements of a loop.
+    pub fn children(&self) -> Vec<&AstNode> {
+        match self {
+            AstNode::FunctionDefine(_, _, args, body, fn_return)
+            | AstNode::AnonymousFunction(_, args, body, fn_return) => {
+                args.iter().chain(body.iter()).chain(Some(fn_return.as_ref())).collect()
+            }
+            AstNode::Assign(_, _, _, expr)
+            | AstNode::Argument(_, _, expr)
+            | AstNode::Return(_, expr)
+            | AstNode::Throw(_, expr)
+            | AstNode::Not(_, expr)
+            | AstNode::BitNot(_, expr)
+            | AstNode::Negate(_, expr)
+            | AstNode::UnaryPlus(_, expr)
+            | AstNode::Field(_, expr, _) => vec![expr],
+            AstNode::FunctionCall(_, _, nodes)
+            | AstNode::Impl(_, _, nodes)
+            | AstNode::Variant(_, _, _, nodes)
+            | AstNode::Strs(_, nodes)
+            | AstNode::List(_, nodes) => nodes.iter().collect(),
+            AstNode::Calc(_, _, lhs, rhs)
+            | AstNode::Index(_, lhs, rhs)
+            | AstNode::Condition(_, lhs, rhs)
+            | AstNode::Comparison(_, lhs, _, rhs) => vec![lhs, rhs],
+            AstNode::Map(_, entries) => entries.iter().flat_map(|(key, val)| vec![key, val]).collect(),
+            AstNode::IndexAssign(_, _, accessors, _, expr) => {
+                let mut nodes: Vec<&AstNode> = accessors.iter().filter_map(|accessor| match accessor {
+                    Accessor::Index(index) => Some(index),
+                    Accessor::Field(_) => None,
+                }).collect();
+                nodes.push(expr);
+                nodes
+            }
+            AstNode::MethodCall(_, receiver, _, args) | AstNode::Call(_, receiver, args) => Some(receiver.as_ref()).into_iter().chain(args).collect(),
+            AstNode::StructLiteral(_, _, fields) => fields.iter().map(|(_, val)| val).collect(),
+            AstNode::IF(_, condition, body, else_ifs, else_body) => {
+                let mut nodes: Vec<&AstNode> = vec![condition];
+                nodes.extend(body);
+                for (conditions, body) in else_ifs {
+                    nodes.extend(conditions);
+                    nodes.extend(body);
+                }
+                nodes.extend(else_body);
+                nodes
+            }
+            AstNode::ForLoop(_, _, _, _, first, last, body) => vec![first.as_ref(), last].into_iter().chain(body).collect(),
+            AstNode::ForEach(_, _, _, _, iterable, body) | AstNode::WhileLoop(_, iterable, body) => {
+                Some(iterable.as_ref()).into_iter().chain(body).collect()
+            }
+            AstNode::Try(_, body, catch, finally) => {
+                let mut nodes: Vec<&AstNode> = body.iter().collect();
+                if let Some((_, catch_body)) = catch {
+                    nodes.extend(catch_body);
+                }
+                nodes.extend(finally.iter().flatten());
+                nodes
+            }
+            AstNode::Match(_, value, arms) => {
+                let mut nodes: Vec<&AstNode> = vec![value];
+                for arm in arms {
+                    for pattern in &arm.patterns {
+                        match pattern {
+                            Pattern::Literal(literal) => nodes.push(literal),
+                            Pattern::Range(first, last, _) => nodes.extend(vec![first, last]),
+                            Pattern::Wildcard | Pattern::Variant(_) => {}
+                        }
+                    }
+                    nodes.extend(&arm.body);
+                    nodes.push(&arm.value);
+                }
+                nodes
+            }
+            AstNode::Ident(..)
+            | AstNode::Str(..)
+            | AstNode::Int(..)
+            | AstNode::BigInt(..)
+            | AstNode::Decimal(..)
+            | AstNode::Number(..)
+            | AstNode::Bool(..)
+            | AstNode::Break(..)
+            | AstNode::Continue(..)
+            | AstNode::StructDefine(..)
+            | AstNode::EnumDefine(..)
+            | AstNode::Import(..)
+            | AstNode::Null => vec![],
+        }
+    }
+
+    pub fn calculation<L, R>(location: (String, usize, usize), op: CalcOp, lhs: L, rhs: R) -> Self
     where
         L: Into<AstNode>,
         R: Into<AstNode>,
     {
-        AstNode::Calc(op.into(), Box::new(lhs.into()), Box::new(rhs.into()))
+        AstNode::Calc(location, op, Box::new(lhs.into()), Box::new(rhs.into()))
     }
 }
 
@@ -140,6 +383,6 @@ impl AstNode {
         L: Into<AstNode>,
         R: Into<AstNode>,
     {
-        AstNode::Condition(op.into(), Box::new(lhs.into()), Box::new(rhs.into()))
+        AstNode::Condition(op, Box::new(lhs.into()), Box::new(rhs.into()))
     }
 }
@@ -1,18 +1,17 @@
 use pest::{iterators::Pair, prec_climber::{Assoc, Operator, PrecClimber}};
-use std::process;
 use super::{Rule, astnode::{AstNode, CalcOp, ComparisonlOperatorType, LogicalOperatorType}};
 use super::function;
 use super::ast_build;
-use colored::*;
+use crate::value::oran_error::OranError;
 
-/**
+/*
  * This part was created by refering to 
  * https://github.com/ubnt-intrepid/pest-calculator
  * Copyright (c) 2017 Yusuke Sasaki
  * But a bit modified.
 */
 
-pub fn into_logical_expression(location:(String, usize, usize), pair: Pair<Rule>) -> AstNode {
+pub fn into_logical_expression(location:(String, usize, usize), pair: Pair<Rule>) -> Result<AstNode, OranError> {
     let climber = PrecClimber::new(vec![
         Operator::new(Rule::op_or, Assoc::Left),
         Operator::new(Rule::op_and, Assoc::Left),
@@ -21,8 +20,13 @@ pub fn into_logical_expression(location:(String, usize, usize), pair: Pair<Rule>
     logical_consume(location, pair, &climber)
 }
 
-pub fn into_calc_expression(location:(String, usize, usize), pair: Pair<Rule>) -> AstNode {
+pub fn into_calc_expression(location:(String, usize, usize), pair: Pair<Rule>) -> Result<AstNode, OranError> {
     let climber = PrecClimber::new(vec![
+        Operator::new(Rule::concat, Assoc::Left),
+        Operator::new(Rule::bit_or, Assoc::Left),
+        Operator::new(Rule::bit_xor, Assoc::Left),
+        Operator::new(Rule::bit_and, Assoc::Left),
+        Operator::new(Rule::shift_left, Assoc::Left) | Operator::new(Rule::shift_right, Assoc::Left),
         Operator::new(Rule::plus, Assoc::Left) | Operator::new(Rule::minus, Assoc::Left),
         Operator::new(Rule::times, Assoc::Left) | Operator::new(Rule::divide, Assoc::Left) | Operator::new(Rule::modulus, Assoc::Left),
         Operator::new(Rule::power, Assoc::Right),
@@ -31,37 +35,49 @@ pub fn into_calc_expression(location:(String, usize, usize), pair: Pair<Rule>) -
     calc_consume(location, pair, &climber)
 }
 
-fn get_op_ast_node (lhs: AstNode, op: Pair<Rule>, rhs: AstNode) -> AstNode {
+fn get_op_ast_node (location: &(String, usize, usize), lhs: AstNode, op: Pair<Rule>, rhs: AstNode) -> AstNode {
+    let location = ast_build::pair_location(location, &op);
     match op.as_rule() {
-        Rule::op_and => AstNode::condition(ComparisonlOperatorType::AND, lhs, rhs),
-        Rule::op_or => AstNode::condition(ComparisonlOperatorType::OR, lhs, rhs),
-        Rule::plus => AstNode::calculation(CalcOp::Plus, lhs, rhs),
-        Rule::minus => AstNode::calculation(CalcOp::Minus, lhs, rhs),
-        Rule::times => AstNode::calculation(CalcOp::Times, lhs, rhs),
-        Rule::divide => AstNode::calculation(CalcOp::Divide, lhs, rhs),
-        Rule::modulus => AstNode::calculation(CalcOp::Modulus, lhs, rhs),
-        Rule::power => AstNode::calculation(CalcOp::Power, lhs, rhs),
+        Rule::op_and => AstNode::condition(ComparisonlOperatorType::And, lhs, rhs),
+        Rule::op_or => AstNode::condition(ComparisonlOperatorType::Or, lhs, rhs),
+        Rule::plus => AstNode::calculation(location.clone(), CalcOp::Plus, lhs, rhs),
+        Rule::minus => AstNode::calculation(location.clone(), CalcOp::Minus, lhs, rhs),
+        Rule::times => AstNode::calculation(location.clone(), CalcOp::Times, lhs, rhs),
+        Rule::divide => AstNode::calculation(location.clone(), CalcOp::Divide, lhs, rhs),
+        Rule::modulus => AstNode::calculation(location.clone(), CalcOp::Modulus, lhs, rhs),
+        Rule::power => AstNode::calculation(location.clone(), CalcOp::Power, lhs, rhs),
+        Rule::bit_and => AstNode::calculation(location.clone(), CalcOp::BitAnd, lhs, rhs),
+        Rule::bit_or => AstNode::calculation(location.clone(), CalcOp::BitOr, lhs, rhs),
+        Rule::bit_xor => AstNode::calculation(location.clone(), CalcOp::BitXor, lhs, rhs),
+        Rule::shift_left => AstNode::calculation(location.clone(), CalcOp::ShiftLeft, lhs, rhs),
+        Rule::shift_right => AstNode::calculation(location.clone(), CalcOp::ShiftRight, lhs, rhs),
+        Rule::concat => AstNode::calculation(location.clone(), CalcOp::Concat, lhs, rhs),
         _ => unreachable!(),
     }
 }
 
-fn logical_consume(location: (String, usize, usize), pair: Pair<Rule>, climber: &PrecClimber<Rule>) -> AstNode {
-    match pair.as_rule() {
+fn logical_consume(location: (String, usize, usize), pair: Pair<Rule>, climber: &PrecClimber<Rule>) -> Result<AstNode, OranError> {
+    let location = ast_build::pair_location(&location, &pair);
+    let node = match pair.as_rule() {
         Rule::condition => {
             let pairs = pair.into_inner();
-            climber.climb(pairs, |pair| logical_consume(location.clone(), pair, climber), get_op_ast_node)
-        }
-        Rule::bool_operation => {
-            let newpair = pair.into_inner().next().map(|pair| logical_consume(location, pair, climber)).unwrap();
-            newpair
+            climber.climb(
+                pairs,
+                |pair| logical_consume(location.clone(), pair, climber),
+                |lhs, op, rhs| Ok(get_op_ast_node(&location, lhs?, op, rhs?)),
+            )?
         }
         Rule::comparison => {
             let mut inner_pairs = pair.into_inner();
-            let element = ast_build::build_ast_from_expr(location.clone(), inner_pairs.next().unwrap());
-            let compare = inner_pairs.next().unwrap();
-            let other = ast_build::build_ast_from_expr(location.clone(), inner_pairs.next().unwrap());
+            let element = ast_build::build_ast_from_expr(location.clone(), inner_pairs.next().unwrap())?;
+            let compare = match inner_pairs.next() {
+                Some(compare) => compare,
+                None => return Ok(element),
+            };
+            let other = ast_build::build_ast_from_expr(location.clone(), inner_pairs.next().unwrap())?;
             let compare_type = match compare.as_rule() {
                 Rule::two_equals => LogicalOperatorType::Equal,
+                Rule::not_equal => LogicalOperatorType::NotEqual,
                 Rule::bigger_than => LogicalOperatorType::BiggerThan,
                 Rule::smaller_than => LogicalOperatorType::SmallerThan,
                 Rule::e_bigger_than => LogicalOperatorType::EbiggerThan,
@@ -70,17 +86,7 @@ fn logical_consume(location: (String, usize, usize), pair: Pair<Rule>, climber:
             };
             AstNode::Comparison(location, Box::new(element), compare_type, Box::new(other))
         }
-        Rule::number => {
-            let number = pair.as_str().parse().unwrap();
-            AstNode::Number(location, number)
-        }
-        Rule::string => {
-            let str = &pair.as_str();
-            // Strip leading and ending quotes.
-            let str = &str[1..str.len() - 1];
-            let number = str.parse().unwrap();
-            AstNode::Number(location, number)
-        }
+        Rule::number => ast_build::build_number(location, pair)?,
         Rule::ident => {
             let ident = pair.as_str();
             AstNode::Ident(location, ident.to_string())
@@ -91,11 +97,11 @@ fn logical_consume(location: (String, usize, usize), pair: Pair<Rule>, climber:
             let next = pair.next();
             match next {
                 None => {
-                    function::function_call(location, function_name, vec![AstNode::Null])
+                    function::function_call(location, function_name, vec![])
                 },
                 _ => {
                     let expr = next.unwrap();
-                    let args: Vec<AstNode> = expr.into_inner().map(|v| ast_build::build_ast_from_expr(location.clone(), v)).collect();
+                    let args: Vec<AstNode> = expr.into_inner().map(|v| ast_build::build_ast_from_expr(location.clone(), v)).collect::<Result<_, _>>()?;
                     function::function_call(location, function_name, args)
                 }
             }
@@ -107,59 +113,49 @@ fn logical_consume(location: (String, usize, usize), pair: Pair<Rule>, climber:
                 _ => unreachable!()
             }
         }
-        _ => panic!("Unknown rule: {:?}", pair),
-    }
+        // Any value can be an operand of `&&` and `||`.
+        _ => ast_build::build_ast_from_expr(location, pair)?,
+    };
+    Ok(node)
 }
 
-fn calc_consume(location: (String, usize, usize), pair: Pair<Rule>, climber: &PrecClimber<Rule>) -> AstNode {
-    match pair.as_rule() {
-        Rule::calc_term => {
+fn calc_consume(location: (String, usize, usize), pair: Pair<Rule>, climber: &PrecClimber<Rule>) -> Result<AstNode, OranError> {
+    let location = ast_build::pair_location(&location, &pair);
+    let node = match pair.as_rule() {
+        Rule::calc_term | Rule::power_term => {
             let pairs = pair.into_inner();
-            climber.climb(pairs, |pair| calc_consume(location.clone(), pair, climber), get_op_ast_node)
+            climber.climb(
+                pairs,
+                |pair| calc_consume(location.clone(), pair, climber),
+                |lhs, op, rhs| Ok(get_op_ast_node(&location, lhs?, op, rhs?)),
+            )?
         }
         Rule::element => {
-            let newpair = pair.into_inner().next().map(|pair| calc_consume(location, pair, climber)).unwrap();
+            let newpair = calc_consume(location, pair.into_inner().next().unwrap(), climber)?;
             newpair
         },
-        Rule::string => {
-            let str = &pair.as_str();
-            // Strip leading and ending quotes.
-            let str = &str[1..str.len() - 1];
-            let number = str.parse().unwrap_or_else(|_x|{
-                println!("{}\n{}\nLine number: {}, column number:{}: This \"{}\" is not a number.",
-                    "Error!".red().bold(),    
-                    location.0,    
-                    location.1,
-                    location.2,
-                    str
-                );
-                process::exit(1);
-            });
-            AstNode::Number(location, number)
-        }
         Rule::ident => {
             let ident = pair.as_str();
             AstNode::Ident(location, ident.to_string())
         }
-        Rule::number => {
-            let number = pair.as_str().parse().unwrap();
-            AstNode::Number(location, number)
-        }
+        Rule::number => ast_build::build_number(location, pair)?,
         Rule::function_call => {
             let mut pair = pair.into_inner();
             let function_name = pair.next().unwrap();
             let next = pair.next();
             match next {
                 None => {
-                    function::function_call(location, function_name, vec![AstNode::Null])
+                    function::function_call(location, function_name, vec![])
                 },
                 _ => {
                     let expr = next.unwrap();
-                    let args: Vec<AstNode> = expr.into_inner().map(|v| ast_build::build_ast_from_expr(location.clone(), v)).collect();
+                    let args: Vec<AstNode> = expr.into_inner().map(|v| ast_build::build_ast_from_expr(location.clone(), v)).collect::<Result<_, _>>()?;
                     function::function_call(location, function_name, args)
                 }
             }
         }
-        _ => panic!("Unknown rule: {:?}", pair),
-    }
+        // Any other value is an operand too, like a string for `++`.
+        _ => ast_build::build_ast_from_expr(location, pair)?,
+    };
+    Ok(node)
 }
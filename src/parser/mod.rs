@@ -1,37 +1,152 @@
 pub mod astnode;
 pub mod function;
 pub mod calculation;
+pub mod string;
 mod ast_build;
-use astnode::AstNode;
+use astnode::{AstNode, MatchArm, Pattern};
 use pest::Parser;
-use pest::error::Error;
+use pest::iterators::Pairs;
+use pest::error::InputLocation;
+use crate::value::oran_error::OranError;
+use crate::value::oran_warning::OranWarning;
 
 #[derive(Parser)]
 #[grammar = "grammer/oran.pest"]
 pub struct OParser;
 
-pub fn parse(filename: &str, source: &str) -> Result<Vec<Box<AstNode>>, Error<Rule>> {
+/// Parses a program and tells whether it ends with an expression without `;`, which is the value of the program.
+pub fn parse(filename: &str, source: &str) -> Result<(Vec<AstNode>, bool), OranError> {
+    let result = OParser::parse(Rule::program, source);
+    let pairs = ast_build::get_pairs(filename.to_string(), result)?;
+    build_program(filename, pairs)
+}
+
+/// Finds what is probably a mistake in a program, like a `match` on booleans that misses `true` or `false`.
+pub fn warnings(ast: &[AstNode]) -> Vec<OranWarning> {
+    let mut warnings = vec![];
+    for node in ast {
+        collect_warnings(node, &mut warnings);
+    }
+    warnings
+}
+
+fn collect_warnings(node: &AstNode, warnings: &mut Vec<OranWarning>) {
+    if let AstNode::Match(location, _, arms) = node {
+        if let Some(missing) = missing_boolean(arms) {
+            let message = format!("This match on booleans does not cover {}. Add an arm for it or for _.", missing);
+            warnings.push(OranWarning::new(location, &message));
+        }
+    }
+    for child in node.children() {
+        collect_warnings(child, warnings);
+    }
+}
+
+/// The boolean that a `match` misses when all of its patterns are `true` or `false`.
+fn missing_boolean(arms: &[MatchArm]) -> Option<bool> {
+    let mut covered = [false, false];
+    let mut patterns = arms.iter().flat_map(|arm| &arm.patterns).peekable();
+    patterns.peek()?;
+    for pattern in patterns {
+        match pattern {
+            Pattern::Literal(AstNode::Bool(_, b)) => covered[*b as usize] = true,
+            _ => return None,
+        }
+    }
+    if !covered[0] {
+        Some(false)
+    } else if !covered[1] {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// Whether the source ends before an expression is complete, like an unclosed `fn` or `if`.
+/// A complete source may still have syntax errors.
+pub fn is_incomplete(source: &str) -> bool {
+    match OParser::parse(Rule::program, source) {
+        Ok(_) => false,
+        Err(e) => {
+            let position = match e.location {
+                InputLocation::Pos(position) | InputLocation::Span((position, _)) => position,
+            };
+            // pest reports the furthest position it reached, so an unfinished input usually fails at its end.
+            // A block can also fail before its end, at the last statement, while its braces are still open.
+            position >= source.trim_end().len() || has_unclosed_bracket(source)
+        }
+    }
+}
+
+fn build_program(filename: &str, pairs: Pairs<Rule>) -> Result<(Vec<AstNode>, bool), OranError> {
     let mut ast = vec![];
+    let mut is_trailing_expr = false;
+    for inner_pair in pairs {
+        match inner_pair.as_rule() {
+            Rule::expr | Rule::expr_without_end_mark | Rule::last_stmt => {
+                // The last statement without ";" is the value of the program.
+                is_trailing_expr = inner_pair.as_rule() == Rule::last_stmt
+                    || (inner_pair.as_rule() == Rule::expr_without_end_mark && ast_build::is_match_without_end_mark(&inner_pair));
+                for expr in inner_pair.into_inner() {
+                    let location = (filename.to_owned(), 0, 0);
+                    ast.push(ast_build::build_ast_from_expr(location, expr)?);
+                }
+            }
+            _ => {}
+        }
+    }
 
-    let result = OParser::parse(Rule::program, source);
-    let pairs = ast_build::get_pairs(filename.to_string(), result);
-    if pairs != None {
-        for pair in pairs {
-            for inner_pair in pair {
-                match inner_pair.as_rule() {
-                    Rule::expr | Rule::expr_without_end_mark => {
-                        for expr in inner_pair.into_inner() {
-                            let span = expr.as_span();
-                            let location = span.start_pos().line_col();
-                            let location = (filename.to_owned(), location.0, location.1);
-                            ast.push(Box::new(ast_build::build_ast_from_expr(location, expr)));
+    Ok((ast, is_trailing_expr))
+}
+
+/// Whether a bracket is left open, ignoring the ones in strings and comments.
+fn has_unclosed_bracket(source: &str) -> bool {
+    let mut depth = 0;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            // A raw string has no escapes and ends with the quote and the #s it started with.
+            'r' if chars.peek() == Some(&'"') || chars.peek() == Some(&'#') => {
+                let mut closing = String::from("\"");
+                while chars.next_if_eq(&'#').is_some() {
+                    closing.push('#');
+                }
+                if chars.next_if_eq(&'"').is_some() {
+                    let mut content = String::new();
+                    for d in chars.by_ref() {
+                        content.push(d);
+                        if content.ends_with(&closing) {
+                            break;
                         }
                     }
-                    _ => {}
                 }
             }
+            '\'' | '"' => {
+                while let Some(d) = chars.next() {
+                    if d == '\\' {
+                        chars.next();
+                    } else if d == c {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.next_if(|&d| d != '\n').is_some() {}
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for d in chars.by_ref() {
+                    if prev == '*' && d == '/' {
+                        break;
+                    }
+                    prev = d;
+                }
+            }
+            _ => {}
         }
     }
-
-    Ok(ast)
+    depth > 0
 }
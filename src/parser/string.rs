@@ -0,0 +1,123 @@
+use pest::iterators::Pair;
+use super::astnode::AstNode;
+use crate::value::oran_error::OranError;
+use super::{Rule, ast_build};
+
+pub fn build_string(location: (String, usize, usize), pair: Pair<Rule>) -> Result<AstNode, OranError> {
+    let string = pair.into_inner().next().unwrap();
+    let text = match string.as_rule() {
+        Rule::raw_string => {
+            string.into_inner().next().unwrap().as_str().to_owned()
+        }
+        Rule::triple_quote_string => {
+            let content = string.into_inner().next().unwrap();
+            let span = content.as_span();
+            // The escapes are checked where they are written, and decoded after the indentation is removed.
+            for pair in content.clone().into_inner() {
+                if pair.as_rule() == Rule::escape_sequence {
+                    unescape(pair.as_str()).map_err(|message| ast_build::custom_error(&location.0, message, pair.as_span()))?;
+                }
+            }
+            decode(&strip_indent(content.as_str()))
+                .map_err(|message| ast_build::custom_error(&location.0, message, span))?
+        }
+        _ => {
+            // Interpolated expressions split the string into parts that are concatenated like `<<`.
+            let mut parts: Vec<AstNode> = Vec::new();
+            let mut text = String::new();
+            for pair in string.into_inner() {
+                match pair.as_rule() {
+                    Rule::escape_sequence => {
+                        let c = unescape(pair.as_str())
+                            .map_err(|message| ast_build::custom_error(&location.0, message, pair.as_span()))?;
+                        text.push(c);
+                    }
+                    Rule::interpolation => {
+                        if !text.is_empty() {
+                            parts.push(AstNode::Str(location.clone(), std::mem::take(&mut text)));
+                        }
+                        let expr = pair.into_inner().next().unwrap();
+                        parts.push(ast_build::build_ast_from_expr(location.clone(), expr)?);
+                    }
+                    _ => text.push_str(pair.as_str()),
+                }
+            }
+            if parts.is_empty() {
+                text
+            } else {
+                if !text.is_empty() {
+                    parts.push(AstNode::Str(location.clone(), text));
+                }
+                return Ok(AstNode::Strs(location, parts));
+            }
+        }
+    };
+    Ok(AstNode::Str(location, text))
+}
+
+/// Decodes one escape sequence like `\n` or `\u{1F600}`. `\$` is a `$` that does not start `${...}`.
+fn unescape(sequence: &str) -> Result<char, String> {
+    let c = match &sequence[1..] {
+        "n" => '\n',
+        "t" => '\t',
+        "r" => '\r',
+        "0" => '\0',
+        "\\" => '\\',
+        "'" => '\'',
+        "\"" => '"',
+        "$" => '$',
+        code if code.starts_with("u{") && code.ends_with('}') => {
+            let hex = &code[2..code.len() - 1];
+            let c = if (1..=6).contains(&hex.len()) {
+                u32::from_str_radix(hex, 16).ok().and_then(std::char::from_u32)
+            } else {
+                None
+            };
+            match c {
+                Some(c) => c,
+                None => return Err(format!("Invalid unicode escape: {}. It must be 1 to 6 hex digits of a unicode scalar value, like \\u{{1F600}}.", sequence)),
+            }
+        }
+        _ => return Err(format!("Unknown escape sequence: {}", sequence)),
+    };
+    Ok(c)
+}
+
+/// Decodes all the escape sequences in a text.
+fn decode(text: &str) -> Result<String, String> {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('\\') {
+        decoded.push_str(&rest[..at]);
+        let escape = &rest[at..];
+        let len = if escape.starts_with("\\u{") {
+            escape.find('}').map_or(2, |close| close + 1)
+        } else {
+            1 + escape[1..].chars().next().map_or(0, char::len_utf8)
+        };
+        decoded.push(unescape(&escape[..len])?);
+        rest = &escape[len..];
+    }
+    decoded.push_str(rest);
+    Ok(decoded)
+}
+
+/// Removes the line break after the opening quotes, the last line if it has only the indentation
+/// of the closing quotes, and the indentation that all the lines have in common.
+fn strip_indent(text: &str) -> String {
+    let text = text.strip_prefix("\r\n").or_else(|| text.strip_prefix('\n')).unwrap_or(text);
+    let mut lines: Vec<&str> = text.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)).collect();
+    if lines.len() > 1 && lines.last().unwrap().trim().is_empty() {
+        lines.pop();
+    }
+    let is_indent = |c: char| c == ' ' || c == '\t';
+    let indent = lines.iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches(is_indent).len())
+        .min()
+        .unwrap_or(0);
+    lines.iter()
+        .map(|line| if line.trim().is_empty() { "" } else { &line[indent..] })
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
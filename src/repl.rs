@@ -0,0 +1,61 @@
+use std::io::{self, BufRead, Write};
+use oran::{Engine, Backend, OranValue};
+
+const FILENAME: &str = "<repl>";
+
+/// Reads programs from stdin and runs them one by one in the same environment.
+/// An entry continues on the next line while it is incomplete, like an unclosed `fn` or `if`.
+/// Prompts go to stderr so that stdout has only what the programs print.
+pub fn start(mut engine: Engine) {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut source = String::new();
+    let backend = if engine.backend() == Backend::Vm { "vm" } else { "tree" };
+    eprintln!("oran {} ({} backend). Type :quit to exit.", env!("CARGO_PKG_VERSION"), backend);
+    loop {
+        eprint!("{}", if source.is_empty() { ">> " } else { ".. " });
+        io::stderr().flush().unwrap();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        if source.is_empty() {
+            match line.trim() {
+                "" => continue,
+                ":quit" => break,
+                ":reset" => {
+                    engine.reset();
+                    continue;
+                }
+                ":env" => {
+                    for (name, val) in engine.globals() {
+                        println!("{} = {}", name, val);
+                    }
+                    for name in engine.functions() {
+                        println!("fn {}", name);
+                    }
+                    continue;
+                }
+                command if command.starts_with(':') => {
+                    eprintln!("Unknown command: {}. Available commands are :quit, :reset and :env.", command);
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        source.push_str(&line);
+        source.push('\n');
+        // An empty line finishes an incomplete entry, to show the syntax error.
+        if !line.trim().is_empty() && Engine::is_incomplete(&source) {
+            continue;
+        }
+        let result = engine.eval_named(FILENAME, &source);
+        crate::print_warnings(engine.warnings());
+        match result {
+            Ok(OranValue::Null) => {}
+            Ok(val) => println!("{}", val),
+            Err(e) => crate::print_error(&e),
+        }
+        source.clear();
+    }
+}
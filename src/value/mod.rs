@@ -3,3 +3,5 @@ pub mod oran_variable;
 pub mod oran_string;
 pub mod var_type;
 pub mod scope;
+pub mod oran_error;
+pub mod oran_warning;
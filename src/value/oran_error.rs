@@ -0,0 +1,105 @@
+use std::fmt;
+
+/// What went wrong. Hosts can match on this instead of parsing the message.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ErrorKind {
+    Syntax,
+    UndefinedVariable,
+    UndefinedFunction,
+    MissingArgument,
+    ConstantAssignment,
+    AssignmentWithoutLet,
+    Type,
+    Index,
+    /// Integer or decimal overflow, division by zero, or a shift by a negative amount.
+    Arithmetic,
+    LoopControl,
+    Io,
+    /// A module that is not found, or that imports itself through other modules.
+    Import,
+    /// A value thrown with `throw` that no `catch` caught.
+    Thrown,
+}
+
+impl ErrorKind {
+    /// The kind with the given name, which is how `Display` shows it.
+    pub fn from_name(name: &str) -> Option<ErrorKind> {
+        let kind = match name {
+            "Syntax" => ErrorKind::Syntax,
+            "UndefinedVariable" => ErrorKind::UndefinedVariable,
+            "UndefinedFunction" => ErrorKind::UndefinedFunction,
+            "MissingArgument" => ErrorKind::MissingArgument,
+            "ConstantAssignment" => ErrorKind::ConstantAssignment,
+            "AssignmentWithoutLet" => ErrorKind::AssignmentWithoutLet,
+            "Type" => ErrorKind::Type,
+            "Index" => ErrorKind::Index,
+            "Arithmetic" => ErrorKind::Arithmetic,
+            "LoopControl" => ErrorKind::LoopControl,
+            "Io" => ErrorKind::Io,
+            "Import" => ErrorKind::Import,
+            "Thrown" => ErrorKind::Thrown,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// An error raised while parsing or running a program.
+/// Errors from the operators on `OranValue` do not know where they happened,
+/// so the interpreter fills in the location with `at`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct OranError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub location: Option<(String, usize, usize)>,
+}
+
+impl OranError {
+    pub fn new(kind: ErrorKind, message: &str) -> Self {
+        OranError {
+            kind,
+            message: message.to_owned(),
+            location: None,
+        }
+    }
+
+    pub fn located(kind: ErrorKind, location: &(String, usize, usize), message: &str) -> Self {
+        OranError {
+            kind,
+            message: message.to_owned(),
+            location: Some(location.clone()),
+        }
+    }
+
+    /// Sets the location if the error does not have one yet.
+    pub fn at(mut self, location: &(String, usize, usize)) -> Self {
+        if self.location.is_none() {
+            self.location = Some(location.clone());
+        }
+        self
+    }
+}
+
+impl fmt::Display for OranError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.location {
+            // pest already shows where the syntax error is.
+            Some(ref location) if self.kind == ErrorKind::Syntax => write!(f, "{}{}", location.0, self.message),
+            Some(ref location) => write!(f, "{}\nLine number: {}, column number:{}: {}",
+                location.0,
+                location.1,
+                location.2,
+                self.message
+            ),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for OranError {}
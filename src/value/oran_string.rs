@@ -1,76 +1,74 @@
 use std::fmt::{Display, Formatter, Result};
-use std::hash::Hash;
+use std::borrow::Borrow;
+use std::rc::Rc;
 use super::oran_value::OranValue;
-use std::borrow::Cow;
 
-#[derive(Clone, Hash, Debug)]
-pub struct OranString<'a> {
-    pub val_str: Cow<'a, str>,
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct OranString {
+    pub val_str: Rc<str>,
 }
 
-impl PartialEq for OranString<'_> {
-    fn eq(&self, other: &Self) -> bool {
-        self.val_str.as_ref() == other.val_str.as_ref()
+impl Borrow<str> for OranString {
+    fn borrow(&self) -> &str {
+        self.val_str.as_ref()
     }
 }
 
-impl Eq for OranString<'_> {}
-
-impl<'a> From<&'a String> for OranString<'a> {
-    fn from(val: &'a String) -> Self {
+impl From<&String> for OranString {
+    fn from(val: &String) -> Self {
         OranString {
-            val_str:Cow::from(val),
+            val_str: Rc::from(val.as_str()),
         }
     }
 }
 
-impl<'a> From<String> for OranString<'a> {
+impl From<String> for OranString {
     fn from(val: String) -> Self {
         OranString {
-            val_str: Cow::from(val),
+            val_str: Rc::from(val),
         }
     }
 }
 
-impl<'a> From<&'a str> for OranString<'a> {
-    fn from(val: &'a str) -> Self {
+impl From<&str> for OranString {
+    fn from(val: &str) -> Self {
         OranString {
-            val_str: Cow::from(val),
+            val_str: Rc::from(val),
         }
     }
 }
 
-impl<'a> From<OranString<'a>> for String {
+impl From<OranString> for String {
     fn from(val: OranString) -> Self {
         val.val_str.to_string()
     }
 }
 
-impl<'a> From<&OranString<'a>> for String {
+impl From<&OranString> for String {
     fn from(val: &OranString) -> Self {
         val.val_str.as_ref().to_string()
     }
 }
 
-impl<'a> From<OranValue<'a>> for OranString<'a> {
-    fn from(val: OranValue<'a>) -> Self {
+impl From<OranValue> for OranString {
+    fn from(val: OranValue) -> Self {
         let val = String::from(val);
         OranString {
-            val_str: Cow::from(val),
+            val_str: Rc::from(val),
         }
     }
 }
 
-impl<'a> From<&OranValue<'a>> for OranString<'a> {
-    fn from(val: &OranValue<'a>) -> Self {
+impl From<&OranValue> for OranString {
+    fn from(val: &OranValue) -> Self {
         let val = String::from(val);
         OranString {
-            val_str: Cow::from(val),
+            val_str: Rc::from(val),
         }
     }
 }
 
-impl Display for OranString<'_> {
+impl Display for OranString {
     fn fmt(&self, f: &mut Formatter) -> Result {
         write!(f, "{}", self.val_str)           
     }
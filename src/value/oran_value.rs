@@ -1,338 +1,1007 @@
 use std::fmt;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use indexmap::IndexMap;
 use std::cmp::{PartialOrd, Ordering};
-use std::ops::{Add, Sub, Div, Mul, Rem};
+use std::convert::TryFrom;
+use std::ops::{Add, Sub, Div, Mul, Rem, Neg, BitAnd, BitOr, BitXor, Shl, Shr, Not};
 use num_traits::pow::Pow;
+use num_traits::{Zero, Signed, ToPrimitive, FromPrimitive};
+use num_bigint::BigInt;
+use rust_decimal::{Decimal, MathematicalOps};
 use super::oran_variable::{OranVariable, OranVariableValue};
 use super::oran_string::OranString;
+use super::scope::Scope;
+use super::var_type::FunctionOrValueType;
+use super::oran_error::{OranError, ErrorKind};
 use crate::parser::astnode::AstNode;
+use crate::vm::{Vm, VmFunction};
 
 #[derive(Debug)]
-pub enum OranValue<'a> {
+pub enum OranValue {
+    Int(i64),
+    BigInt(BigInt),
+    Decimal(Decimal),
     Float(f64),
-    Str(OranString<'a>),
+    Str(OranString),
     Boolean(bool),
-    Variable(OranVariable<'a>),
-    Function(FunctionDefine<'a>),
+    List(OranList),
+    Map(OranMap),
+    Struct(OranStruct),
+    StructDefine(Rc<StructDefine>),
+    Enum(OranEnum),
+    EnumDefine(Rc<EnumDefine>),
+    Module(Rc<OranModule>),
+    Variable(OranVariable),
+    Function(FunctionDefine),
+    VmFunction(VmFunction),
+    Native(NativeFunction),
     Null
 }
 
-impl Pow<OranValue<'_>> for OranValue<'_> {
-    type Output = Self;
+/// A number taken out of an operand. Integers stay exact until they meet a float.
+#[derive(Debug, Clone)]
+enum Number {
+    Int(i64),
+    BigInt(BigInt),
+    Decimal(Decimal),
+    Float(f64),
+}
 
-    fn pow(self, exp: OranValue) -> Self::Output {
+impl Number {
+    fn as_f64(&self) -> f64 {
         match self {
-            OranValue::Float(ref fl) => { OranValue::Float(fl.powf(f64::from(exp))) },
-            OranValue::Str(ref s) => OranValue::Float(s.val_str.as_ref().parse::<f64>().unwrap_or_else(|e| panic!("{}", e)).powf(f64::from(exp))),
-            OranValue::Variable(ref v) => { 
-                match v.value {
-                    OranVariableValue::Float(ref vfl) => { OranValue::Float(vfl.powf(f64::from(exp))) },
-                    OranVariableValue::Str(ref s) => OranValue::Float(s.val_str.as_ref().parse::<f64>().unwrap_or_else(|e| panic!("{}", e)).powf(f64::from(exp))),
-                    _ => panic!("Variable types are not Number: {:?}", self)
-                }
-            },
-            _ => panic!("Variable types are not Number: {:?}", self)
+            Number::Int(i) => *i as f64,
+            Number::BigInt(b) => b.to_f64().unwrap_or(f64::NAN),
+            Number::Decimal(d) => d.to_f64().unwrap_or(f64::NAN),
+            Number::Float(fl) => *fl,
+        }
+    }
+
+    /// Converts the number to a bigint. Only whole numbers can be converted.
+    fn as_bigint(&self) -> Result<BigInt, OranError> {
+        let bigint = match self {
+            Number::Int(i) => Some(BigInt::from(*i)),
+            Number::BigInt(b) => Some(b.clone()),
+            Number::Decimal(d) if d.fract().is_zero() => d.to_i128().map(BigInt::from),
+            Number::Float(fl) if fl.fract() == 0.0 => BigInt::from_f64(*fl),
+            _ => None,
+        };
+        bigint.ok_or_else(|| OranError::new(ErrorKind::Type, &format!("This \"{}\" is not an integer.", self)))
+    }
+
+    /// Converts the number to a decimal. A float is converted from the digits it is shown with,
+    /// so `0.1` becomes exactly 0.1.
+    fn as_decimal(&self) -> Result<Decimal, OranError> {
+        let decimal = match self {
+            Number::Int(i) => Some(Decimal::from(*i)),
+            Number::BigInt(b) => Decimal::from_str_exact(&b.to_string()).ok(),
+            Number::Decimal(d) => Some(*d),
+            Number::Float(fl) => Decimal::from_str_exact(&fl.to_string()).ok(),
+        };
+        decimal.ok_or_else(|| OranError::new(ErrorKind::Arithmetic, &format!("This \"{}\" cannot be represented as a decimal.", self)))
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Number::Int(i) => write!(f, "{}", i),
+            Number::BigInt(b) => write!(f, "{}", b),
+            Number::Decimal(d) => write!(f, "{}", d),
+            Number::Float(fl) => write!(f, "{}", fl),
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Number) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Number) -> Option<Ordering> {
+        match promote(self, "<", other) {
+            Ok(operands) => operands.compare(),
+            // A decimal and a float cannot be calculated together, but they can be compared as floats.
+            Err(_e) => self.as_f64().partial_cmp(&other.as_f64()),
         }
     }
 }
 
+/// Both operands of an operator, converted to the same type.
+enum Operands {
+    Int(i64, i64),
+    BigInt(BigInt, BigInt),
+    Decimal(Decimal, Decimal),
+    Float(f64, f64),
+}
+
+impl Operands {
+    fn compare(&self) -> Option<Ordering> {
+        match self {
+            Operands::Int(a, b) => a.partial_cmp(b),
+            Operands::BigInt(a, b) => a.partial_cmp(b),
+            Operands::Decimal(a, b) => a.partial_cmp(b),
+            Operands::Float(a, b) => a.partial_cmp(b),
+        }
+    }
+}
+
+/// Converts the operands to the type the operator calculates with.
+/// An integer and a bigint are calculated as bigints, a decimal and an integer or a bigint as decimals,
+/// and a float and an integer or a bigint as floats. A decimal and a float cannot be mixed,
+/// because the result would be neither exact nor a float.
+fn promote(lhs: &Number, op: &str, rhs: &Number) -> Result<Operands, OranError> {
+    let operands = match (lhs, rhs) {
+        (Number::Int(a), Number::Int(b)) => Operands::Int(*a, *b),
+        (Number::Decimal(_), Number::Float(_)) | (Number::Float(_), Number::Decimal(_)) => {
+            return Err(OranError::new(
+                ErrorKind::Type,
+                &format!("A decimal and a float cannot be calculated together: {} {} {}. Convert the float with decimal().", lhs, op, rhs),
+            ));
+        }
+        (Number::Float(_), _) | (_, Number::Float(_)) => Operands::Float(lhs.as_f64(), rhs.as_f64()),
+        (Number::Decimal(_), _) | (_, Number::Decimal(_)) => Operands::Decimal(lhs.as_decimal()?, rhs.as_decimal()?),
+        _ => Operands::BigInt(lhs.as_bigint()?, rhs.as_bigint()?),
+    };
+    Ok(operands)
+}
+
+/// Takes the operands of an arithmetic operator. Integers and floats, which most calculations have,
+/// are taken directly without looking for bigints and decimals.
+#[inline]
+fn operands(lhs: &OranValue, op: &str, rhs: &OranValue) -> Result<Operands, OranError> {
+    match (lhs, rhs) {
+        (OranValue::Int(a), OranValue::Int(b)) => Ok(Operands::Int(*a, *b)),
+        (OranValue::Float(a), OranValue::Float(b)) => Ok(Operands::Float(*a, *b)),
+        (OranValue::Float(a), OranValue::Int(b)) => Ok(Operands::Float(*a, *b as f64)),
+        (OranValue::Int(a), OranValue::Float(b)) => Ok(Operands::Float(*a as f64, *b)),
+        _ => promote(&number(lhs)?, op, &number(rhs)?),
+    }
+}
+
+/// Converts an operand of an arithmetic operator to a number.
+/// A string is read as an integer if it can be, and as a float otherwise.
+fn number(val: &OranValue) -> Result<Number, OranError> {
+    match val {
+        OranValue::Int(i) => Ok(Number::Int(*i)),
+        OranValue::BigInt(b) => Ok(Number::BigInt(b.clone())),
+        OranValue::Decimal(d) => Ok(Number::Decimal(*d)),
+        OranValue::Float(fl) => Ok(Number::Float(*fl)),
+        OranValue::Str(s) => {
+            let s = s.val_str.as_ref();
+            s.parse().map(Number::Int).or_else(|_e| s.parse().map(Number::Float)).map_err(|_e| not_a_number(val))
+        }
+        OranValue::Variable(v) => number(&OranValue::from(&v.value)),
+        _ => Err(not_a_number(val)),
+    }
+}
+
+/// Converts a value to a bigint, which is what `bigint(x)` does.
+/// A string is read as it is written, so it can have more digits than an integer.
+pub fn to_bigint(val: &OranValue) -> Result<OranValue, OranError> {
+    if let OranValue::Str(s) = val {
+        if let Ok(bigint) = s.val_str.parse() {
+            return Ok(OranValue::BigInt(bigint));
+        }
+    }
+    number(val)?.as_bigint().map(OranValue::BigInt)
+}
+
+/// Converts a value to a decimal, which is what `decimal(x)` does.
+/// A string is read as it is written, so `decimal("0.10")` keeps its two decimal places.
+pub fn to_decimal(val: &OranValue) -> Result<OranValue, OranError> {
+    if let OranValue::Str(s) = val {
+        if let Ok(decimal) = Decimal::from_str_exact(&s.val_str) {
+            return Ok(OranValue::Decimal(decimal));
+        }
+    }
+    number(val)?.as_decimal().map(OranValue::Decimal)
+}
 
-impl Clone for OranValue<'_> {
+/// Converts a value to a number, which is what `+x` does. A string is read like in a calculation.
+pub fn to_number(val: &OranValue) -> Result<OranValue, OranError> {
+    let number = match number(val)? {
+        Number::Int(i) => OranValue::Int(i),
+        Number::BigInt(b) => OranValue::BigInt(b),
+        Number::Decimal(d) => OranValue::Decimal(d),
+        Number::Float(fl) => OranValue::Float(fl),
+    };
+    Ok(number)
+}
+
+impl Neg for OranValue {
+    type Output = Result<OranValue, OranError>;
+
+    fn neg(self) -> Self::Output {
+        match number(&self)? {
+            Number::Int(i) => i.checked_neg().map(OranValue::Int)
+                .ok_or_else(|| OranError::new(ErrorKind::Arithmetic, &format!("Integer overflow: -({})", i))),
+            Number::BigInt(b) => Ok(OranValue::BigInt(-b)),
+            Number::Decimal(d) => Ok(OranValue::Decimal(-d)),
+            Number::Float(fl) => Ok(OranValue::Float(-fl)),
+        }
+    }
+}
+
+fn overflow(lhs: &dyn fmt::Display, op: &str, rhs: &dyn fmt::Display) -> OranError {
+    OranError::new(ErrorKind::Arithmetic, &format!("Integer overflow: {} {} {}", lhs, op, rhs))
+}
+
+fn decimal_overflow(lhs: &Decimal, op: &str, rhs: &Decimal) -> OranError {
+    OranError::new(ErrorKind::Arithmetic, &format!("Decimal overflow: {} {} {}", lhs, op, rhs))
+}
+
+fn division_by_zero() -> OranError {
+    OranError::new(ErrorKind::Arithmetic, "Division by zero.")
+}
+
+impl Pow<OranValue> for OranValue {
+    type Output = Result<OranValue, OranError>;
+
+    /// An integer or a bigint to the power of a non-negative integer stays exact, and a negative exponent gives a float.
+    /// A decimal can be raised only to whole powers, so that the result is exact.
+    fn pow(self, exp: OranValue) -> Self::Output {
+        match operands(&self, "^", &exp)? {
+            Operands::Int(a, b) if b >= 0 => {
+                u32::try_from(b).ok()
+                    .and_then(|b| a.checked_pow(b))
+                    .map(OranValue::Int)
+                    .ok_or_else(|| overflow(&a, "^", &b))
+            }
+            Operands::Int(a, b) => Ok(OranValue::Float((a as f64).powf(b as f64))),
+            Operands::BigInt(a, b) if !b.is_negative() => {
+                match u32::try_from(&b) {
+                    Ok(e) => Ok(OranValue::BigInt(a.pow(e))),
+                    Err(_e) => Err(overflow(&a, "^", &b)),
+                }
+            }
+            Operands::BigInt(a, b) => Ok(OranValue::Float(Number::BigInt(a).as_f64().powf(Number::BigInt(b).as_f64()))),
+            Operands::Decimal(a, b) => {
+                if !b.fract().is_zero() {
+                    return Err(OranError::new(ErrorKind::Type, &format!("A decimal can be raised only to whole powers: {} ^ {}", a, b)));
+                }
+                if a.is_zero() && b.is_sign_negative() {
+                    return Err(division_by_zero());
+                }
+                b.to_i64()
+                    .and_then(|e| a.checked_powi(e))
+                    .map(OranValue::Decimal)
+                    .ok_or_else(|| decimal_overflow(&a, "^", &b))
+            }
+            Operands::Float(a, b) => Ok(OranValue::Float(a.powf(b))),
+        }
+    }
+}
+
+impl Clone for OranValue {
     fn clone(&self) -> Self {
         match self {
+            OranValue::Int(a) => OranValue::Int(*a),
+            OranValue::BigInt(a) => OranValue::BigInt(a.clone()),
+            OranValue::Decimal(a) => OranValue::Decimal(*a),
             OranValue::Float(a) => OranValue::Float(*a),
             OranValue::Str(a) => OranValue::Str(a.clone()),
             OranValue::Boolean(a) => OranValue::Boolean(*a),
+            OranValue::List(a) => OranValue::List(a.clone()),
+            OranValue::Map(a) => OranValue::Map(a.clone()),
+            OranValue::Struct(a) => OranValue::Struct(a.clone()),
+            OranValue::Enum(a) => OranValue::Enum(a.clone()),
+            OranValue::StructDefine(a) => OranValue::StructDefine(a.clone()),
+            OranValue::EnumDefine(a) => OranValue::EnumDefine(a.clone()),
+            OranValue::Module(a) => OranValue::Module(a.clone()),
             OranValue::Variable(a) => OranValue::Variable(a.clone()),
-            OranValue::Function(a) => OranValue::Function(*a),
+            OranValue::Function(a) => OranValue::Function(a.clone()),
+            OranValue::VmFunction(a) => OranValue::VmFunction(a.clone()),
+            OranValue::Native(a) => OranValue::Native(a.clone()),
             OranValue::Null => OranValue::Null
         }
     }
 }
 
-#[derive(PartialEq, Clone, Copy, Debug)]
-pub struct FunctionDefine<'a> {
-    pub name: &'a str,
-    pub args: &'a Vec<AstNode>,
-    pub fn_return: &'a Box<AstNode>,
-    pub body: &'a Vec<AstNode>,
+/// A list is copied when it is stored in a variable, an element or a field, so it is changed only through the place that holds it.
+pub type OranList = Rc<RefCell<Vec<OranValue>>>;
+
+/// Maps keep their insertion order, so iterating over them is deterministic.
+pub type OranMap = Rc<RefCell<IndexMap<MapKey, OranValue>>>;
+
+/// A key of a map. Keys keep their types, so `m[1]` and `m["1"]` are different entries
+/// and `keys(m)` gives back the values the entries were made with.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Int(i64),
+    BigInt(BigInt),
+    Decimal(Decimal),
+    /// The bits of a float, with `-0.0` as `0.0`, so that floats are compared and hashed like the other keys.
+    Float(u64),
+    Str(OranString),
+    Boolean(bool),
+}
+
+impl fmt::Display for MapKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_element(f, &OranValue::from(self))
+    }
+}
+
+/// A struct declared like `struct Point { x, y }`.
+/// Methods are added by `impl` blocks after the declaration, so they can change while the struct is shared.
+#[derive(Debug)]
+pub struct StructDefine {
+    pub name: OranString,
+    pub fields: Vec<String>,
+    pub methods: RefCell<IndexMap<String, OranValue>>,
+}
+
+/// An instance of a struct, whose fields are in the order of the declaration.
+/// Like lists, instances are copied when they are stored, so a field set through one variable is not seen through the others.
+#[derive(Clone, Debug)]
+pub struct OranStruct {
+    pub define: Rc<StructDefine>,
+    pub fields: Rc<RefCell<Vec<OranValue>>>,
+}
+
+impl PartialEq for OranStruct {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.define, &other.define)
+            && eq_once(address(&self.fields), address(&other.fields), || *self.fields.borrow() == *other.fields.borrow())
+    }
+}
+
+/// An enum declared like `enum Shape { Circle(r), Rect(w, h) }`, with the names of the values of each variant.
+#[derive(Debug)]
+pub struct EnumDefine {
+    pub name: OranString,
+    pub variants: Vec<(String, Vec<String>)>,
+}
+
+impl EnumDefine {
+    pub fn variant_position(&self, variant: &str) -> Option<usize> {
+        self.variants.iter().position(|(name, _)| name == variant)
+    }
+}
+
+/// A value of an enum like `Shape::Circle(1)`. Its values cannot be changed, so they are shared between copies.
+#[derive(Clone, Debug)]
+pub struct OranEnum {
+    pub define: Rc<EnumDefine>,
+    pub variant: usize,
+    pub values: Rc<Vec<OranValue>>,
+}
+
+impl PartialEq for OranEnum {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.define, &other.define) && self.variant == other.variant && self.values == other.values
+    }
+}
+
+impl OranEnum {
+    pub fn variant_name(&self) -> &str {
+        &self.define.variants[self.variant].0
+    }
+}
+
+/// The globals of a module after it has run. They are kept, so that the members of the module are what its globals are now.
+pub enum ModuleGlobals {
+    Tree(Scope),
+    Vm(Box<Vm>),
+}
+
+/// A program imported with `import`. Its global variables and functions are its members, like `util.name`.
+pub struct OranModule {
+    pub name: OranString,
+    pub path: String,
+    pub globals: ModuleGlobals,
+}
+
+impl OranModule {
+    /// The global variable or function of the module with the name.
+    pub fn member(&self, kind: FunctionOrValueType, name: &str) -> Option<OranValue> {
+        match self.globals {
+            ModuleGlobals::Tree(ref scope) => scope.get(kind, name),
+            ModuleGlobals::Vm(ref vm) => vm.get_global(kind, name),
+        }
+    }
+}
+
+impl fmt::Debug for OranModule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "OranModule({})", self.path)
+    }
+}
+
+thread_local! {
+    /// `Option` and `Result`, which are defined without declarations.
+    /// `Some(x)`, `None`, `Ok(x)` and `Err(e)` are their variants.
+    static PRELUDE: [Rc<EnumDefine>; 2] = [
+        Rc::new(EnumDefine {
+            name: OranString::from("Option"),
+            variants: vec![("Some".to_owned(), vec!["value".to_owned()]), ("None".to_owned(), vec![])],
+        }),
+        Rc::new(EnumDefine {
+            name: OranString::from("Result"),
+            variants: vec![("Ok".to_owned(), vec!["value".to_owned()]), ("Err".to_owned(), vec!["error".to_owned()])],
+        }),
+    ];
+}
+
+thread_local! {
+    /// The struct of the values that `catch` gets, which tell what went wrong and where.
+    /// `value` is the value given to `throw`, or `null` for an error of the language.
+    static ERROR: Rc<StructDefine> = Rc::new(StructDefine {
+        name: OranString::from("Error"),
+        fields: ["kind", "message", "value", "file", "line", "column"].iter().map(|field| field.to_string()).collect(),
+        methods: RefCell::new(IndexMap::new()),
+    });
+}
+
+pub fn error_define() -> Rc<StructDefine> {
+    ERROR.with(Rc::clone)
+}
+
+/// The enum of the prelude with the given name, which is used when the name is not declared in the program.
+pub fn prelude_enum(name: &str) -> Option<Rc<EnumDefine>> {
+    PRELUDE.with(|prelude| prelude.iter().find(|define| define.name.val_str.as_ref() == name).cloned())
+}
+
+impl OranValue {
+    fn prelude_variant(enum_name: &str, variant: usize, values: Vec<OranValue>) -> OranValue {
+        OranValue::Enum(OranEnum {
+            define: prelude_enum(enum_name).unwrap(),
+            variant,
+            values: Rc::new(values),
+        })
+    }
+
+    /// `Some(val)`, for functions that can find nothing.
+    pub fn some(val: OranValue) -> OranValue {
+        OranValue::prelude_variant("Option", 0, vec![val])
+    }
+
+    /// `None`, for functions that can find nothing.
+    pub fn none() -> OranValue {
+        OranValue::prelude_variant("Option", 1, vec![])
+    }
+
+    /// `Ok(val)`, for functions that can fail.
+    pub fn ok(val: OranValue) -> OranValue {
+        OranValue::prelude_variant("Result", 0, vec![val])
+    }
+
+    /// `Err(error)`, for functions that can fail.
+    pub fn err(error: OranValue) -> OranValue {
+        OranValue::prelude_variant("Result", 1, vec![error])
+    }
+}
+
+fn fmt_element(f: &mut fmt::Formatter, val: &OranValue) -> fmt::Result {
+    match val {
+        OranValue::Str(ref s) => write!(f, "\"{}\"", s),
+        _ => write!(f, "{}", val)
+    }
+}
+
+thread_local! {
+    /// The lists, maps and instances that are being shown, innermost last.
+    static SHOWING: RefCell<Vec<usize>> = const { RefCell::new(vec![]) };
+    /// The pairs of lists, maps and instances that are being compared, innermost last.
+    static COMPARING: RefCell<Vec<(usize, usize)>> = const { RefCell::new(vec![]) };
+}
+
+/// A copy of the value to store in a variable, an element or a field.
+/// The lists, maps and instances in it are copied too, so that changing the copy doesn't change a constant that holds the value,
+/// and changing the value through another variable doesn't change the copy. Other values can't be changed, so they are shared.
+pub fn copy_value(val: &OranValue) -> OranValue {
+    copy_with(val, &mut HashMap::new())
+}
+
+/// Copies the value with the copies made so far by address, so that a list or instance that is in the value twice is copied once.
+fn copy_with(val: &OranValue, copies: &mut HashMap<usize, OranValue>) -> OranValue {
+    match val {
+        OranValue::List(list) => {
+            if let Some(copy) = copies.get(&address(list)) {
+                return copy.clone();
+            }
+            let copy: OranList = Rc::new(RefCell::new(Vec::new()));
+            copies.insert(address(list), OranValue::List(copy.clone()));
+            let items = list.borrow().iter().map(|item| copy_with(item, copies)).collect();
+            *copy.borrow_mut() = items;
+            OranValue::List(copy)
+        }
+        OranValue::Map(map) => {
+            if let Some(copy) = copies.get(&address(map)) {
+                return copy.clone();
+            }
+            let copy: OranMap = Rc::new(RefCell::new(IndexMap::new()));
+            copies.insert(address(map), OranValue::Map(copy.clone()));
+            let entries = map.borrow().iter().map(|(key, val)| (key.clone(), copy_with(val, copies))).collect();
+            *copy.borrow_mut() = entries;
+            OranValue::Map(copy)
+        }
+        OranValue::Struct(instance) => {
+            if let Some(copy) = copies.get(&address(&instance.fields)) {
+                return copy.clone();
+            }
+            let copy = OranStruct { define: instance.define.clone(), fields: Rc::new(RefCell::new(Vec::new())) };
+            copies.insert(address(&instance.fields), OranValue::Struct(copy.clone()));
+            let fields = instance.fields.borrow().iter().map(|field| copy_with(field, copies)).collect();
+            *copy.fields.borrow_mut() = fields;
+            OranValue::Struct(copy)
+        }
+        OranValue::Enum(instance) => OranValue::Enum(OranEnum {
+            define: instance.define.clone(),
+            variant: instance.variant,
+            values: Rc::new(instance.values.iter().map(|val| copy_with(val, copies)).collect()),
+        }),
+        OranValue::Variable(var) => copy_with(&OranValue::from(&var.value), copies),
+        _ => val.clone(),
+    }
+}
+
+/// The address of a shared value, which tells whether two values are the same list, map or instance.
+pub fn address<T>(shared: &Rc<T>) -> usize {
+    Rc::as_ptr(shared) as *const () as usize
+}
+
+/// Shows the list, map or instance at the address with `show`.
+/// One that contains itself is shown as `placeholder` inside itself, like `[1, [...]]`.
+fn fmt_once<F>(f: &mut fmt::Formatter, address: usize, placeholder: &str, show: F) -> fmt::Result
+where
+    F: FnOnce(&mut fmt::Formatter) -> fmt::Result,
+{
+    if SHOWING.with(|showing| showing.borrow().contains(&address)) {
+        return write!(f, "{}", placeholder);
+    }
+    SHOWING.with(|showing| showing.borrow_mut().push(address));
+    let result = show(f);
+    SHOWING.with(|showing| showing.borrow_mut().pop());
+    result
+}
+
+/// Compares the lists, maps or instances at the addresses with `eq`.
+/// When they contain themselves, they are equal if no element that has been compared differs.
+pub fn eq_once<F: FnOnce() -> bool>(a: usize, b: usize, eq: F) -> bool {
+    if COMPARING.with(|comparing| comparing.borrow().contains(&(a, b))) {
+        return true;
+    }
+    COMPARING.with(|comparing| comparing.borrow_mut().push((a, b)));
+    let result = eq();
+    COMPARING.with(|comparing| comparing.borrow_mut().pop());
+    result
+}
+
+pub fn fmt_list(f: &mut fmt::Formatter, list: &OranList) -> fmt::Result {
+    fmt_once(f, address(list), "[...]", |f| {
+        write!(f, "[")?;
+        for (i, val) in list.borrow().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            fmt_element(f, val)?;
+        }
+        write!(f, "]")
+    })
+}
+
+pub fn fmt_struct(f: &mut fmt::Formatter, instance: &OranStruct) -> fmt::Result {
+    if instance.define.fields.is_empty() {
+        return write!(f, "{} {{}}", instance.define.name);
+    }
+    let placeholder = format!("{} {{...}}", instance.define.name);
+    fmt_once(f, address(&instance.fields), &placeholder, |f| {
+        write!(f, "{} {{ ", instance.define.name)?;
+        for (i, (name, val)) in instance.define.fields.iter().zip(instance.fields.borrow().iter()).enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: ", name)?;
+            fmt_element(f, val)?;
+        }
+        write!(f, " }}")
+    })
+}
+
+pub fn fmt_struct_define(f: &mut fmt::Formatter, define: &StructDefine) -> fmt::Result {
+    write!(f, "<struct {}>", define.name)
+}
+
+/// Shows a value of an enum like `Circle(1)`, or `None` for a variant without values.
+pub fn fmt_enum(f: &mut fmt::Formatter, val: &OranEnum) -> fmt::Result {
+    write!(f, "{}", val.variant_name())?;
+    if val.values.is_empty() {
+        return Ok(());
+    }
+    write!(f, "(")?;
+    for (i, val) in val.values.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        fmt_element(f, val)?;
+    }
+    write!(f, ")")
+}
+
+pub fn fmt_enum_define(f: &mut fmt::Formatter, define: &EnumDefine) -> fmt::Result {
+    write!(f, "<enum {}>", define.name)
+}
+
+pub fn fmt_module(f: &mut fmt::Formatter, module: &OranModule) -> fmt::Result {
+    write!(f, "<module {}>", module.name)
+}
+
+pub fn fmt_function(f: &mut fmt::Formatter, name: &str) -> fmt::Result {
+    if name.is_empty() {
+        write!(f, "<function>")
+    } else {
+        write!(f, "<function {}>", name)
+    }
+}
+
+pub fn fmt_map(f: &mut fmt::Formatter, map: &OranMap) -> fmt::Result {
+    fmt_once(f, address(map), "{...}", |f| {
+        write!(f, "{{")?;
+        for (i, (key, val)) in map.borrow().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: ", key)?;
+            fmt_element(f, val)?;
+        }
+        write!(f, "}}")
+    })
+}
+
+#[derive(Clone, Debug)]
+pub struct FunctionDefine {
+    pub name: OranString,
+    pub args: Rc<Vec<AstNode>>,
+    pub fn_return: Rc<AstNode>,
+    pub body: Rc<Vec<AstNode>>,
+    /// The scope the function was defined in. Calls run in a child of it.
+    pub closure: Scope,
+}
+
+impl PartialEq for FunctionDefine {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.body, &other.body) && self.name == other.name && self.closure.ptr_eq(&other.closure)
+    }
+}
+
+/// The signature of functions that hosts register with `Engine::register_fn`.
+pub type NativeFn = dyn Fn(&[OranValue]) -> Result<OranValue, OranError>;
+
+/// A Rust function callable from scripts.
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: OranString,
+    pub func: Rc<NativeFn>,
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.func, &other.func)
+    }
 }
 
-impl fmt::Display for OranValue<'_> {
+impl fmt::Display for OranValue {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
+            OranValue::Int(ref i) => write!(f, "{}", i),
+            OranValue::BigInt(ref b) => write!(f, "{}", b),
+            OranValue::Decimal(ref d) => write!(f, "{}", d),
             OranValue::Float(ref fl) => write!(f, "{}", fl),
             OranValue::Str(ref s) => write!(f, "{}", s.val_str.as_ref()),
             OranValue::Boolean(ref b) => write!(f, "{}", b),
+            OranValue::List(ref l) => fmt_list(f, l),
+            OranValue::Map(ref m) => fmt_map(f, m),
+            OranValue::Struct(ref s) => fmt_struct(f, s),
+            OranValue::Enum(ref s) => fmt_enum(f, s),
+            OranValue::StructDefine(ref d) => fmt_struct_define(f, d),
+            OranValue::EnumDefine(ref d) => fmt_enum_define(f, d),
+            OranValue::Module(ref m) => fmt_module(f, m),
             OranValue::Variable(ref v) => write!(f, "{}", v.value),
+            OranValue::Function(ref func) => fmt_function(f, &func.name.val_str),
+            OranValue::VmFunction(ref func) => fmt_function(f, &func.proto.name),
+            OranValue::Native(ref func) => fmt_function(f, &func.name.val_str),
             OranValue::Null => write!(f, ""),
-            _ => write!(f, "")
         }
     }
 }
 
-impl PartialEq for OranValue<'_> {
-    fn eq(&self, other: &OranValue) -> bool {
+impl PartialEq for OranValue {
+    fn eq(&self, other: &Self) -> bool {
         match self {
-            OranValue::Float(ref fl) => fl == &f64::from(other),
-            OranValue::Str(ref s) =>  s.val_str.as_ref().to_string() == other.to_string(),
-            OranValue::Boolean(ref b) => bool::from(*b) == bool::from(other),
-            OranValue::Variable(ref v) => v.value == OranVariableValue::from(other),
-            OranValue::Null => {
-                match other {
-                    OranValue::Null => true,
-                    _ => false
-                }
+            OranValue::Int(_) | OranValue::BigInt(_) | OranValue::Decimal(_) | OranValue::Float(_) => self.partial_cmp(other) == Some(Ordering::Equal),
+            OranValue::Str(ref s) =>  s.val_str.as_ref() == other.to_string(),
+            OranValue::Boolean(ref b) => (*b) == bool::from(other),
+            OranValue::List(_) | OranValue::Map(_) | OranValue::Struct(_) | OranValue::StructDefine(_) | OranValue::Enum(_) | OranValue::EnumDefine(_) | OranValue::Module(_) => {
+                OranVariableValue::from(self) == OranVariableValue::from(other)
             },
-            _ => false
+            OranValue::Variable(ref v) => v.value == OranVariableValue::from(other),
+            OranValue::Function(ref func) => matches!(OranVariableValue::from(other), OranVariableValue::Function(ref o) if o == func),
+            OranValue::VmFunction(ref func) => matches!(OranVariableValue::from(other), OranVariableValue::VmFunction(ref o) if o == func),
+            OranValue::Native(ref func) => matches!(OranVariableValue::from(other), OranVariableValue::Native(ref o) if o == func),
+            OranValue::Null => matches!(other, OranValue::Null),
         }
     }
 }
 
-impl Eq for OranValue<'_> {}
+impl Eq for OranValue {}
 
-impl PartialOrd for OranValue<'_> {
+impl PartialOrd for OranValue {
     fn partial_cmp(&self, other: &OranValue) -> Option<Ordering> {
-        match self {
-            OranValue::Float(fl) => fl.partial_cmp(&f64::from(other)),
-            OranValue::Str(_s) => {
-                f64::from(self).partial_cmp(&f64::from(other))
-            }
-            OranValue::Variable(v) => {
-                match &v.value {
-                    OranVariableValue::Float(fl) => fl.partial_cmp(&f64::from(other)),
-                    OranVariableValue::Str(_st) => {
-                        f64::from(&v.value).partial_cmp(&f64::from(other))
-                    }
-                    _ => None,
-                }
-            }
-            _ => None,
+        match operands(self, "<", other) {
+            Ok(operands) => operands.compare(),
+            Err(_e) => number(self).ok()?.partial_cmp(&number(other).ok()?),
         }
     }
 }
 
-impl Sub for OranValue<'_> {
-    type Output = Self;
+impl Sub for OranValue {
+    type Output = Result<OranValue, OranError>;
 
     fn sub(self, other: Self) -> Self::Output {
-        match self {
-            OranValue::Float(ref fl) => { OranValue::Float(fl - f64::from(other)) },
-            OranValue::Str(ref s) => OranValue::Float(s.val_str.as_ref().parse::<f64>().unwrap_or_else(|e| panic!("{}", e)) - f64::from(other)),
-            OranValue::Variable(ref v) => { 
-                match v.value {
-                    OranVariableValue::Float(ref vfl) => { OranValue::Float(vfl - f64::from(other)) },
-                    OranVariableValue::Str(ref s) => OranValue::Float(s.val_str.as_ref().parse::<f64>().unwrap_or_else(|e| panic!("{}", e)) - f64::from(other)),
-                    _ => panic!("Variable types are not Number: {:?}", self)
-                }
-            },
-            _ => panic!("Variable types are not Number: {:?}", self)
+        match operands(&self, "-", &other)? {
+            Operands::Int(a, b) => a.checked_sub(b).map(OranValue::Int).ok_or_else(|| overflow(&a, "-", &b)),
+            Operands::BigInt(a, b) => Ok(OranValue::BigInt(a - b)),
+            Operands::Decimal(a, b) => a.checked_sub(b).map(OranValue::Decimal).ok_or_else(|| decimal_overflow(&a, "-", &b)),
+            Operands::Float(a, b) => Ok(OranValue::Float(a - b)),
         }
     }
 }
 
-impl Add for OranValue<'_> {
-    type Output = Self;
+impl Add for OranValue {
+    type Output = Result<OranValue, OranError>;
 
     fn add(self, other: Self) -> Self::Output {
-        match self {
-            OranValue::Float(ref fl) => { OranValue::Float(fl + f64::from(other)) },
-            OranValue::Str(ref s) => OranValue::Float(s.val_str.as_ref().parse::<f64>().unwrap_or_else(|e| panic!("{}", e)) + f64::from(other)),
-            OranValue::Variable(ref v) => { 
-                match v.value {
-                    OranVariableValue::Float(ref vfl) => { OranValue::Float(vfl + f64::from(other)) },
-                    OranVariableValue::Str(ref s) => OranValue::Float(s.val_str.as_ref().parse::<f64>().unwrap_or_else(|e| panic!("{}", e)) + f64::from(other)),
-                    _ => panic!("Variable types are not Number: {:?}", self)
-                }
-            },
-            _ => panic!("Variable types are not Number: {:?}", self)
+        match operands(&self, "+", &other)? {
+            Operands::Int(a, b) => a.checked_add(b).map(OranValue::Int).ok_or_else(|| overflow(&a, "+", &b)),
+            Operands::BigInt(a, b) => Ok(OranValue::BigInt(a + b)),
+            Operands::Decimal(a, b) => a.checked_add(b).map(OranValue::Decimal).ok_or_else(|| decimal_overflow(&a, "+", &b)),
+            Operands::Float(a, b) => Ok(OranValue::Float(a + b)),
         }
     }
 }
 
-impl Div for OranValue<'_> {
-    type Output = Self;
+impl Div for OranValue {
+    type Output = Result<OranValue, OranError>;
 
+    /// Integer division truncates toward zero, so `7 / 2` is 3 while `7.0 / 2` is 3.5 and `7d / 2` is 3.5 exactly.
     fn div(self, other: Self) -> Self::Output {
-        match self {
-            OranValue::Float(ref fl) => { OranValue::Float(fl / f64::from(other)) },
-            OranValue::Str(ref s) => OranValue::Float(s.val_str.as_ref().parse::<f64>().unwrap_or_else(|e| panic!("{}", e)) / f64::from(other)),
-            OranValue::Variable(ref v) => { 
-                match v.value {
-                    OranVariableValue::Float(ref vfl) => { OranValue::Float(vfl / f64::from(other)) },
-                    OranVariableValue::Str(ref s) => OranValue::Float(s.val_str.as_ref().parse::<f64>().unwrap_or_else(|e| panic!("{}", e)) / f64::from(other)),
-                    _ => panic!("Variable types are not Number: {:?}", self)
-                }
-            },
-            _ => panic!("Variable types are not Number: {:?}", self)
+        match operands(&self, "/", &other)? {
+            Operands::Int(_, 0) => Err(division_by_zero()),
+            Operands::Int(a, b) => a.checked_div(b).map(OranValue::Int).ok_or_else(|| overflow(&a, "/", &b)),
+            Operands::BigInt(_, b) if b.is_zero() => Err(division_by_zero()),
+            Operands::BigInt(a, b) => Ok(OranValue::BigInt(a / b)),
+            Operands::Decimal(_, b) if b.is_zero() => Err(division_by_zero()),
+            Operands::Decimal(a, b) => a.checked_div(b).map(OranValue::Decimal).ok_or_else(|| decimal_overflow(&a, "/", &b)),
+            Operands::Float(a, b) => Ok(OranValue::Float(a / b)),
         }
     }
 }
 
-impl Mul for OranValue<'_> {
-    type Output = Self;
+impl Mul for OranValue {
+    type Output = Result<OranValue, OranError>;
 
     fn mul(self, other: Self) -> Self::Output {
-        match self {
-            OranValue::Float(ref fl) => { OranValue::Float(fl * f64::from(other)) },
-            OranValue::Str(ref s) => OranValue::Float(s.val_str.as_ref().parse::<f64>().unwrap_or_else(|e| panic!("{}", e)) * f64::from(other)),
-            OranValue::Variable(ref v) => { 
-                match v.value {
-                    OranVariableValue::Float(ref vfl) => { OranValue::Float(vfl * f64::from(other)) },
-                    OranVariableValue::Str(ref s) => OranValue::Float(s.val_str.as_ref().parse::<f64>().unwrap_or_else(|e| panic!("{}", e)) * f64::from(other)),
-                    _ => panic!("Variable types are not Number: {:?}", self)
-                }
-            },
-            _ => panic!("Variable types are not Number: {:?}", self)
+        match operands(&self, "*", &other)? {
+            Operands::Int(a, b) => a.checked_mul(b).map(OranValue::Int).ok_or_else(|| overflow(&a, "*", &b)),
+            Operands::BigInt(a, b) => Ok(OranValue::BigInt(a * b)),
+            Operands::Decimal(a, b) => a.checked_mul(b).map(OranValue::Decimal).ok_or_else(|| decimal_overflow(&a, "*", &b)),
+            Operands::Float(a, b) => Ok(OranValue::Float(a * b)),
         }
     }
 }
 
-impl Rem for OranValue<'_> {
-    type Output = Self;
+impl Rem for OranValue {
+    type Output = Result<OranValue, OranError>;
 
+    /// The remainder of integers, bigints and decimals has the sign of the left side, like the truncating division.
     fn rem(self, other: Self) -> Self::Output {
-        match self {
-            OranValue::Float(ref fl) => { OranValue::Float(fl % f64::from(other)) },
-            OranValue::Str(ref s) => OranValue::Float(s.val_str.as_ref().parse::<f64>().unwrap_or_else(|e| panic!("{}", e)) % f64::from(other)),
-            OranValue::Variable(ref v) => { 
-                match v.value {
-                    OranVariableValue::Float(ref vfl) => { OranValue::Float(vfl % f64::from(other)) },
-                    OranVariableValue::Str(ref s) => OranValue::Float(s.val_str.as_ref().parse::<f64>().unwrap_or_else(|e| panic!("{}", e)) % f64::from(other)),
-                    _ => panic!("Variable types are not Number: {:?}", self)
-                }
-            },
-            _ => panic!("Variable types are not Number: {:?}", self)
+        match operands(&self, "%", &other)? {
+            Operands::Int(_, 0) => Err(division_by_zero()),
+            Operands::Int(a, b) => a.checked_rem(b).map(OranValue::Int).ok_or_else(|| overflow(&a, "%", &b)),
+            Operands::BigInt(_, b) if b.is_zero() => Err(division_by_zero()),
+            Operands::BigInt(a, b) => Ok(OranValue::BigInt(a % b)),
+            Operands::Decimal(_, b) if b.is_zero() => Err(division_by_zero()),
+            Operands::Decimal(a, b) => a.checked_rem(b).map(OranValue::Decimal).ok_or_else(|| decimal_overflow(&a, "%", &b)),
+            Operands::Float(a, b) => Ok(OranValue::Float(a % b)),
         }
     }
 }
 
-impl From<OranValue<'_>> for f64 {
-    fn from(val: OranValue) -> Self {
-        match val {
-            OranValue::Float(ref fl) => { *fl },
-            OranValue::Str(ref s) => s.val_str.as_ref().parse().unwrap_or_else(|e| panic!("{}", e)),
-            OranValue::Variable(ref v) => {
-                match v.value {
-                    OranVariableValue::Float(ref fl) => { *fl },
-                    OranVariableValue::Str(ref s) => s.val_str.as_ref().parse().unwrap_or_else(|e| panic!("{}", e)),
-                    OranVariableValue::Null => { f64::from(0) }
-                    _ => panic!("Variable types are not Number: {:?}", val)
-                }
-            },
-            _ => panic!("Variable types are not Number: {:?}", val)
+/// Both operands of a bitwise operator. An integer and a bigint are calculated as bigints.
+enum IntOperands {
+    Int(i64, i64),
+    BigInt(BigInt, BigInt),
+}
+
+/// Takes the operands of a bitwise operator, which are only defined for integers and bigints.
+fn int_operands(lhs: &OranValue, op: &str, rhs: &OranValue) -> Result<IntOperands, OranError> {
+    if let (OranValue::Int(a), OranValue::Int(b)) = (lhs, rhs) {
+        return Ok(IntOperands::Int(*a, *b));
+    }
+    let (a, b) = (number(lhs)?, number(rhs)?);
+    match (&a, &b) {
+        (Number::Int(a), Number::Int(b)) => Ok(IntOperands::Int(*a, *b)),
+        (Number::Int(_) | Number::BigInt(_), Number::Int(_) | Number::BigInt(_)) => Ok(IntOperands::BigInt(a.as_bigint()?, b.as_bigint()?)),
+        _ => Err(OranError::new(ErrorKind::Type, &format!("Bitwise operators need integers: {} {} {}", a, op, b))),
+    }
+}
+
+impl BitAnd for OranValue {
+    type Output = Result<OranValue, OranError>;
+
+    fn bitand(self, other: Self) -> Self::Output {
+        match int_operands(&self, "&", &other)? {
+            IntOperands::Int(a, b) => Ok(OranValue::Int(a & b)),
+            IntOperands::BigInt(a, b) => Ok(OranValue::BigInt(a & b)),
         }
     }
 }
 
-impl From<&OranValue<'_>> for Result<f64, String> {
-    fn from(val: &OranValue) -> Self {
-        match val {
-            OranValue::Float(ref fl) => { Ok(*fl) },
-            OranValue::Str(ref s) => {
-                match s.val_str.as_ref().parse() {
-                    Ok(v) => Ok(v),
-                    Err(_e) => {
-                        let mut err = "Variable type is not Number:".to_owned();
-                        err.push_str(&val.to_string());
-                        Err(err)
-                    }
-                }
-            },
-            OranValue::Null => { 
-                let mut err = "Variable type is null:".to_owned();
-                err.push_str(&val.to_string());
-                Err(err) 
-            },
-            OranValue::Variable(ref v) => {
-                match v.value {
-                    OranVariableValue::Float(ref fl) => { Ok(*fl) },
-                    OranVariableValue::Str(ref s) => match s.val_str.as_ref().parse() {
-                        Ok(v) => Ok(v),
-                        Err(_e) => {
-                            let mut err = "Variable type is not Number:".to_owned();
-                            err.push_str(&val.to_string());
-                            Err(err)
-                        }
-                    },
-                    OranVariableValue::Null => { 
-                        let mut err = "Variable type is null:".to_owned();
-                        err.push_str(&val.to_string());
-                        Err(err) 
-                    }
-                    _ => panic!("Variable type is not Number: {:?}", val)
-                }
+impl BitOr for OranValue {
+    type Output = Result<OranValue, OranError>;
+
+    fn bitor(self, other: Self) -> Self::Output {
+        match int_operands(&self, "|", &other)? {
+            IntOperands::Int(a, b) => Ok(OranValue::Int(a | b)),
+            IntOperands::BigInt(a, b) => Ok(OranValue::BigInt(a | b)),
+        }
+    }
+}
+
+impl BitXor for OranValue {
+    type Output = Result<OranValue, OranError>;
+
+    fn bitxor(self, other: Self) -> Self::Output {
+        match int_operands(&self, "xor", &other)? {
+            IntOperands::Int(a, b) => Ok(OranValue::Int(a ^ b)),
+            IntOperands::BigInt(a, b) => Ok(OranValue::BigInt(a ^ b)),
+        }
+    }
+}
+
+impl Shl for OranValue {
+    type Output = Result<OranValue, OranError>;
+
+    /// Shifting left multiplies by a power of two, so an integer that loses bits is an overflow.
+    fn shl(self, other: Self) -> Self::Output {
+        match int_operands(&self, "<<", &other)? {
+            IntOperands::Int(_, b) if b < 0 => Err(negative_shift(&self, "<<", &other)),
+            IntOperands::Int(0, _) => Ok(OranValue::Int(0)),
+            IntOperands::Int(a, b) if b < 64 && (a << b) >> b == a => Ok(OranValue::Int(a << b)),
+            IntOperands::Int(a, b) => Err(overflow(&a, "<<", &b)),
+            IntOperands::BigInt(a, b) => match u32::try_from(&b) {
+                Ok(b) => Ok(OranValue::BigInt(a << b)),
+                Err(_e) if b.is_negative() => Err(negative_shift(&self, "<<", &other)),
+                Err(_e) => Err(overflow(&a, "<<", &b)),
             },
-            _ => panic!("Variable types are not Number: {:?}", val)
         }
     }
 }
 
-impl From<&OranValue<'_>> for f64 {
+impl Shr for OranValue {
+    type Output = Result<OranValue, OranError>;
+
+    /// Shifting right keeps the sign, so it divides by a power of two rounding down.
+    fn shr(self, other: Self) -> Self::Output {
+        match int_operands(&self, ">>", &other)? {
+            IntOperands::Int(_, b) if b < 0 => Err(negative_shift(&self, ">>", &other)),
+            IntOperands::Int(a, b) => Ok(OranValue::Int(a >> b.min(63))),
+            IntOperands::BigInt(_, b) if b.is_negative() => Err(negative_shift(&self, ">>", &other)),
+            IntOperands::BigInt(a, b) => {
+                // Shifting by more bits than the number has leaves only the sign.
+                let bits = u32::try_from(&b).unwrap_or(u32::MAX).min(a.bits() as u32 + 1);
+                Ok(OranValue::BigInt(a >> bits))
+            }
+        }
+    }
+}
+
+impl Not for OranValue {
+    type Output = Result<OranValue, OranError>;
+
+    /// Inverts the bits of an integer. This is `~`, since `!` negates the truthiness of any value.
+    fn not(self) -> Self::Output {
+        match number(&self)? {
+            Number::Int(i) => Ok(OranValue::Int(!i)),
+            Number::BigInt(b) => Ok(OranValue::BigInt(!b)),
+            n => Err(OranError::new(ErrorKind::Type, &format!("Bitwise operators need integers: ~{}", n))),
+        }
+    }
+}
+
+fn negative_shift(lhs: &dyn fmt::Display, op: &str, rhs: &dyn fmt::Display) -> OranError {
+    OranError::new(ErrorKind::Arithmetic, &format!("Cannot shift by a negative amount: {} {} {}", lhs, op, rhs))
+}
+
+impl From<&OranValue> for Result<f64, OranError> {
     fn from(val: &OranValue) -> Self {
         match val {
-            OranValue::Float(ref fl) => { *fl },
-            OranValue::Str(ref s) => s.val_str.as_ref().parse().unwrap_or_else(|e| panic!("{}", e)),
-            OranValue::Variable(ref v) => {
-                match v.value {
-                    OranVariableValue::Float(ref fl) => { *fl },
-                    OranVariableValue::Str(ref s) => s.val_str.as_ref().parse().unwrap_or_else(|e| panic!("{}", e)),
-                    OranVariableValue::Null => { f64::from(0) }
-                    _ => panic!("Variable types are not Number: {:?}", val)
-                }
-            },
-            _ => panic!("Variable types are not Number: {:?}", val)
+            OranValue::Int(ref i) => { Ok(*i as f64) },
+            OranValue::BigInt(_) | OranValue::Decimal(_) => Ok(number(val)?.as_f64()),
+            OranValue::Float(ref fl) => { Ok(*fl) },
+            OranValue::Str(ref s) => s.val_str.as_ref().parse().map_err(|_e| not_a_number(val)),
+            OranValue::Variable(ref v) => Result::<f64, OranError>::from(&v.value),
+            _ => Err(not_a_number(val))
         }
     }
 }
 
-impl From<&OranVariableValue<'_>> for f64 {
+impl From<&OranVariableValue> for Result<f64, OranError> {
     fn from(val: &OranVariableValue) -> Self {
         match val {
-            OranVariableValue::Float(ref fl) => { *fl },
-            OranVariableValue::Str(ref s) => s.val_str.as_ref().parse().unwrap_or_else(|e| panic!("{}", e)),
-            OranVariableValue::Null => { f64::from(0) }
-            _ => panic!("Variable types are not Number: {:?}", val)
+            OranVariableValue::Int(ref i) => { Ok(*i as f64) },
+            OranVariableValue::BigInt(_) | OranVariableValue::Decimal(_) => Result::<f64, OranError>::from(&OranValue::from(val)),
+            OranVariableValue::Float(ref fl) => { Ok(*fl) },
+            OranVariableValue::Str(ref s) => s.val_str.as_ref().parse().map_err(|_e| not_a_number(val)),
+            _ => Err(not_a_number(val))
         }
     }
 }
 
-impl From<OranValue<'_>> for String {
+fn not_a_number(val: &dyn fmt::Display) -> OranError {
+    OranError::new(ErrorKind::Type, &format!("This \"{}\" is not a number.", val))
+}
+
+impl From<OranValue> for String {
     fn from(val: OranValue) -> Self {
         match val {
             OranValue::Str(ref s) => s.val_str.as_ref().to_string(),
+            OranValue::Int(ref i) => { i.to_string() },
+            OranValue::BigInt(ref b) => { b.to_string() },
+            OranValue::Decimal(ref d) => { d.to_string() },
             OranValue::Float(ref fl) => { fl.to_string() },
             OranValue::Boolean(ref bl) => { bl.to_string() },
+            OranValue::List(_) | OranValue::Map(_) | OranValue::Struct(_) | OranValue::StructDefine(_) | OranValue::Enum(_) | OranValue::EnumDefine(_) | OranValue::Module(_) => { val.to_string() },
             OranValue::Variable(ref v) => { v.value.to_string() },
+            OranValue::Function(_) | OranValue::VmFunction(_) | OranValue::Native(_) => { val.to_string() },
             OranValue::Null => { "".to_string() },
-            _ => { "".to_string() }
         }
     }
 }
 
-impl From<&OranValue<'_>> for String {
+impl From<&OranValue> for String {
     fn from(val: &OranValue) -> Self {
         match val {
             OranValue::Str(ref s) => s.val_str.as_ref().to_string(),
+            OranValue::Int(ref i) => { i.to_string() },
+            OranValue::BigInt(ref b) => { b.to_string() },
+            OranValue::Decimal(ref d) => { d.to_string() },
             OranValue::Float(ref fl) => { fl.to_string() },
             OranValue::Boolean(ref bl) => { bl.to_string() },
+            OranValue::List(_) | OranValue::Map(_) | OranValue::Struct(_) | OranValue::StructDefine(_) | OranValue::Enum(_) | OranValue::EnumDefine(_) | OranValue::Module(_) => { val.to_string() },
             OranValue::Variable(ref v) => { v.value.to_string() },
+            OranValue::Function(_) | OranValue::VmFunction(_) | OranValue::Native(_) => { val.to_string() },
             OranValue::Null => { "".to_string() },
-            _ => { "".to_string() }
         }
     }
 }
 
-impl From<OranValue<'_>> for bool {
+impl From<OranValue> for bool {
     fn from(val: OranValue) -> Self {
         match val {
             OranValue::Str(ref s) => {
-                if s.val_str.as_ref().to_string() == "true" {
+                if s.val_str.as_ref() == "true" {
                     return true;
-                } else if s.val_str.as_ref().to_string() == "" {
+                } else if s.val_str.as_ref() == "" {
                     return false;
                 }
                 true
             },
+            OranValue::Int(ref i) => { *i != 0 },
+            OranValue::BigInt(ref b) => { !b.is_zero() },
+            OranValue::Decimal(ref d) => { !d.is_zero() },
             OranValue::Float(ref fl) => {
                 if *fl == f64::from(0) {
                     return false;
@@ -340,16 +1009,22 @@ impl From<OranValue<'_>> for bool {
                 true
             },
             OranValue::Boolean(ref bl) => { *bl },
+            OranValue::List(ref l) => { !l.borrow().is_empty() },
+            OranValue::Map(ref m) => { !m.borrow().is_empty() },
+            OranValue::Struct(_) | OranValue::StructDefine(_) | OranValue::Enum(_) | OranValue::EnumDefine(_) | OranValue::Module(_) => true,
             OranValue::Variable(ref v) => {
                 match v.value {
                     OranVariableValue::Str(ref s) => {
-                        if s.val_str.as_ref().to_string() == "true" {
+                        if s.val_str.as_ref() == "true" {
                             return true;
-                        } else if s.val_str.as_ref().to_string() == "" {
+                        } else if s.val_str.as_ref() == "" {
                             return false;
                         }
                         true
                     },
+                    OranVariableValue::Int(ref i) => { *i != 0 },
+                    OranVariableValue::BigInt(ref b) => { !b.is_zero() },
+                    OranVariableValue::Decimal(ref d) => { !d.is_zero() },
                     OranVariableValue::Float(ref fl) => {
                         if *fl == f64::from(0) {
                             return false;
@@ -357,26 +1032,33 @@ impl From<OranValue<'_>> for bool {
                         true
                     },
                     OranVariableValue::Boolean(ref bl) => { *bl },
+                    OranVariableValue::List(ref l) => { !l.borrow().is_empty() },
+                    OranVariableValue::Map(ref m) => { !m.borrow().is_empty() },
+                    OranVariableValue::Struct(_) | OranVariableValue::StructDefine(_) | OranVariableValue::Enum(_) | OranVariableValue::EnumDefine(_) | OranVariableValue::Module(_) => true,
+                    OranVariableValue::Function(_) | OranVariableValue::VmFunction(_) | OranVariableValue::Native(_) => true,
                     OranVariableValue::Null => false,
                 }
             },
+            OranValue::Function(_) | OranValue::VmFunction(_) | OranValue::Native(_) => true,
             OranValue::Null => false,
-            _ => false
         }
     }
 }
 
-impl From<&OranValue<'_>> for bool {
+impl From<&OranValue> for bool {
     fn from(val: &OranValue) -> Self {
         match val {
             OranValue::Str(ref s) => {
-                if s.val_str.as_ref().to_string() == "true" {
+                if s.val_str.as_ref() == "true" {
                     return true;
-                } else if s.val_str.as_ref().to_string() == "" {
+                } else if s.val_str.as_ref() == "" {
                     return false;
                 }
                 true
             },
+            OranValue::Int(ref i) => { *i != 0 },
+            OranValue::BigInt(ref b) => { !b.is_zero() },
+            OranValue::Decimal(ref d) => { !d.is_zero() },
             OranValue::Float(ref fl) => {
                 if *fl == f64::from(0) {
                     return false;
@@ -384,16 +1066,22 @@ impl From<&OranValue<'_>> for bool {
                 true
             },
             OranValue::Boolean(ref bl) => { *bl },
+            OranValue::List(ref l) => { !l.borrow().is_empty() },
+            OranValue::Map(ref m) => { !m.borrow().is_empty() },
+            OranValue::Struct(_) | OranValue::StructDefine(_) | OranValue::Enum(_) | OranValue::EnumDefine(_) | OranValue::Module(_) => true,
             OranValue::Variable(ref v) => {
                 match v.value {
                     OranVariableValue::Str(ref s) => {
-                        if s.val_str.as_ref().to_string() == "true" {
+                        if s.val_str.as_ref() == "true" {
                             return true;
-                        } else if s.val_str.as_ref().to_string() == "" {
+                        } else if s.val_str.as_ref() == "" {
                             return false;
                         }
                         true
                     },
+                    OranVariableValue::Int(ref i) => { *i != 0 },
+                    OranVariableValue::BigInt(ref b) => { !b.is_zero() },
+                    OranVariableValue::Decimal(ref d) => { !d.is_zero() },
                     OranVariableValue::Float(ref fl) => {
                         if *fl == f64::from(0) {
                             return false;
@@ -401,67 +1089,81 @@ impl From<&OranValue<'_>> for bool {
                         true
                     },
                     OranVariableValue::Boolean(ref bl) => { *bl },
+                    OranVariableValue::List(ref l) => { !l.borrow().is_empty() },
+                    OranVariableValue::Map(ref m) => { !m.borrow().is_empty() },
+                    OranVariableValue::Struct(_) | OranVariableValue::StructDefine(_) | OranVariableValue::Enum(_) | OranVariableValue::EnumDefine(_) | OranVariableValue::Module(_) => true,
+                    OranVariableValue::Function(_) | OranVariableValue::VmFunction(_) | OranVariableValue::Native(_) => true,
                     OranVariableValue::Null => false
                 }
             },
+            OranValue::Function(_) | OranValue::VmFunction(_) | OranValue::Native(_) => true,
             OranValue::Null => false,
-            _ => false
         }
     }
 }
 
-impl<'a> From<OranValue<'a>> for OranVariableValue<'a> {
-    fn from(val: OranValue<'a>) -> Self {
+impl From<OranValue> for OranVariableValue {
+    fn from(val: OranValue) -> Self {
         match val {
             OranValue::Str(ref s) => {
                 OranVariableValue::Str(s.to_owned())
             },
+            OranValue::Int(ref i) => { OranVariableValue::Int(*i) },
+            OranValue::BigInt(ref b) => { OranVariableValue::BigInt(b.clone()) },
+            OranValue::Decimal(ref d) => { OranVariableValue::Decimal(*d) },
             OranValue::Float(ref fl) => { OranVariableValue::Float(*fl) },
             OranValue::Boolean(ref bl) => { OranVariableValue::Boolean(*bl) },
+            OranValue::List(ref l) => { OranVariableValue::List(l.clone()) },
+            OranValue::Map(ref m) => { OranVariableValue::Map(m.clone()) },
+            OranValue::Struct(ref s) => { OranVariableValue::Struct(s.clone()) },
+            OranValue::Enum(ref s) => { OranVariableValue::Enum(s.clone()) },
+            OranValue::StructDefine(ref d) => { OranVariableValue::StructDefine(d.clone()) },
+            OranValue::EnumDefine(ref d) => { OranVariableValue::EnumDefine(d.clone()) },
+            OranValue::Module(ref m) => { OranVariableValue::Module(m.clone()) },
             OranValue::Null => { OranVariableValue::Null },
-            OranValue::Variable(ref v) => { 
-                match v.value {
-                    OranVariableValue::Str(ref s) => { OranVariableValue::Str(s.to_owned()) },
-                    OranVariableValue::Float(ref fl) => { OranVariableValue::Float(*fl) },
-                    OranVariableValue::Boolean(ref bl) => { OranVariableValue::Boolean(*bl) },
-                    OranVariableValue::Null => { OranVariableValue::Null },
-                }
-            },
-            _ => panic!("Failed to parse: {:?}", val)
+            OranValue::Variable(ref v) => { v.value.clone() },
+            OranValue::Function(ref func) => { OranVariableValue::Function(func.clone()) },
+            OranValue::VmFunction(ref func) => { OranVariableValue::VmFunction(func.clone()) },
+            OranValue::Native(ref func) => { OranVariableValue::Native(func.clone()) },
         }
     }
 }
 
-impl<'a> From<&OranValue<'a>> for OranVariableValue<'a> {
-    fn from(val: &OranValue<'a>) -> Self {
+impl From<&OranValue> for OranVariableValue {
+    fn from(val: &OranValue) -> Self {
         match val {
             OranValue::Str(ref s) => {
                 OranVariableValue::Str(s.to_owned())
             },
+            OranValue::Int(ref i) => { OranVariableValue::Int(*i) },
+            OranValue::BigInt(ref b) => { OranVariableValue::BigInt(b.clone()) },
+            OranValue::Decimal(ref d) => { OranVariableValue::Decimal(*d) },
             OranValue::Float(ref fl) => { OranVariableValue::Float(*fl) },
             OranValue::Boolean(ref bl) => { OranVariableValue::Boolean(*bl) },
+            OranValue::List(ref l) => { OranVariableValue::List(l.clone()) },
+            OranValue::Map(ref m) => { OranVariableValue::Map(m.clone()) },
+            OranValue::Struct(ref s) => { OranVariableValue::Struct(s.clone()) },
+            OranValue::Enum(ref s) => { OranVariableValue::Enum(s.clone()) },
+            OranValue::StructDefine(ref d) => { OranVariableValue::StructDefine(d.clone()) },
+            OranValue::EnumDefine(ref d) => { OranVariableValue::EnumDefine(d.clone()) },
+            OranValue::Module(ref m) => { OranVariableValue::Module(m.clone()) },
             OranValue::Null => { OranVariableValue::Null },
-            OranValue::Variable(ref v) => { 
-                match v.value {
-                    OranVariableValue::Str(ref s) => { OranVariableValue::Str(s.to_owned()) },
-                    OranVariableValue::Float(ref fl) => { OranVariableValue::Float(*fl) },
-                    OranVariableValue::Boolean(ref bl) => { OranVariableValue::Boolean(*bl) },
-                    OranVariableValue::Null => { OranVariableValue::Null },
-                }
-            },
-            _ => panic!("Failed to parse: {:?}", val)
+            OranValue::Variable(ref v) => { v.value.clone() },
+            OranValue::Function(ref func) => { OranVariableValue::Function(func.clone()) },
+            OranValue::VmFunction(ref func) => { OranVariableValue::VmFunction(func.clone()) },
+            OranValue::Native(ref func) => { OranVariableValue::Native(func.clone()) },
         }
     }
 }
 
-impl<'a> From<&OranValue<'a>> for OranVariable<'a> {
-    fn from(val: &OranValue<'a>) -> Self {
+impl From<&OranValue> for OranVariable {
+    fn from(val: &OranValue) -> Self {
         match &val {
             OranValue::Variable(ref v) => { 
                 OranVariable {
                     var_type: v.var_type,
                     value: v.value.clone(),
-                    name: v.name,
+                    name: v.name.clone(),
                 }
             },
             _ => panic!("Failed to parse: {:?}", val)
@@ -469,8 +1171,8 @@ impl<'a> From<&OranValue<'a>> for OranVariable<'a> {
     }
 }
 
-impl<'a> From<&OranValue<'a>> for FunctionDefine<'a> {
-    fn from(val: &OranValue<'a>) -> Self {
+impl From<&OranValue> for FunctionDefine {
+    fn from(val: &OranValue) -> Self {
         match val {
             OranValue::Function(f) => {
                 f.clone()
@@ -479,3 +1181,160 @@ impl<'a> From<&OranValue<'a>> for FunctionDefine<'a> {
         }
     }
 }
+
+impl From<&OranVariableValue> for OranValue {
+    fn from(val: &OranVariableValue) -> Self {
+        match val {
+            OranVariableValue::Str(ref s) => { OranValue::Str(s.to_owned()) },
+            OranVariableValue::Int(ref i) => { OranValue::Int(*i) },
+            OranVariableValue::BigInt(ref b) => { OranValue::BigInt(b.clone()) },
+            OranVariableValue::Decimal(ref d) => { OranValue::Decimal(*d) },
+            OranVariableValue::Float(ref fl) => { OranValue::Float(*fl) },
+            OranVariableValue::Boolean(ref bl) => { OranValue::Boolean(*bl) },
+            OranVariableValue::List(ref l) => { OranValue::List(l.clone()) },
+            OranVariableValue::Map(ref m) => { OranValue::Map(m.clone()) },
+            OranVariableValue::Struct(ref s) => { OranValue::Struct(s.clone()) },
+            OranVariableValue::Enum(ref s) => { OranValue::Enum(s.clone()) },
+            OranVariableValue::StructDefine(ref d) => { OranValue::StructDefine(d.clone()) },
+            OranVariableValue::EnumDefine(ref d) => { OranValue::EnumDefine(d.clone()) },
+            OranVariableValue::Module(ref m) => { OranValue::Module(m.clone()) },
+            OranVariableValue::Function(ref func) => { OranValue::Function(func.clone()) },
+            OranVariableValue::VmFunction(ref func) => { OranValue::VmFunction(func.clone()) },
+            OranVariableValue::Native(ref func) => { OranValue::Native(func.clone()) },
+            OranVariableValue::Null => { OranValue::Null },
+        }
+    }
+}
+
+impl From<&OranValue> for Option<OranList> {
+    fn from(val: &OranValue) -> Self {
+        match val {
+            OranValue::List(ref l) => Some(l.clone()),
+            OranValue::Variable(ref v) => {
+                match v.value {
+                    OranVariableValue::List(ref l) => Some(l.clone()),
+                    _ => None
+                }
+            },
+            _ => None
+        }
+    }
+}
+
+impl From<&OranValue> for Option<OranStruct> {
+    fn from(val: &OranValue) -> Self {
+        match val {
+            OranValue::Struct(ref s) => Some(s.clone()),
+            OranValue::Variable(ref v) => {
+                match v.value {
+                    OranVariableValue::Struct(ref s) => Some(s.clone()),
+                    _ => None
+                }
+            },
+            _ => None
+        }
+    }
+}
+
+impl From<&OranValue> for Option<Rc<StructDefine>> {
+    fn from(val: &OranValue) -> Self {
+        match val {
+            OranValue::StructDefine(ref d) => Some(d.clone()),
+            OranValue::Variable(ref v) => {
+                match v.value {
+                    OranVariableValue::StructDefine(ref d) => Some(d.clone()),
+                    _ => None
+                }
+            },
+            _ => None
+        }
+    }
+}
+
+impl From<&OranValue> for Option<OranEnum> {
+    fn from(val: &OranValue) -> Self {
+        match val {
+            OranValue::Enum(ref e) => Some(e.clone()),
+            OranValue::Variable(ref v) => {
+                match v.value {
+                    OranVariableValue::Enum(ref e) => Some(e.clone()),
+                    _ => None
+                }
+            },
+            _ => None
+        }
+    }
+}
+
+impl From<&OranValue> for Option<Rc<EnumDefine>> {
+    fn from(val: &OranValue) -> Self {
+        match val {
+            OranValue::EnumDefine(ref d) => Some(d.clone()),
+            OranValue::Variable(ref v) => {
+                match v.value {
+                    OranVariableValue::EnumDefine(ref d) => Some(d.clone()),
+                    _ => None
+                }
+            },
+            _ => None
+        }
+    }
+}
+
+impl From<&OranValue> for Option<Rc<OranModule>> {
+    fn from(val: &OranValue) -> Self {
+        match val {
+            OranValue::Module(ref m) => Some(m.clone()),
+            OranValue::Variable(ref v) => {
+                match v.value {
+                    OranVariableValue::Module(ref m) => Some(m.clone()),
+                    _ => None
+                }
+            },
+            _ => None
+        }
+    }
+}
+
+/// Strings, numbers and booleans can be keys of maps.
+impl From<&OranValue> for Option<MapKey> {
+    fn from(val: &OranValue) -> Self {
+        match OranVariableValue::from(val) {
+            OranVariableValue::Int(i) => Some(MapKey::Int(i)),
+            OranVariableValue::BigInt(b) => Some(MapKey::BigInt(b)),
+            OranVariableValue::Decimal(d) => Some(MapKey::Decimal(d)),
+            OranVariableValue::Float(fl) => Some(MapKey::Float(if fl == 0.0 { 0.0f64.to_bits() } else { fl.to_bits() })),
+            OranVariableValue::Str(s) => Some(MapKey::Str(s)),
+            OranVariableValue::Boolean(b) => Some(MapKey::Boolean(b)),
+            _ => None
+        }
+    }
+}
+
+impl From<&MapKey> for OranValue {
+    fn from(key: &MapKey) -> Self {
+        match key {
+            MapKey::Int(i) => OranValue::Int(*i),
+            MapKey::BigInt(b) => OranValue::BigInt(b.clone()),
+            MapKey::Decimal(d) => OranValue::Decimal(*d),
+            MapKey::Float(bits) => OranValue::Float(f64::from_bits(*bits)),
+            MapKey::Str(s) => OranValue::Str(s.clone()),
+            MapKey::Boolean(b) => OranValue::Boolean(*b),
+        }
+    }
+}
+
+impl From<&OranValue> for Option<OranMap> {
+    fn from(val: &OranValue) -> Self {
+        match val {
+            OranValue::Map(ref m) => Some(m.clone()),
+            OranValue::Variable(ref v) => {
+                match v.value {
+                    OranVariableValue::Map(ref m) => Some(m.clone()),
+                    _ => None
+                }
+            },
+            _ => None
+        }
+    }
+}
@@ -1,78 +1,142 @@
 use std::fmt;
 use std::ops::{Add, Sub, Div, Mul, Rem};
+use num_bigint::BigInt;
+use num_traits::Zero;
+use rust_decimal::Decimal;
 use crate::value::var_type::VarType;
 use super::oran_string::OranString;
+use std::rc::Rc;
+use super::oran_value::{OranValue, OranList, OranMap, OranStruct, StructDefine, OranEnum, EnumDefine, OranModule, FunctionDefine, NativeFunction, fmt_list, fmt_map, fmt_struct, fmt_struct_define, fmt_enum, fmt_enum_define, fmt_module, fmt_function, address, eq_once};
+use crate::vm::VmFunction;
+use super::oran_error::OranError;
 
 #[derive(Clone, Debug)]
-pub struct OranVariable<'a> {
+pub struct OranVariable {
     pub var_type: VarType,
-    pub name: &'a str,
-    pub value: OranVariableValue<'a>
+    pub name: OranString,
+    pub value: OranVariableValue
 }
 
-impl PartialEq for OranVariable<'_> {
+impl PartialEq for OranVariable {
     fn eq(&self, other: &Self) -> bool {
         self.value == other.value
     }
 }
 
 #[derive(Debug)]
-pub enum OranVariableValue<'a> {
+pub enum OranVariableValue {
+    Int(i64),
+    BigInt(BigInt),
+    Decimal(Decimal),
     Float(f64),
-    Str(OranString<'a>),
+    Str(OranString),
     Boolean(bool),
+    List(OranList),
+    Map(OranMap),
+    Struct(OranStruct),
+    StructDefine(Rc<StructDefine>),
+    Enum(OranEnum),
+    EnumDefine(Rc<EnumDefine>),
+    Module(Rc<OranModule>),
+    Function(FunctionDefine),
+    VmFunction(VmFunction),
+    Native(NativeFunction),
     Null
 }
 
-impl Clone for OranVariableValue<'_> {
+impl Clone for OranVariableValue {
     fn clone(&self) -> Self {
         match self {
+            OranVariableValue::Int(a) => OranVariableValue::Int(*a),
+            OranVariableValue::BigInt(a) => OranVariableValue::BigInt(a.clone()),
+            OranVariableValue::Decimal(a) => OranVariableValue::Decimal(*a),
             OranVariableValue::Float(a) => OranVariableValue::Float(*a),
             OranVariableValue::Str(a) => OranVariableValue::Str(a.clone()),
             OranVariableValue::Boolean(a) => OranVariableValue::Boolean(*a),
+            OranVariableValue::List(a) => OranVariableValue::List(a.clone()),
+            OranVariableValue::Map(a) => OranVariableValue::Map(a.clone()),
+            OranVariableValue::Struct(a) => OranVariableValue::Struct(a.clone()),
+            OranVariableValue::Enum(a) => OranVariableValue::Enum(a.clone()),
+            OranVariableValue::StructDefine(a) => OranVariableValue::StructDefine(a.clone()),
+            OranVariableValue::EnumDefine(a) => OranVariableValue::EnumDefine(a.clone()),
+            OranVariableValue::Module(a) => OranVariableValue::Module(a.clone()),
+            OranVariableValue::Function(a) => OranVariableValue::Function(a.clone()),
+            OranVariableValue::VmFunction(a) => OranVariableValue::VmFunction(a.clone()),
+            OranVariableValue::Native(a) => OranVariableValue::Native(a.clone()),
             OranVariableValue::Null => OranVariableValue::Null
         }
     }
 }
 
-impl fmt::Display for OranVariableValue<'_> {
+impl fmt::Display for OranVariableValue {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
+            OranVariableValue::Int(ref i) => write!(f, "{}", i),
+            OranVariableValue::BigInt(ref b) => write!(f, "{}", b),
+            OranVariableValue::Decimal(ref d) => write!(f, "{}", d),
             OranVariableValue::Float(ref fl) => write!(f, "{}", fl),
             OranVariableValue::Str(ref s) => write!(f, "{}", s.val_str.as_ref()),
             OranVariableValue::Boolean(ref b) => write!(f, "{}", b),
+            OranVariableValue::List(ref l) => fmt_list(f, l),
+            OranVariableValue::Map(ref m) => fmt_map(f, m),
+            OranVariableValue::Struct(ref s) => fmt_struct(f, s),
+            OranVariableValue::Enum(ref s) => fmt_enum(f, s),
+            OranVariableValue::StructDefine(ref d) => fmt_struct_define(f, d),
+            OranVariableValue::EnumDefine(ref d) => fmt_enum_define(f, d),
+            OranVariableValue::Module(ref m) => fmt_module(f, m),
+            OranVariableValue::Function(ref func) => fmt_function(f, &func.name.val_str),
+            OranVariableValue::VmFunction(ref func) => fmt_function(f, &func.proto.name),
+            OranVariableValue::Native(ref func) => fmt_function(f, &func.name.val_str),
             OranVariableValue::Null => write!(f, ""),
         }
     }
 }
 
-impl PartialEq for OranVariableValue<'_> {
-    fn eq(&self, other: &OranVariableValue) -> bool {
+impl PartialEq for OranVariableValue {
+    fn eq(&self, other: &Self) -> bool {
         match *self {
-            OranVariableValue::Float(ref fl) => *fl == f64::from(other),
-            OranVariableValue::Str(ref s) => s.val_str.as_ref().to_string() == other.to_string(),
-            OranVariableValue::Boolean(ref b) => bool::from(*b) == bool::from(other),
-            OranVariableValue::Null => {
+            OranVariableValue::Int(_) | OranVariableValue::BigInt(_) | OranVariableValue::Decimal(_) | OranVariableValue::Float(_) => OranValue::from(self) == OranValue::from(other),
+            OranVariableValue::Str(ref s) => s.val_str.as_ref() == other.to_string(),
+            OranVariableValue::Boolean(ref b) => (*b) == bool::from(other),
+            OranVariableValue::List(ref l) => {
+                match other {
+                    OranVariableValue::List(ref o) => eq_once(address(l), address(o), || *l.borrow() == *o.borrow()),
+                    _ => false
+                }
+            },
+            OranVariableValue::Map(ref m) => {
                 match other {
-                    OranVariableValue::Null => true,
+                    OranVariableValue::Map(ref o) => eq_once(address(m), address(o), || *m.borrow() == *o.borrow()),
                     _ => false
                 }
-            }
+            },
+            OranVariableValue::Struct(ref s) => matches!(other, OranVariableValue::Struct(ref o) if o == s),
+            OranVariableValue::Enum(ref s) => matches!(other, OranVariableValue::Enum(ref o) if o == s),
+            OranVariableValue::StructDefine(ref d) => matches!(other, OranVariableValue::StructDefine(ref o) if Rc::ptr_eq(o, d)),
+            OranVariableValue::EnumDefine(ref d) => matches!(other, OranVariableValue::EnumDefine(ref o) if Rc::ptr_eq(o, d)),
+            OranVariableValue::Module(ref m) => matches!(other, OranVariableValue::Module(ref o) if Rc::ptr_eq(o, m)),
+            OranVariableValue::Function(_) | OranVariableValue::VmFunction(_) | OranVariableValue::Native(_) => {
+                OranValue::from(self) == OranValue::from(other)
+            },
+            OranVariableValue::Null => matches!(other, OranVariableValue::Null)
         }
     }
 }
 
-impl From<OranVariableValue<'_>> for bool {
+impl From<OranVariableValue> for bool {
     fn from(val: OranVariableValue) -> Self {
         match val {
             OranVariableValue::Str(ref s) => {
-                if s.val_str.as_ref().to_string() == "true" {
+                if s.val_str.as_ref() == "true" {
                     return true;
-                } else if s.val_str.as_ref().to_string() == "" {
+                } else if s.val_str.as_ref() == "" {
                     return false;
                 }
                 true
             },
+            OranVariableValue::Int(ref i) => { *i != 0 },
+            OranVariableValue::BigInt(ref b) => { !b.is_zero() },
+            OranVariableValue::Decimal(ref d) => { !d.is_zero() },
             OranVariableValue::Float(ref fl) => {
                 if *fl == f64::from(0) {
                     return false;
@@ -80,22 +144,29 @@ impl From<OranVariableValue<'_>> for bool {
                 true
             },
             OranVariableValue::Boolean(ref bl) => { *bl },
+            OranVariableValue::List(ref l) => { !l.borrow().is_empty() },
+            OranVariableValue::Map(ref m) => { !m.borrow().is_empty() },
+            OranVariableValue::Struct(_) | OranVariableValue::StructDefine(_) | OranVariableValue::Enum(_) | OranVariableValue::EnumDefine(_) | OranVariableValue::Module(_) => true,
+            OranVariableValue::Function(_) | OranVariableValue::VmFunction(_) | OranVariableValue::Native(_) => true,
             OranVariableValue::Null => false,
         }
     }
 }
 
-impl From<&OranVariableValue<'_>> for bool {
+impl From<&OranVariableValue> for bool {
     fn from(val: &OranVariableValue) -> Self {
         match val {
             OranVariableValue::Str(ref s) => {
-                if s.val_str.as_ref().to_string() == "true" {
+                if s.val_str.as_ref() == "true" {
                     return true;
-                } else if s.val_str.as_ref().to_string() == "" {
+                } else if s.val_str.as_ref() == "" {
                     return false;
                 }
                 true
             },
+            OranVariableValue::Int(ref i) => { *i != 0 },
+            OranVariableValue::BigInt(ref b) => { !b.is_zero() },
+            OranVariableValue::Decimal(ref d) => { !d.is_zero() },
             OranVariableValue::Float(ref fl) => {
                 if *fl == f64::from(0) {
                     return false;
@@ -103,83 +174,67 @@ impl From<&OranVariableValue<'_>> for bool {
                 true
             },
             OranVariableValue::Boolean(ref bl) => { *bl },
+            OranVariableValue::List(ref l) => { !l.borrow().is_empty() },
+            OranVariableValue::Map(ref m) => { !m.borrow().is_empty() },
+            OranVariableValue::Struct(_) | OranVariableValue::StructDefine(_) | OranVariableValue::Enum(_) | OranVariableValue::EnumDefine(_) | OranVariableValue::Module(_) => true,
+            OranVariableValue::Function(_) | OranVariableValue::VmFunction(_) | OranVariableValue::Native(_) => true,
             OranVariableValue::Null => false,
         }
     }
 }
 
-impl Sub for OranVariableValue<'_> {
-    type Output = Self;
+impl Sub for OranVariableValue {
+    type Output = Result<OranVariableValue, OranError>;
 
     fn sub(self, other: Self) -> Self::Output {
-        match self {
-            OranVariableValue::Float(ref fl) => { OranVariableValue::Float(fl - f64::from(other)) },
-            _ => panic!("Variable types are not Number: {:?}", self)
-        }
+        (OranValue::from(&self) - OranValue::from(&other)).map(OranVariableValue::from)
     }
 }
 
-impl Add for OranVariableValue<'_> {
-    type Output = Self;
+impl Add for OranVariableValue {
+    type Output = Result<OranVariableValue, OranError>;
 
     fn add(self, other: Self) -> Self::Output {
-        match self {
-            OranVariableValue::Float(ref fl) => { OranVariableValue::Float(fl + f64::from(other)) },
-            _ => panic!("Variable types are not Number: {:?}", self)
-        }
+        (OranValue::from(&self) + OranValue::from(&other)).map(OranVariableValue::from)
     }
 }
 
-impl Div for OranVariableValue<'_> {
-    type Output = Self;
+impl Div for OranVariableValue {
+    type Output = Result<OranVariableValue, OranError>;
 
     fn div(self, other: Self) -> Self::Output {
-        match self {
-            OranVariableValue::Float(ref fl) => { OranVariableValue::Float(fl / f64::from(other)) },
-            _ => panic!("Variable types are not Number: {:?}", self)
-        }
+        (OranValue::from(&self) / OranValue::from(&other)).map(OranVariableValue::from)
     }
 }
 
-impl Mul for OranVariableValue<'_> {
-    type Output = Self;
+impl Mul for OranVariableValue {
+    type Output = Result<OranVariableValue, OranError>;
 
     fn mul(self, other: Self) -> Self::Output {
-        match self {
-            OranVariableValue::Float(ref fl) => { OranVariableValue::Float(fl * f64::from(other)) },
-            _ => panic!("Variable types are not Number: {:?}", self)
-        }
+        (OranValue::from(&self) * OranValue::from(&other)).map(OranVariableValue::from)
     }
 }
 
-impl Rem for OranVariableValue<'_> {
-    type Output = Self;
+impl Rem for OranVariableValue {
+    type Output = Result<OranVariableValue, OranError>;
 
     fn rem(self, other: Self) -> Self::Output {
-        match self {
-            OranVariableValue::Float(ref fl) => { OranVariableValue::Float(fl % f64::from(other)) },
-            _ => panic!("Variable types are not Number: {:?}", self)
-        }
-    }
-}
-
-impl From<OranVariableValue<'_>> for f64 {
-    fn from(val: OranVariableValue) -> Self {
-        match val {
-            OranVariableValue::Float(ref fl) => { *fl },
-            OranVariableValue::Str(ref s) => s.val_str.as_ref().parse().unwrap_or_else(|e| panic!("{}", e)),
-            OranVariableValue::Null => { f64::from(0) }
-            _ => panic!("Variable types are not Number: {:?}", val)
-        }
+        (OranValue::from(&self) % OranValue::from(&other)).map(OranVariableValue::from)
     }
 }
 
-impl From<OranVariableValue<'_>> for String {
+impl From<OranVariableValue> for String {
     fn from(val: OranVariableValue) -> Self {
         match val {
             OranVariableValue::Str(ref s) => s.val_str.as_ref().to_string(),
+            OranVariableValue::Int(ref i) => { i.to_string() },
+            OranVariableValue::BigInt(ref b) => { b.to_string() },
+            OranVariableValue::Decimal(ref d) => { d.to_string() },
             OranVariableValue::Float(ref fl) => { fl.to_string() },
             OranVariableValue::Boolean(ref bl) => { bl.to_string() },
+            OranVariableValue::List(_) | OranVariableValue::Map(_) => { val.to_string() },
+            OranVariableValue::Struct(_) | OranVariableValue::StructDefine(_) | OranVariableValue::Enum(_) | OranVariableValue::EnumDefine(_) | OranVariableValue::Module(_) => { val.to_string() },
+            OranVariableValue::Function(_) | OranVariableValue::VmFunction(_) | OranVariableValue::Native(_) => { val.to_string() },
             OranVariableValue::Null => { "".to_string() }
         }
     }
@@ -0,0 +1,29 @@
+use std::fmt;
+
+/// Something in a program that is allowed but is probably a mistake, like a `match` on booleans
+/// without an arm for `false`. Warnings do not stop the program.
+#[derive(PartialEq, Debug, Clone)]
+pub struct OranWarning {
+    pub message: String,
+    pub location: (String, usize, usize),
+}
+
+impl OranWarning {
+    pub fn new(location: &(String, usize, usize), message: &str) -> Self {
+        OranWarning {
+            message: message.to_owned(),
+            location: location.clone(),
+        }
+    }
+}
+
+impl fmt::Display for OranWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\nLine number: {}, column number:{}: {}",
+            self.location.0,
+            self.location.1,
+            self.location.2,
+            self.message
+        )
+    }
+}
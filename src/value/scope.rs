@@ -1 +1,129 @@
-pub static MAIN_FUNCTION: usize = 0;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use super::oran_string::OranString;
+use super::oran_value::OranValue;
+use super::var_type::FunctionOrValueType;
+use crate::interpreter::module::Modules;
+
+/// One frame of bindings: the top level, a function call or a block.
+#[derive(Debug, Default)]
+pub struct Frame {
+    values: HashMap<OranString, OranValue>,
+    functions: HashMap<OranString, OranValue>,
+    parent: Option<Scope>,
+    /// The modules of the engine, which only the top level has.
+    modules: Option<Rc<Modules>>,
+}
+
+impl Frame {
+    fn bindings(&self, kind: FunctionOrValueType) -> &HashMap<OranString, OranValue> {
+        match kind {
+            FunctionOrValueType::Value => &self.values,
+            FunctionOrValueType::Function => &self.functions,
+        }
+    }
+
+    fn bindings_mut(&mut self, kind: FunctionOrValueType) -> &mut HashMap<OranString, OranValue> {
+        match kind {
+            FunctionOrValueType::Value => &mut self.values,
+            FunctionOrValueType::Function => &mut self.functions,
+        }
+    }
+}
+
+/// A chain of frames. Names are looked up from the innermost frame outwards,
+/// so inner bindings shadow outer ones and a function body sees the scope
+/// where the function was defined, not the scope of its caller.
+#[derive(Clone, Debug, Default)]
+pub struct Scope(Rc<RefCell<Frame>>);
+
+impl Scope {
+    /// Creates the top level of a program, whose imports go to the modules.
+    pub fn new(modules: Rc<Modules>) -> Self {
+        Scope(Rc::new(RefCell::new(Frame {
+            modules: Some(modules),
+            ..Frame::default()
+        })))
+    }
+
+    /// Creates a frame whose enclosing frame is this scope.
+    pub fn new_child(&self) -> Self {
+        Scope(Rc::new(RefCell::new(Frame {
+            values: HashMap::new(),
+            functions: HashMap::new(),
+            parent: Some(self.clone()),
+            modules: None,
+        })))
+    }
+
+    /// The modules of the top level that this scope is in.
+    pub fn modules(&self) -> Rc<Modules> {
+        let frame = self.0.borrow();
+        match frame.modules {
+            Some(ref modules) => modules.clone(),
+            None => frame.parent.as_ref().unwrap().modules(),
+        }
+    }
+
+    pub fn get(&self, kind: FunctionOrValueType, name: &str) -> Option<OranValue> {
+        let frame = self.0.borrow();
+        match frame.bindings(kind).get(name) {
+            Some(val) => Some(val.clone()),
+            None => match frame.parent {
+                Some(ref parent) => parent.get(kind, name),
+                None => None,
+            }
+        }
+    }
+
+    /// Looks a name up in both namespaces. Each frame is searched for the preferred kind first,
+    /// so the nearest binding wins: a variable holding a function shadows an outer function of the same name.
+    pub fn lookup(&self, preferred: FunctionOrValueType, name: &str) -> Option<OranValue> {
+        let other = match preferred {
+            FunctionOrValueType::Value => FunctionOrValueType::Function,
+            FunctionOrValueType::Function => FunctionOrValueType::Value,
+        };
+        let frame = self.0.borrow();
+        match frame.bindings(preferred).get(name).or_else(|| frame.bindings(other).get(name)) {
+            Some(val) => Some(val.clone()),
+            None => match frame.parent {
+                Some(ref parent) => parent.lookup(preferred, name),
+                None => None,
+            }
+        }
+    }
+
+    pub fn ptr_eq(&self, other: &Scope) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// The bindings of the innermost frame, sorted by name.
+    pub fn local_bindings(&self, kind: FunctionOrValueType) -> Vec<(OranString, OranValue)> {
+        let frame = self.0.borrow();
+        let mut bindings: Vec<(OranString, OranValue)> = frame.bindings(kind).iter()
+            .map(|(name, val)| (name.clone(), val.clone()))
+            .collect();
+        bindings.sort_by(|a, b| a.0.val_str.cmp(&b.0.val_str));
+        bindings
+    }
+
+    /// Binds a name in the innermost frame, shadowing any outer binding.
+    pub fn define(&self, kind: FunctionOrValueType, name: &str, val: OranValue) {
+        self.0.borrow_mut().bindings_mut(kind).insert(OranString::from(name), val);
+    }
+
+    /// Replaces the nearest existing binding of the name.
+    /// Returns `false` if the name is not bound anywhere in the chain.
+    pub fn assign(&self, kind: FunctionOrValueType, name: &str, val: OranValue) -> bool {
+        let mut frame = self.0.borrow_mut();
+        if let Some(bound) = frame.bindings_mut(kind).get_mut(name) {
+            *bound = val;
+            return true;
+        }
+        match frame.parent {
+            Some(ref parent) => parent.assign(kind, name, val),
+            None => false,
+        }
+    }
+}
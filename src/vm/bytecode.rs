@@ -0,0 +1,148 @@
+use std::rc::Rc;
+use crate::parser::astnode::{CalcOp, LogicalOperatorType};
+use crate::value::oran_value::OranValue;
+use crate::value::oran_error::ErrorKind;
+
+/// Whether a method may change the value it is called on through `self`.
+#[derive(Debug, Clone, Copy)]
+pub enum Receiver {
+    /// A value of a `let mut` variable, or a value that is not in a variable.
+    Variable,
+    /// A value of a constant, like a `let` variable or an argument.
+    Constant,
+    /// `self` of the method whose frame is `depth` out, which is as changeable as the value that method was called on.
+    Outer(u32),
+}
+
+/// How `self` is changed, for the error when it is a constant.
+#[derive(Debug, Clone, Copy)]
+pub enum SelfChange {
+    Variable,
+    Element,
+    Field,
+}
+
+/// One instruction of the VM.
+/// Slots are positions in the frame of a function call. `depth` counts how many
+/// functions outwards the frame is, so 0 is the frame of the running function.
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    /// Pushes `constants[i]`.
+    Constant(u32),
+    Null,
+    Pop,
+    /// Replaces the value on the top of the stack with a copy to store in a variable.
+    Copy,
+    /// Pushes copies of the given number of values on the top of the stack.
+    Dup(u32),
+    GetLocal(u32),
+    GetOuter(u32, u32),
+    /// Pops a value and binds it to a slot of the current frame.
+    DefineLocal(u32),
+    /// Pops a value and stores it in a slot that must be bound already.
+    SetLocal(u32),
+    SetOuter(u32, u32),
+    GetFunction(u32, u32),
+    /// Pushes `functions[i]` as a function that captures the current frame.
+    Function(u32),
+    /// Calls the function below the given number of arguments.
+    /// The name it was called with is recorded for this instruction, but not for a value like `f()(1)`.
+    Call(u32),
+    /// Calls a method on the value below the given number of arguments.
+    /// The name of the method is recorded for this instruction.
+    Method(u32, Receiver),
+    /// Fails when the method whose frame is `depth` out was called on a constant, before `self` is changed.
+    CheckReceiver(u32, SelfChange),
+    /// Calls `BUILTINS[i]` with the given number of arguments.
+    Builtin(u32, u32),
+    Return,
+    Calc(CalcOp),
+    Compare(LogicalOperatorType),
+    /// Pops a value and pushes the opposite of its truthiness.
+    Not,
+    Negate,
+    UnaryPlus,
+    BitNot,
+    /// Pops the last and the first numbers of a range and a value, and pushes whether the value is in the range.
+    /// The flag tells whether the last number is included.
+    InRange(bool),
+    Concat(u32),
+    List(u32),
+    Map(u32),
+    Index,
+    SetIndex,
+    /// Pops a struct and the given number of field names and values, and pushes a new instance of the struct.
+    /// The name of the struct is recorded for this instruction.
+    Struct(u32),
+    /// Pops an instance and pushes one of its fields. The name of the field is recorded for this instruction.
+    GetField,
+    /// Pops a value and an instance and stores the value in a field, whose name is recorded for this instruction.
+    SetField,
+    /// Pops an enum and the given number of values, and pushes a value of a variant of the enum.
+    /// The enum and the variant are recorded for this instruction like `Shape::Circle`.
+    Variant(u32),
+    /// Pops an enum and a value, and pushes whether the value is of a variant of the enum, which is recorded
+    /// for this instruction. The number is how many names the pattern gives to the values of the variant.
+    MatchVariant(Option<u32>),
+    /// Pops a value of an enum and pushes the value of its variant at the given position.
+    Payload(u32),
+    /// Imports the module at the path recorded for this instruction and pushes it.
+    Import,
+    /// Pops a function and a struct, and adds the function to the methods of the struct.
+    /// The name of the struct is recorded for this instruction.
+    DefineMethod,
+    Jump(u32),
+    JumpIfFalse(u32),
+    JumpIfTrue(u32),
+    /// Pops the end and the start of a range and keeps the next number and the last one in two slots from the given one.
+    RangeInit(u32, bool),
+    /// Moves the range in `counter` forward and binds the number to `var`, or jumps to `exit`.
+    RangeNext(u32, u32, u32),
+    /// Pops a list or a map and starts iterating over it. The flags tell if the loop has two variables,
+    /// and if they are mutable, so that they get copies of the elements.
+    IterInit(bool, bool),
+    /// Binds the next item to the slot (and the one after it for two variables), or jumps to `exit`.
+    IterNext(u32, u32),
+    IterPop,
+    /// Runs the next instructions in a new frame with the given number of slots, whose parent is the current frame.
+    EnterFrame(u32),
+    /// Goes back to the parent of the current frame.
+    LeaveFrame,
+    /// Starts a try block. An error in it goes to the given instruction,
+    /// with the frame and the stack of the start and the value that `catch` gets pushed.
+    Try(u32),
+    /// Ends the innermost try block.
+    EndTry,
+    /// Pops a value and throws it.
+    Throw,
+    /// Stops with a runtime error of this kind. The message is recorded for this instruction.
+    Fail(ErrorKind),
+}
+
+/// Where an instruction came from, for error messages.
+/// `detail` is the name of the variable or function it refers to, or the message of `Op::Fail`.
+#[derive(Debug, PartialEq)]
+pub struct DebugInfo {
+    pub location: (String, usize, usize),
+    pub detail: String,
+}
+
+/// A compiled function body, or the top level of a program.
+#[derive(Debug)]
+pub struct FunctionProto {
+    pub name: String,
+    pub arity: usize,
+    pub slot_count: usize,
+    pub code: Vec<Op>,
+    pub constants: Vec<OranValue>,
+    pub functions: Vec<Rc<FunctionProto>>,
+    /// Index into `debug_info` for each instruction.
+    pub debug: Vec<u32>,
+    pub debug_info: Vec<DebugInfo>,
+}
+
+impl FunctionProto {
+    pub fn debug_info(&self, pc: usize) -> &DebugInfo {
+        &self.debug_info[self.debug[pc] as usize]
+    }
+}
@@ -0,0 +1,1062 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use indexmap::IndexMap;
+use crate::parser::astnode::{AstNode, Accessor, CalcOp, ComparisonlOperatorType, LogicalOperatorType, Pattern, VariantPattern};
+use crate::value::oran_value::{OranValue, StructDefine, EnumDefine};
+use crate::value::oran_string::OranString;
+use crate::value::var_type::{FunctionOrValueType, VarType};
+use crate::value::oran_error::OranError;
+use crate::interpreter::builtin::{self, BUILTINS};
+use crate::interpreter::util;
+use super::bytecode::{Op, DebugInfo, FunctionProto, Receiver, SelfChange};
+
+/// The names declared in one block at compile time.
+/// Blocks are flattened into the frame of their function, so each name gets its own slot.
+#[derive(Default)]
+struct Block {
+    values: HashMap<String, (u32, VarType)>,
+    functions: HashMap<String, u32>,
+    parent: Option<Rc<RefCell<Block>>>,
+    /// How many functions deep the block is.
+    level: usize,
+    /// Whether the block is the body of a method, whose first slot is `self`.
+    has_receiver: bool,
+}
+
+impl Block {
+    fn child(parent: &Rc<RefCell<Block>>, level: usize) -> Rc<RefCell<Block>> {
+        Rc::new(RefCell::new(Block {
+            values: HashMap::new(),
+            functions: HashMap::new(),
+            parent: Some(parent.clone()),
+            level,
+            has_receiver: false,
+        }))
+    }
+}
+
+struct Loop {
+    start: u32,
+    breaks: Vec<usize>,
+    /// Whether each iteration runs in its own frame, which `break` and `continue` must leave.
+    has_frame: bool,
+    /// How many try blocks the loop is in. `break` and `continue` leave the ones in the loop.
+    tries: usize,
+}
+
+/// A try block that the code is in. A jump out of it must end it and run its finally block first.
+#[derive(Clone)]
+struct TryBlock {
+    finally: Option<Rc<Vec<AstNode>>>,
+    /// The block of the try statement, whose names the finally block sees.
+    block: Rc<RefCell<Block>>,
+}
+
+/// A function body waiting to be compiled.
+/// Bodies are compiled after the code around them, so that they can see
+/// names that are declared after the function, the same as the interpreter.
+struct Deferred {
+    name: String,
+    args: Rc<Vec<AstNode>>,
+    body: Rc<Vec<AstNode>>,
+    fn_return: Rc<AstNode>,
+    block: Rc<RefCell<Block>>,
+    index: usize,
+    /// Whether the function is a method, whose first argument `self` can be changed.
+    is_method: bool,
+}
+
+struct FunctionCompiler {
+    name: String,
+    arity: usize,
+    slot_count: u32,
+    code: Vec<Op>,
+    constants: Vec<OranValue>,
+    functions: Vec<Option<Rc<FunctionProto>>>,
+    debug: Vec<u32>,
+    debug_info: Vec<DebugInfo>,
+    block: Rc<RefCell<Block>>,
+    loops: Vec<Loop>,
+    tries: Vec<TryBlock>,
+    deferred: Vec<Deferred>,
+    location: (String, usize, usize),
+}
+
+/// Compiles programs to bytecode.
+/// The top-level block is kept between calls so that later programs can use earlier globals.
+pub struct Compiler {
+    globals: Rc<RefCell<Block>>,
+    slot_count: u32,
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Compiler {
+            globals: Rc::new(RefCell::new(Block::default())),
+            slot_count: 0,
+        }
+    }
+
+    /// Declares a name at the top level and returns its slot.
+    pub fn declare_global(&mut self, kind: FunctionOrValueType, name: &str, var_type: VarType) -> u32 {
+        let slot = match self.global_slot(kind, name) {
+            Some(slot) => slot,
+            None => {
+                self.slot_count += 1;
+                self.slot_count - 1
+            }
+        };
+        let mut block = self.globals.borrow_mut();
+        match kind {
+            FunctionOrValueType::Value => { block.values.insert(name.to_owned(), (slot, var_type)); }
+            FunctionOrValueType::Function => { block.functions.insert(name.to_owned(), slot); }
+        }
+        slot
+    }
+
+    pub fn global_slot(&self, kind: FunctionOrValueType, name: &str) -> Option<u32> {
+        let block = self.globals.borrow();
+        match kind {
+            FunctionOrValueType::Value => block.values.get(name).map(|(slot, _)| *slot),
+            FunctionOrValueType::Function => block.functions.get(name).copied(),
+        }
+    }
+
+    /// The names declared at the top level so far and their slots.
+    pub fn globals(&self, kind: FunctionOrValueType) -> Vec<(String, u32)> {
+        let block = self.globals.borrow();
+        match kind {
+            FunctionOrValueType::Value => block.values.iter().map(|(name, (slot, _))| (name.clone(), *slot)).collect(),
+            FunctionOrValueType::Function => block.functions.iter().map(|(name, slot)| (name.clone(), *slot)).collect(),
+        }
+    }
+
+    pub fn compile_program(&mut self, ast: &[AstNode]) -> Rc<FunctionProto> {
+        let mut compiler = FunctionCompiler::new("main".to_owned(), self.globals.clone(), self.slot_count);
+        // The program returns the value of the last statement if it is an expression.
+        match ast.split_last() {
+            Some((last, stmts)) if last.is_expression() => {
+                for node in stmts {
+                    compiler.compile_stmt(node);
+                }
+                compiler.compile_expr(last);
+            }
+            _ => {
+                for node in ast {
+                    compiler.compile_stmt(node);
+                }
+                compiler.emit(Op::Null);
+            }
+        }
+        compiler.emit(Op::Return);
+        self.slot_count = compiler.slot_count;
+        compiler.finish()
+    }
+}
+
+impl FunctionCompiler {
+    fn new(name: String, block: Rc<RefCell<Block>>, slot_count: u32) -> Self {
+        FunctionCompiler {
+            name,
+            arity: 0,
+            slot_count,
+            code: vec![],
+            constants: vec![],
+            functions: vec![],
+            debug: vec![],
+            debug_info: vec![],
+            block,
+            loops: vec![],
+            tries: vec![],
+            deferred: vec![],
+            location: ("".to_owned(), 0, 0),
+        }
+    }
+
+    /// Compiles the deferred function bodies and builds the prototype.
+    fn finish(mut self) -> Rc<FunctionProto> {
+        for deferred in std::mem::take(&mut self.deferred) {
+            let index = deferred.index;
+            self.functions[index] = Some(compile_function(deferred));
+        }
+        Rc::new(FunctionProto {
+            name: self.name,
+            arity: self.arity,
+            slot_count: self.slot_count as usize,
+            code: self.code,
+            constants: self.constants,
+            functions: self.functions.into_iter().map(Option::unwrap).collect(),
+            debug: self.debug,
+            debug_info: self.debug_info,
+        })
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.emit_detail(op, "")
+    }
+
+    fn emit_detail(&mut self, op: Op, detail: &str) -> usize {
+        let info = DebugInfo { location: self.location.clone(), detail: detail.to_owned() };
+        if self.debug_info.last() != Some(&info) {
+            self.debug_info.push(info);
+        }
+        self.debug.push(self.debug_info.len() as u32 - 1);
+        self.code.push(op);
+        self.code.len() - 1
+    }
+
+    /// Compiles a check that failed at compile time to an instruction that fails when it runs,
+    /// so that the statements before it still run like in the tree-walking interpreter.
+    fn fail(&mut self, error: OranError) {
+        self.emit_detail(Op::Fail(error.kind), &error.message);
+    }
+
+    /// Pushes the enum with the given name, which is `Option` or `Result` when the program does not declare it.
+    fn enum_define(&mut self, name: &str) {
+        match self.resolve(FunctionOrValueType::Value, name) {
+            Some((depth, slot, _)) => self.get_value(depth, slot, name),
+            None => match util::enum_define(&self.location, name, None) {
+                Ok(define) => self.constant(OranValue::EnumDefine(define)),
+                Err(error) => {
+                    self.fail(error);
+                    self.emit(Op::Null);
+                }
+            },
+        }
+    }
+
+    fn constant(&mut self, val: OranValue) {
+        self.constants.push(val);
+        let index = self.constants.len() as u32 - 1;
+        self.emit(Op::Constant(index));
+    }
+
+    fn here(&self) -> u32 {
+        self.code.len() as u32
+    }
+
+    /// Points the jump at `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let target = self.here();
+        match self.code[at] {
+            Op::Jump(ref mut t) | Op::JumpIfFalse(ref mut t) | Op::JumpIfTrue(ref mut t) | Op::Try(ref mut t) => *t = target,
+            Op::RangeNext(_, _, ref mut t) | Op::IterNext(_, ref mut t) => *t = target,
+            op => unreachable!("{:?} is not a jump", op),
+        }
+    }
+
+    fn new_slot(&mut self) -> u32 {
+        self.slot_count += 1;
+        self.slot_count - 1
+    }
+
+    fn level(&self) -> usize {
+        self.block.borrow().level
+    }
+
+    fn enter_block(&mut self) {
+        let level = self.level();
+        self.block = Block::child(&self.block, level);
+    }
+
+    fn leave_block(&mut self) {
+        let parent = self.block.borrow().parent.clone();
+        self.block = parent.unwrap();
+    }
+
+    /// Binds a value name in the current block. A name declared again in the same block keeps its slot.
+    fn declare_value(&mut self, name: &str, var_type: VarType) -> u32 {
+        let existing = self.block.borrow().values.get(name).map(|(slot, _)| *slot);
+        let slot = match existing {
+            Some(slot) => slot,
+            None => self.new_slot(),
+        };
+        self.block.borrow_mut().values.insert(name.to_owned(), (slot, var_type));
+        slot
+    }
+
+    fn declare_function(&mut self, name: &str) -> u32 {
+        let existing = self.block.borrow().functions.get(name).copied();
+        let slot = match existing {
+            Some(slot) => slot,
+            None => self.new_slot(),
+        };
+        self.block.borrow_mut().functions.insert(name.to_owned(), slot);
+        slot
+    }
+
+    /// Finds the nearest binding of a name in either namespace, preferring the given kind in each block.
+    /// Returns its depth and slot, and the kind that was found.
+    fn resolve_preferring(&self, preferred: FunctionOrValueType, name: &str) -> Option<(u32, u32, FunctionOrValueType)> {
+        let level = self.level();
+        let mut block = Some(self.block.clone());
+        while let Some(current) = block {
+            let current = current.borrow();
+            let value = current.values.get(name).map(|(slot, _)| (*slot, FunctionOrValueType::Value));
+            let function = current.functions.get(name).map(|slot| (*slot, FunctionOrValueType::Function));
+            let found = match preferred {
+                FunctionOrValueType::Value => value.or(function),
+                FunctionOrValueType::Function => function.or(value),
+            };
+            if let Some((slot, kind)) = found {
+                return Some(((level - current.level) as u32, slot, kind));
+            }
+            block = current.parent.clone();
+        }
+        None
+    }
+
+    /// Finds the nearest binding of a name and returns its depth, slot and type.
+    fn resolve(&self, kind: FunctionOrValueType, name: &str) -> Option<(u32, u32, VarType)> {
+        let level = self.level();
+        let mut block = Some(self.block.clone());
+        while let Some(current) = block {
+            let current = current.borrow();
+            let found = match kind {
+                FunctionOrValueType::Value => current.values.get(name).copied(),
+                FunctionOrValueType::Function => current.functions.get(name).map(|slot| (*slot, VarType::Constant)),
+            };
+            if let Some((slot, var_type)) = found {
+                return Some(((level - current.level) as u32, slot, var_type));
+            }
+            block = current.parent.clone();
+        }
+        None
+    }
+
+    /// The depth of the method whose `self` the name is, if it is.
+    fn resolve_receiver(&self, name: &str) -> Option<u32> {
+        let level = self.level();
+        let mut block = Some(self.block.clone());
+        while let Some(current) = block {
+            let current = current.borrow();
+            if let Some((slot, _)) = current.values.get(name) {
+                let is_receiver = current.has_receiver && *slot == 0;
+                return if is_receiver { Some((level - current.level) as u32) } else { None };
+            }
+            block = current.parent.clone();
+        }
+        None
+    }
+
+    /// Whether the value that a method is called on may be changed through `self`.
+    /// A value in a constant can't, even when it is reached through a field or an index.
+    fn receiver(&self, node: &AstNode) -> Receiver {
+        match node {
+            AstNode::Ident(_, name) => match self.resolve(FunctionOrValueType::Value, name) {
+                Some((_, _, VarType::Constant)) => Receiver::Constant,
+                Some(_) => match self.resolve_receiver(name) {
+                    Some(depth) => Receiver::Outer(depth),
+                    None => Receiver::Variable,
+                },
+                None => Receiver::Variable,
+            },
+            AstNode::Index(_, target, _) | AstNode::Field(_, target, _) => self.receiver(target),
+            _ => Receiver::Variable,
+        }
+    }
+
+    /// Checks that `self` can be changed, when the name is `self` of a method.
+    fn check_receiver(&mut self, name: &str, change: SelfChange) {
+        if let Some(depth) = self.resolve_receiver(name) {
+            self.emit(Op::CheckReceiver(depth, change));
+        }
+    }
+
+    fn compile_block(&mut self, stmts: &[AstNode]) {
+        self.enter_block();
+        for stmt in stmts {
+            self.compile_stmt(stmt);
+        }
+        self.leave_block();
+    }
+
+    /// Compiles the value an element or a field is set to. With the operator of `xs[i] += v`,
+    /// it is calculated from the current value, which is on the stack.
+    fn compile_value_of(&mut self, expr: &AstNode, compound: &Option<((String, usize, usize), CalcOp)>) {
+        self.compile_expr(expr);
+        if let Some((op_location, verb)) = compound {
+            self.location = op_location.clone();
+            self.emit(Op::Calc(*verb));
+        }
+    }
+
+    /// Compiles the finally block of a try statement where the code leaves the statement.
+    /// It sees the names around the statement, but runs in the current frame, which can be a frame of a loop in it.
+    fn compile_finally(&mut self, try_block: &TryBlock) {
+        if let Some(finally) = &try_block.finally {
+            let level = self.level();
+            let block = std::mem::replace(&mut self.block, Block::child(&try_block.block, level));
+            for stmt in finally.iter() {
+                self.compile_stmt(stmt);
+            }
+            self.block = block;
+        }
+    }
+
+    /// Ends the try blocks after the first `keep` ones, from the inside out, and runs their finally blocks
+    /// before a jump out of them. A jump in a finally block leaves only the try blocks around it.
+    fn leave_tries(&mut self, keep: usize) {
+        let tries = std::mem::take(&mut self.tries);
+        for (i, try_block) in tries.iter().enumerate().skip(keep).rev() {
+            self.tries = tries[..i].to_vec();
+            self.emit(Op::EndTry);
+            self.compile_finally(try_block);
+        }
+        self.tries = tries;
+    }
+
+    /// Compiles a loop body. `vars` are the loop variables and the slots where the loop instruction puts them.
+    ///
+    /// Blocks are flattened into the frame of their function, so a function created in a loop would
+    /// see the variables of the last iteration. When the body creates functions, each iteration gets
+    /// its own frame instead, like the scopes of the interpreter.
+    fn compile_loop_body(&mut self, stmts: &[AstNode], start: u32, vars: &[(&str, u32, VarType)]) -> Vec<usize> {
+        let has_frame = stmts.iter().any(AstNode::defines_function);
+        self.loops.push(Loop { start, breaks: vec![], has_frame, tries: self.tries.len() });
+        if has_frame {
+            let enter = self.emit(Op::EnterFrame(0));
+            let level = self.level() + 1;
+            self.block = Block::child(&self.block, level);
+            let outer_slot_count = std::mem::replace(&mut self.slot_count, 0);
+            for (name, hidden, var_type) in vars {
+                self.emit(Op::GetOuter(1, *hidden));
+                let slot = self.declare_value(name, *var_type);
+                self.emit(Op::DefineLocal(slot));
+            }
+            for stmt in stmts {
+                self.compile_stmt(stmt);
+            }
+            self.code[enter] = Op::EnterFrame(self.slot_count);
+            self.slot_count = outer_slot_count;
+            self.leave_block();
+            self.emit(Op::LeaveFrame);
+        } else {
+            for (name, slot, var_type) in vars {
+                self.block.borrow_mut().values.insert(name.to_string(), (*slot, *var_type));
+            }
+            for stmt in stmts {
+                self.compile_stmt(stmt);
+            }
+        }
+        self.emit(Op::Jump(start));
+        self.loops.pop().unwrap().breaks
+    }
+
+    /// Compiles a node whose value is not used.
+    fn compile_stmt(&mut self, node: &AstNode) {
+        match node {
+            AstNode::Assign(location, variable_type, ident, expr) => {
+                self.location = location.clone();
+                if *variable_type == VarType::VariableReAssigned {
+                    match self.resolve(FunctionOrValueType::Value, ident) {
+                        None => self.fail(util::assignment_without_let(&self.location)),
+                        Some((_, _, VarType::Constant)) => self.fail(util::constant_assignment(&self.location)),
+                        Some((depth, slot, _)) => {
+                            self.check_receiver(ident, SelfChange::Variable);
+                            self.compile_expr(expr);
+                            self.emit(Op::Copy);
+                            self.location = location.clone();
+                            if depth == 0 {
+                                self.emit_detail(Op::SetLocal(slot), ident);
+                            } else {
+                                self.emit_detail(Op::SetOuter(depth, slot), ident);
+                            }
+                        }
+                    }
+                } else {
+                    self.compile_expr(expr);
+                    self.emit(Op::Copy);
+                    let slot = self.declare_value(ident, *variable_type);
+                    self.emit(Op::DefineLocal(slot));
+                }
+            }
+            AstNode::FunctionDefine(location, name, args, body, fn_return) => {
+                self.location = location.clone();
+                self.function(name, args, body, fn_return, false);
+                let slot = self.declare_function(name);
+                self.emit(Op::DefineLocal(slot));
+            }
+            AstNode::IF(location, if_conditions, body, else_if_bodies_conditions, else_bodies) => {
+                let mut ends = vec![];
+                self.compile_expr(if_conditions);
+                self.location = location.clone();
+                let next = self.emit(Op::JumpIfFalse(0));
+                self.compile_block(body);
+                ends.push(self.emit(Op::Jump(0)));
+                self.patch(next);
+                for (conditions, else_if_body) in else_if_bodies_conditions {
+                    let mut matched = vec![];
+                    for c in conditions {
+                        self.compile_expr(c);
+                        matched.push(self.emit(Op::JumpIfTrue(0)));
+                    }
+                    let next = self.emit(Op::Jump(0));
+                    for at in matched {
+                        self.patch(at);
+                    }
+                    self.compile_block(else_if_body);
+                    ends.push(self.emit(Op::Jump(0)));
+                    self.patch(next);
+                }
+                self.compile_block(else_bodies);
+                for at in ends {
+                    self.patch(at);
+                }
+            }
+            AstNode::ForLoop(location, is_inclusive, var_type, i, first, last, stmts) => {
+                self.compile_expr(first);
+                self.compile_expr(last);
+                self.location = location.clone();
+                let counter = self.new_slot();
+                self.new_slot();
+                self.emit(Op::RangeInit(counter, *is_inclusive));
+                self.enter_block();
+                let var = self.new_slot();
+                let start = self.here();
+                let next = self.emit(Op::RangeNext(counter, var, 0));
+                let breaks = self.compile_loop_body(stmts, start, &[(i, var, *var_type)]);
+                self.leave_block();
+                self.patch(next);
+                for at in breaks {
+                    self.patch(at);
+                }
+            }
+            AstNode::ForEach(location, var_type, first_name, second_name, iterable, stmts) => {
+                self.compile_expr(iterable);
+                self.location = location.clone();
+                self.emit(Op::IterInit(second_name.is_some(), *var_type != VarType::Constant));
+                self.enter_block();
+                // The loop variables take two fresh slots next to each other.
+                let first = self.new_slot();
+                let second = self.new_slot();
+                let mut vars = vec![(first_name.as_str(), first, *var_type)];
+                if let Some(second_name) = second_name {
+                    vars.push((second_name.as_str(), second, *var_type));
+                }
+                let start = self.here();
+                let next = self.emit(Op::IterNext(first, 0));
+                let breaks = self.compile_loop_body(stmts, start, &vars);
+                self.leave_block();
+                self.patch(next);
+                for at in breaks {
+                    self.patch(at);
+                }
+                self.emit(Op::IterPop);
+            }
+            AstNode::WhileLoop(location, condition, stmts) => {
+                let start = self.here();
+                self.compile_expr(condition);
+                self.location = location.clone();
+                let exit = self.emit(Op::JumpIfFalse(0));
+                self.enter_block();
+                let breaks = self.compile_loop_body(stmts, start, &[]);
+                self.leave_block();
+                self.patch(exit);
+                for at in breaks {
+                    self.patch(at);
+                }
+            }
+            AstNode::Break(location) | AstNode::Continue(location) => {
+                self.location = location.clone();
+                if self.loops.is_empty() {
+                    self.fail(util::loop_control_outside_loop(&self.location));
+                    return;
+                }
+                let keep = self.loops.last().unwrap().tries;
+                self.leave_tries(keep);
+                self.location = location.clone();
+                if self.loops.last().unwrap().has_frame {
+                    self.emit(Op::LeaveFrame);
+                }
+                if let AstNode::Break(_) = node {
+                    let at = self.emit(Op::Jump(0));
+                    self.loops.last_mut().unwrap().breaks.push(at);
+                } else {
+                    let start = self.loops.last().unwrap().start;
+                    self.emit(Op::Jump(start));
+                }
+            }
+            AstNode::Return(location, val) => {
+                self.compile_expr(val);
+                self.leave_tries(0);
+                self.location = location.clone();
+                self.emit(Op::Return);
+            }
+            AstNode::Throw(location, val) => {
+                self.compile_expr(val);
+                self.location = location.clone();
+                self.emit(Op::Throw);
+            }
+            AstNode::Try(location, body, catch, finally) => {
+                self.location = location.clone();
+                let try_block = TryBlock { finally: finally.clone().map(Rc::new), block: self.block.clone() };
+                let handler = self.emit(Op::Try(0));
+                self.tries.push(try_block.clone());
+                self.compile_block(body);
+                self.tries.pop();
+                self.location = location.clone();
+                self.emit(Op::EndTry);
+                self.compile_finally(&try_block);
+                let mut ends = vec![self.emit(Op::Jump(0))];
+                // The caught error is on the stack.
+                self.patch(handler);
+                if let Some((name, catch_body)) = catch {
+                    self.enter_block();
+                    let slot = self.declare_value(name, VarType::Constant);
+                    self.emit(Op::DefineLocal(slot));
+                    // An error in catch runs finally too, and goes on.
+                    let handler = try_block.finally.as_ref().map(|_| self.emit(Op::Try(0)));
+                    if handler.is_some() {
+                        self.tries.push(try_block.clone());
+                    }
+                    for stmt in catch_body {
+                        self.compile_stmt(stmt);
+                    }
+                    self.leave_block();
+                    if let Some(handler) = handler {
+                        self.tries.pop();
+                        self.location = location.clone();
+                        self.emit(Op::EndTry);
+                        self.compile_finally(&try_block);
+                        ends.push(self.emit(Op::Jump(0)));
+                        self.patch(handler);
+                    }
+                }
+                // An error that is not caught runs finally, and is thrown again.
+                if try_block.finally.is_some() {
+                    self.compile_finally(&try_block);
+                    self.location = location.clone();
+                    self.emit(Op::Throw);
+                }
+                for at in ends {
+                    self.patch(at);
+                }
+            }
+            AstNode::IndexAssign(location, ident, accessors, compound, expr) => {
+                self.location = location.clone();
+                match self.resolve(FunctionOrValueType::Value, ident) {
+                    None => self.fail(util::undefined_variable(&self.location, ident)),
+                    Some((_, _, VarType::Constant)) => self.fail(util::constant_element_assignment(&self.location, &accessors[0])),
+                    Some((depth, slot, _)) => {
+                        let change = match accessors[0] {
+                            Accessor::Index(_) => SelfChange::Element,
+                            Accessor::Field(_) => SelfChange::Field,
+                        };
+                        self.check_receiver(ident, change);
+                        self.get_value(depth, slot, ident);
+                        let (last, accessors) = accessors.split_last().unwrap();
+                        for accessor in accessors {
+                            match accessor {
+                                Accessor::Index(index) => {
+                                    self.compile_expr(index);
+                                    self.location = location.clone();
+                                    self.emit(Op::Index);
+                                }
+                                Accessor::Field(field) => {
+                                    self.emit_detail(Op::GetField, field);
+                                }
+                            }
+                        }
+                        // `xs[i] += v` copies the list and the index to read the element before it is set.
+                        match last {
+                            Accessor::Index(index) => {
+                                self.compile_expr(index);
+                                if compound.is_some() {
+                                    self.emit(Op::Dup(2));
+                                    self.location = location.clone();
+                                    self.emit(Op::Index);
+                                }
+                                self.compile_value_of(expr, compound);
+                                self.location = location.clone();
+                                self.emit(Op::SetIndex);
+                            }
+                            Accessor::Field(field) => {
+                                if compound.is_some() {
+                                    self.emit(Op::Dup(1));
+                                    self.location = location.clone();
+                                    self.emit_detail(Op::GetField, field);
+                                }
+                                self.compile_value_of(expr, compound);
+                                self.location = location.clone();
+                                self.emit_detail(Op::SetField, field);
+                            }
+                        }
+                    }
+                }
+            }
+            AstNode::StructDefine(location, name, fields) => {
+                self.location = location.clone();
+                self.constant(OranValue::StructDefine(Rc::new(StructDefine {
+                    name: OranString::from(name),
+                    fields: fields.clone(),
+                    methods: RefCell::new(IndexMap::new()),
+                })));
+                let slot = self.declare_value(name, VarType::Constant);
+                self.emit(Op::DefineLocal(slot));
+            }
+            AstNode::Import(location, path, name) => {
+                self.location = location.clone();
+                self.emit_detail(Op::Import, path);
+                let slot = self.declare_value(name, VarType::Constant);
+                self.emit(Op::DefineLocal(slot));
+            }
+            AstNode::EnumDefine(location, name, variants) => {
+                self.location = location.clone();
+                self.constant(OranValue::EnumDefine(Rc::new(EnumDefine {
+                    name: OranString::from(name),
+                    variants: variants.clone(),
+                })));
+                let slot = self.declare_value(name, VarType::Constant);
+                self.emit(Op::DefineLocal(slot));
+            }
+            AstNode::Impl(location, name, methods) => {
+                for method in methods {
+                    if let AstNode::FunctionDefine(_, method_name, args, body, fn_return) = method {
+                        self.location = location.clone();
+                        match self.resolve(FunctionOrValueType::Value, name) {
+                            Some((depth, slot, _)) => {
+                                self.get_value(depth, slot, name);
+                                self.function(method_name, args, body, fn_return, true);
+                                self.emit_detail(Op::DefineMethod, name);
+                            }
+                            None => self.fail(util::undefined_struct(&self.location, name)),
+                        }
+                    }
+                }
+            }
+            _ => {
+                self.compile_expr(node);
+                self.emit(Op::Pop);
+            }
+        }
+    }
+
+    /// Defers the body of a function and pushes the function, which captures the current frame.
+    fn function(&mut self, name: &str, args: &Rc<Vec<AstNode>>, body: &Rc<Vec<AstNode>>, fn_return: &Rc<AstNode>, is_method: bool) {
+        self.functions.push(None);
+        let index = self.functions.len() - 1;
+        self.deferred.push(Deferred {
+            name: name.to_owned(),
+            args: args.clone(),
+            body: body.clone(),
+            fn_return: fn_return.clone(),
+            block: self.block.clone(),
+            index,
+            is_method,
+        });
+        self.emit(Op::Function(index as u32));
+    }
+
+    fn get_value(&mut self, depth: u32, slot: u32, name: &str) {
+        if depth == 0 {
+            self.emit_detail(Op::GetLocal(slot), name);
+        } else {
+            self.emit_detail(Op::GetOuter(depth, slot), name);
+        }
+    }
+
+    /// Compiles a node that leaves exactly one value on the stack.
+    fn compile_expr(&mut self, node: &AstNode) {
+        match node {
+            AstNode::Int(location, int) => {
+                self.location = location.clone();
+                self.constant(OranValue::Int(*int));
+            }
+            AstNode::BigInt(location, int) => {
+                self.location = location.clone();
+                self.constant(OranValue::BigInt(int.clone()));
+            }
+            AstNode::Decimal(location, decimal) => {
+                self.location = location.clone();
+                self.constant(OranValue::Decimal(*decimal));
+            }
+            AstNode::Number(location, double) => {
+                self.location = location.clone();
+                self.constant(OranValue::Float(*double));
+            }
+            AstNode::Str(location, str_val) => {
+                self.location = location.clone();
+                self.constant(OranValue::Str(OranString::from(str_val)));
+            }
+            AstNode::Bool(location, b) => {
+                self.location = location.clone();
+                self.constant(OranValue::Boolean(*b));
+            }
+            AstNode::Null => {
+                self.emit(Op::Null);
+            }
+            AstNode::Calc(location, verb, lhs, rhs) => {
+                self.compile_expr(lhs);
+                self.compile_expr(rhs);
+                self.location = location.clone();
+                self.emit(Op::Calc(*verb));
+            }
+            AstNode::Ident(location, ident) => {
+                self.location = location.clone();
+                // A function name without a call is the function itself.
+                match self.resolve_preferring(FunctionOrValueType::Value, ident) {
+                    Some((depth, slot, FunctionOrValueType::Value)) => self.get_value(depth, slot, ident),
+                    Some((depth, slot, FunctionOrValueType::Function)) => {
+                        self.emit_detail(Op::GetFunction(depth, slot), ident);
+                    }
+                    None => self.fail(util::undefined_variable(&self.location, ident)),
+                }
+            }
+            AstNode::FunctionCall(location, name, arg_values) => {
+                self.location = location.clone();
+                // A variable or a function takes the place of the builtin function of the same name.
+                let callee = self.resolve_preferring(FunctionOrValueType::Function, name);
+                if let (None, Some(index)) = (callee, BUILTINS.iter().position(|builtin| builtin == name)) {
+                    if builtin::changes_first_arg(name) {
+                        match arg_values.first().map(|first| self.receiver(first)) {
+                            Some(Receiver::Constant) => return self.fail(util::constant_element_change(&self.location)),
+                            Some(Receiver::Outer(depth)) => { self.emit(Op::CheckReceiver(depth, SelfChange::Element)); }
+                            _ => {}
+                        }
+                    }
+                    for arg in arg_values {
+                        self.compile_expr(arg);
+                    }
+                    self.location = location.clone();
+                    self.emit(Op::Builtin(index as u32, arg_values.len() as u32));
+                    return;
+                }
+                // Variables holding functions can be called like functions.
+                match callee {
+                    Some((depth, slot, _)) => {
+                        self.emit_detail(Op::GetFunction(depth, slot), name);
+                        for arg in arg_values {
+                            self.compile_expr(arg);
+                        }
+                        self.location = location.clone();
+                        self.emit_detail(Op::Call(arg_values.len() as u32), name);
+                    }
+                    None => self.fail(util::undefined_function(&self.location, name)),
+                }
+            }
+            AstNode::MethodCall(location, receiver, name, arg_values) => {
+                self.compile_expr(receiver);
+                for arg in arg_values {
+                    self.compile_expr(arg);
+                }
+                self.location = location.clone();
+                let receiver = self.receiver(receiver);
+                self.emit_detail(Op::Method(arg_values.len() as u32, receiver), name);
+            }
+            AstNode::Call(location, callee, arg_values) => {
+                self.compile_expr(callee);
+                for arg in arg_values {
+                    self.compile_expr(arg);
+                }
+                self.location = location.clone();
+                self.emit(Op::Call(arg_values.len() as u32));
+            }
+            AstNode::AnonymousFunction(location, args, body, fn_return) => {
+                self.location = location.clone();
+                self.function("", args, body, fn_return, false);
+            }
+            AstNode::Argument(location, argument_name, val) => {
+                self.compile_expr(val);
+                self.location = location.clone();
+                let slot = self.declare_value(argument_name, VarType::Constant);
+                self.emit(Op::DefineLocal(slot));
+                self.constant(OranValue::Str(OranString::from(argument_name)));
+            }
+            AstNode::Strs(location, strs) => {
+                for str in strs {
+                    self.compile_expr(str);
+                }
+                self.location = location.clone();
+                self.emit(Op::Concat(strs.len() as u32));
+            }
+            AstNode::Condition(c, e, o) => {
+                // The right side runs only when the left side does not decide the result.
+                let (short_circuit, decided): (fn(u32) -> Op, bool) = match c {
+                    ComparisonlOperatorType::And => (Op::JumpIfFalse, false),
+                    ComparisonlOperatorType::Or => (Op::JumpIfTrue, true),
+                };
+                self.compile_expr(e);
+                let left = self.emit(short_circuit(0));
+                self.compile_expr(o);
+                let right = self.emit(short_circuit(0));
+                self.constant(OranValue::Boolean(!decided));
+                let end = self.emit(Op::Jump(0));
+                self.patch(left);
+                self.patch(right);
+                self.constant(OranValue::Boolean(decided));
+                self.patch(end);
+            }
+            AstNode::Match(location, value, arms) => {
+                // The value is evaluated once into a slot, and then compared with the patterns in order.
+                self.compile_expr(value);
+                self.location = location.clone();
+                let slot = self.new_slot();
+                self.emit(Op::DefineLocal(slot));
+                let mut ends = vec![];
+                for arm in arms {
+                    let mut matched = vec![];
+                    for pattern in &arm.patterns {
+                        match pattern {
+                            Pattern::Literal(literal) => {
+                                self.emit(Op::GetLocal(slot));
+                                self.compile_expr(literal);
+                                self.location = location.clone();
+                                self.emit(Op::Compare(LogicalOperatorType::Equal));
+                            }
+                            Pattern::Range(first, last, is_inclusive) => {
+                                self.emit(Op::GetLocal(slot));
+                                self.compile_expr(first);
+                                self.compile_expr(last);
+                                self.location = location.clone();
+                                self.emit(Op::InRange(*is_inclusive));
+                            }
+                            Pattern::Wildcard => {
+                                matched.push(self.emit(Op::Jump(0)));
+                                continue;
+                            }
+                            Pattern::Variant(pattern) => {
+                                self.emit(Op::GetLocal(slot));
+                                self.location = location.clone();
+                                self.enum_define(&pattern.enum_name);
+                                let bindings = pattern.bindings.as_ref().map(|bindings| bindings.len() as u32);
+                                self.emit_detail(Op::MatchVariant(bindings), &format!("{}::{}", pattern.enum_name, pattern.variant));
+                            }
+                        }
+                        matched.push(self.emit(Op::JumpIfTrue(0)));
+                    }
+                    let next = self.emit(Op::Jump(0));
+                    for at in matched {
+                        self.patch(at);
+                    }
+                    self.enter_block();
+                    // A pattern that binds values is the only pattern of its arm, so the value is of its variant here.
+                    for pattern in &arm.patterns {
+                        if let Pattern::Variant(VariantPattern { bindings: Some(bindings), .. }) = pattern {
+                            for (i, name) in bindings.iter().enumerate() {
+                                if let Some(name) = name {
+                                    self.emit(Op::GetLocal(slot));
+                                    self.emit(Op::Payload(i as u32));
+                                    let binding = self.declare_value(name, VarType::Constant);
+                                    self.emit(Op::DefineLocal(binding));
+                                }
+                            }
+                        }
+                    }
+                    for stmt in &arm.body {
+                        self.compile_stmt(stmt);
+                    }
+                    self.compile_expr(&arm.value);
+                    self.leave_block();
+                    ends.push(self.emit(Op::Jump(0)));
+                    self.patch(next);
+                }
+                self.emit(Op::Null);
+                for at in ends {
+                    self.patch(at);
+                }
+            }
+            AstNode::Comparison(location, e, c, o) => {
+                self.compile_expr(e);
+                self.compile_expr(o);
+                self.location = location.clone();
+                self.emit(Op::Compare(*c));
+            }
+            AstNode::Not(_location, val) => {
+                self.compile_expr(val);
+                self.emit(Op::Not);
+            }
+            AstNode::Negate(location, val) => {
+                self.compile_expr(val);
+                self.location = location.clone();
+                self.emit(Op::Negate);
+            }
+            AstNode::UnaryPlus(location, val) => {
+                self.compile_expr(val);
+                self.location = location.clone();
+                self.emit(Op::UnaryPlus);
+            }
+            AstNode::BitNot(location, val) => {
+                self.compile_expr(val);
+                self.location = location.clone();
+                self.emit(Op::BitNot);
+            }
+            AstNode::List(location, items) => {
+                for item in items {
+                    self.compile_expr(item);
+                }
+                self.location = location.clone();
+                self.emit(Op::List(items.len() as u32));
+            }
+            AstNode::Map(location, entries) => {
+                for (key, val) in entries {
+                    self.compile_expr(key);
+                    self.compile_expr(val);
+                }
+                self.location = location.clone();
+                self.emit(Op::Map(entries.len() as u32));
+            }
+            AstNode::Index(location, target, index) => {
+                self.compile_expr(target);
+                self.compile_expr(index);
+                self.location = location.clone();
+                self.emit(Op::Index);
+            }
+            AstNode::Field(location, target, field) => {
+                self.compile_expr(target);
+                self.location = location.clone();
+                self.emit_detail(Op::GetField, field);
+            }
+            AstNode::Variant(location, enum_name, variant, args) => {
+                for arg in args {
+                    self.compile_expr(arg);
+                }
+                self.location = location.clone();
+                self.enum_define(enum_name);
+                self.emit_detail(Op::Variant(args.len() as u32), &format!("{}::{}", enum_name, variant));
+            }
+            AstNode::StructLiteral(location, name, fields) => {
+                for (field, val) in fields {
+                    self.constant(OranValue::Str(OranString::from(field)));
+                    self.compile_expr(val);
+                }
+                self.location = location.clone();
+                match self.resolve(FunctionOrValueType::Value, name) {
+                    Some((depth, slot, _)) => {
+                        self.get_value(depth, slot, name);
+                        self.emit_detail(Op::Struct(fields.len() as u32), name);
+                    }
+                    None => {
+                        self.fail(util::undefined_struct(&self.location, name));
+                        self.emit(Op::Null);
+                    }
+                }
+            }
+            _ => {
+                self.compile_stmt(node);
+                self.emit(Op::Null);
+            }
+        }
+    }
+}
+
+fn compile_function(deferred: Deferred) -> Rc<FunctionProto> {
+    let level = deferred.block.borrow().level + 1;
+    let block = Block::child(&deferred.block, level);
+    block.borrow_mut().has_receiver = deferred.is_method;
+    let mut compiler = FunctionCompiler::new(deferred.name, block, 0);
+    // Arguments take the first slots of the frame, in order.
+    compiler.arity = deferred.args.len();
+    for (i, arg) in deferred.args.iter().enumerate() {
+        let slot = compiler.new_slot();
+        let var_type = if deferred.is_method && i == 0 { VarType::VariableFirstAssigned } else { VarType::Constant };
+        compiler.block.borrow_mut().values.insert(String::from(arg), (slot, var_type));
+    }
+    for stmt in deferred.body.iter() {
+        compiler.compile_stmt(stmt);
+    }
+    compiler.compile_expr(&deferred.fn_return);
+    compiler.emit(Op::Return);
+    compiler.finish()
+}
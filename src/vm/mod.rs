@@ -0,0 +1,525 @@
+use std::fmt;
+use std::rc::Rc;
+use std::cell::RefCell;
+use indexmap::IndexMap;
+use crate::parser::astnode::{AstNode, Accessor};
+use crate::value::oran_value::{self, OranValue, ModuleGlobals};
+use crate::value::oran_error::OranError;
+use crate::value::var_type::{FunctionOrValueType, VarType};
+use crate::interpreter::{builtin, util, Interrupt};
+use crate::interpreter::module::Modules;
+use bytecode::{Op, FunctionProto, Receiver, SelfChange};
+use compiler::Compiler;
+pub mod bytecode;
+pub mod compiler;
+
+/// The variables of one function call, or of the top level.
+/// `None` is a slot whose `let` has not run yet.
+#[derive(Debug)]
+pub struct Frame {
+    slots: RefCell<Vec<Option<OranValue>>>,
+    parent: Option<Rc<Frame>>,
+    /// Whether this is the frame of a method called on a constant, whose `self` can't be changed.
+    constant_receiver: bool,
+    /// The modules of the engine, which only the frame of the globals has.
+    modules: Option<Rc<Modules>>,
+}
+
+impl Frame {
+    fn outer(self: &Rc<Self>, depth: u32) -> &Rc<Frame> {
+        let mut frame = self;
+        for _ in 0..depth {
+            frame = frame.parent.as_ref().unwrap();
+        }
+        frame
+    }
+
+    /// The modules of the globals that this frame is in.
+    fn modules(&self) -> Rc<Modules> {
+        match self.modules {
+            Some(ref modules) => modules.clone(),
+            None => self.parent.as_ref().unwrap().modules(),
+        }
+    }
+}
+
+/// A compiled function together with the frame it was defined in.
+#[derive(Clone)]
+pub struct VmFunction {
+    pub proto: Rc<FunctionProto>,
+    pub frame: Rc<Frame>,
+}
+
+impl fmt::Debug for VmFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "VmFunction({})", self.proto.name)
+    }
+}
+
+impl PartialEq for VmFunction {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.proto, &other.proto) && Rc::ptr_eq(&self.frame, &other.frame)
+    }
+}
+
+/// Runs programs compiled to bytecode. Globals are kept between runs.
+pub struct Vm {
+    compiler: Compiler,
+    globals: Rc<Frame>,
+}
+
+impl Vm {
+    /// Creates a VM whose imports go to the modules.
+    pub fn new(modules: Rc<Modules>) -> Self {
+        Vm {
+            compiler: Compiler::new(),
+            globals: Rc::new(Frame { slots: RefCell::new(vec![]), parent: None, constant_receiver: false, modules: Some(modules) }),
+        }
+    }
+
+    /// Runs a program and returns the value of the last statement if it is an expression.
+    pub fn run(&mut self, ast: &[AstNode]) -> Result<OranValue, OranError> {
+        let proto = self.compiler.compile_program(ast);
+        self.globals.slots.borrow_mut().resize(proto.slot_count, None);
+        execute(&proto, &self.globals).map_err(|interrupt| match interrupt {
+            Interrupt::Error(e) => e,
+            Interrupt::Throw(val) => util::uncaught_error(&val),
+            _ => unreachable!(),
+        })
+    }
+
+    /// Binds a global name as if the program had declared it.
+    pub fn define_global(&mut self, kind: FunctionOrValueType, name: &str, var_type: VarType, val: OranValue) {
+        let slot = self.compiler.declare_global(kind, name, var_type);
+        let mut slots = self.globals.slots.borrow_mut();
+        if slots.len() <= slot as usize {
+            slots.resize(slot as usize + 1, None);
+        }
+        slots[slot as usize] = Some(val);
+    }
+
+    pub fn get_global(&self, kind: FunctionOrValueType, name: &str) -> Option<OranValue> {
+        let slot = self.compiler.global_slot(kind, name)?;
+        self.globals.slots.borrow().get(slot as usize).cloned().flatten()
+    }
+
+    /// The global variables and functions that are defined, sorted by name.
+    pub fn globals(&self, kind: FunctionOrValueType) -> Vec<(String, OranValue)> {
+        let slots = self.globals.slots.borrow();
+        let mut globals: Vec<(String, OranValue)> = self.compiler.globals(kind).into_iter()
+            .filter_map(|(name, slot)| slots[slot as usize].clone().map(|val| (name, val)))
+            .collect();
+        globals.sort_by(|a, b| a.0.cmp(&b.0));
+        globals
+    }
+}
+
+fn get_slot(proto: &FunctionProto, pc: usize, frame: &Frame, slot: u32) -> Result<OranValue, OranError> {
+    match frame.slots.borrow()[slot as usize] {
+        Some(ref val) => Ok(val.clone()),
+        None => {
+            let info = proto.debug_info(pc);
+            Err(util::undefined_variable(&info.location, &info.detail))
+        }
+    }
+}
+
+fn set_slot(proto: &FunctionProto, pc: usize, frame: &Frame, slot: u32, val: OranValue) -> Result<(), OranError> {
+    let mut slots = frame.slots.borrow_mut();
+    match slots[slot as usize] {
+        Some(ref mut bound) => *bound = val,
+        None => return Err(util::assignment_without_let(&proto.debug_info(pc).location)),
+    }
+    Ok(())
+}
+
+fn range_bound(proto: &FunctionProto, pc: usize, val: OranValue) -> Result<i64, OranError> {
+    util::range_bound(&proto.debug_info(pc).location, &val)
+}
+
+/// Calls a function with arguments that are already evaluated. Errors are located at the instruction at `pc`,
+/// whose detail is the name the function was called with.
+fn call(proto: &FunctionProto, pc: usize, callee: OranValue, mut args: Vec<OranValue>, constant_receiver: bool) -> Result<OranValue, Interrupt> {
+    let func = match callee {
+        OranValue::VmFunction(func) => func,
+        OranValue::Native(native) => return Ok(util::call_native(&proto.debug_info(pc).location, &native, &args)?),
+        // A value that is not in a variable is called without a name, like `f()(1)`.
+        callee => {
+            let info = proto.debug_info(pc);
+            return Err(match info.detail.as_str() {
+                "" => util::not_callable(&info.location, &callee),
+                name => util::not_a_function(&info.location, name),
+            }.into());
+        }
+    };
+    if args.len() < func.proto.arity {
+        return Err(util::missing_argument(&proto.debug_info(pc).location).into());
+    }
+    // The body sees the frame where the function was defined, not the caller's.
+    args.truncate(func.proto.arity);
+    let mut slots: Vec<Option<OranValue>> = args.into_iter().map(Some).collect();
+    slots.resize(func.proto.slot_count, None);
+    let call_frame = Rc::new(Frame {
+        slots: RefCell::new(slots),
+        parent: Some(func.frame.clone()),
+        constant_receiver,
+        modules: None,
+    });
+    execute(&func.proto, &call_frame)
+}
+
+/// A running try block: where its errors go, and the state to go back to.
+struct Handler {
+    catch: usize,
+    frame: Rc<Frame>,
+    stack_len: usize,
+    iters_len: usize,
+}
+
+/// Runs a function body in the given frame and returns what it returned.
+fn execute(proto: &FunctionProto, frame: &Rc<Frame>) -> Result<OranValue, Interrupt> {
+    // Loops whose bodies create functions run each iteration in a child frame.
+    let mut frame = frame.clone();
+    let mut stack: Vec<OranValue> = Vec::new();
+    // Snapshots of what the running `for` loops iterate over.
+    let mut iters: Vec<(util::LoopItems, usize)> = Vec::new();
+    let mut handlers: Vec<Handler> = Vec::new();
+    let mut pc = 0;
+    loop {
+        // Runs until the function returns, or until an error that a try block may catch.
+        let result = (|| -> Result<OranValue, Interrupt> {
+            loop {
+                let op = proto.code[pc];
+                pc += 1;
+                match op {
+                    Op::Constant(i) => stack.push(proto.constants[i as usize].clone()),
+                    Op::Null => stack.push(OranValue::Null),
+                    Op::Pop => { stack.pop(); }
+                    Op::Copy => {
+                        let val = stack.pop().unwrap();
+                        stack.push(oran_value::copy_value(&val));
+                    }
+                    Op::Dup(count) => {
+                        let from = stack.len() - count as usize;
+                        stack.extend_from_within(from..);
+                    }
+                    Op::GetLocal(slot) => {
+                        let val = get_slot(proto, pc - 1, &frame, slot)?;
+                        stack.push(val);
+                    }
+                    Op::GetOuter(depth, slot) => {
+                        let val = get_slot(proto, pc - 1, frame.outer(depth), slot)?;
+                        stack.push(val);
+                    }
+                    Op::DefineLocal(slot) => {
+                        let val = stack.pop().unwrap();
+                        frame.slots.borrow_mut()[slot as usize] = Some(val);
+                    }
+                    Op::SetLocal(slot) => {
+                        let val = stack.pop().unwrap();
+                        set_slot(proto, pc - 1, &frame, slot, val)?;
+                    }
+                    Op::SetOuter(depth, slot) => {
+                        let val = stack.pop().unwrap();
+                        set_slot(proto, pc - 1, frame.outer(depth), slot, val)?;
+                    }
+                    Op::GetFunction(depth, slot) => {
+                        match frame.outer(depth).slots.borrow()[slot as usize] {
+                            Some(ref val) => stack.push(val.clone()),
+                            None => {
+                                let info = proto.debug_info(pc - 1);
+                                return Err(util::undefined_function(&info.location, &info.detail).into());
+                            }
+                        }
+                    }
+                    Op::Function(i) => {
+                        stack.push(OranValue::VmFunction(VmFunction {
+                            proto: proto.functions[i as usize].clone(),
+                            frame: frame.clone(),
+                        }));
+                    }
+                    Op::Call(argc) => {
+                        let args = stack.split_off(stack.len() - argc as usize);
+                        let callee = stack.pop().unwrap();
+                        stack.push(call(proto, pc - 1, callee, args, false)?);
+                    }
+                    Op::Method(argc, receiver_kind) => {
+                        let args = stack.split_off(stack.len() - argc as usize);
+                        let receiver = stack.pop().unwrap();
+                        let info = proto.debug_info(pc - 1);
+                        let module_function = util::module_function(&info.location, &receiver, &info.detail)?;
+                        let constant_receiver = match receiver_kind {
+                            Receiver::Variable => false,
+                            Receiver::Constant => true,
+                            Receiver::Outer(depth) => frame.outer(depth).constant_receiver,
+                        };
+                        let val = match module_function {
+                            // A function of a module is called without the module.
+                            Some(func) => call(proto, pc - 1, func, args, false)?,
+                            None => match util::find_method(&info.location, &receiver, &info.detail)? {
+                                // A method gets the value it is called on as its first argument.
+                                Some(method) => {
+                                    let mut args_with_receiver = Vec::with_capacity(args.len() + 1);
+                                    args_with_receiver.push(receiver);
+                                    args_with_receiver.extend(args);
+                                    call(proto, pc - 1, method, args_with_receiver, constant_receiver)?
+                                }
+                                None if constant_receiver && builtin::changes_first_arg(&info.detail) => {
+                                    return Err(util::constant_element_change(&info.location).into());
+                                }
+                                None => builtin::call_method(&info.location, &info.detail, &receiver, &args)?,
+                            },
+                        };
+                        stack.push(val);
+                    }
+                    Op::CheckReceiver(depth, change) => {
+                        if frame.outer(depth).constant_receiver {
+                            let location = &proto.debug_info(pc - 1).location;
+                            return Err(match change {
+                                SelfChange::Variable => util::constant_assignment(location),
+                                SelfChange::Element => util::constant_element_change(location),
+                                SelfChange::Field => util::constant_element_assignment(location, &Accessor::Field(String::new())),
+                            }.into());
+                        }
+                    }
+                    Op::Builtin(i, argc) => {
+                        let args = stack.split_off(stack.len() - argc as usize);
+                        let location = &proto.debug_info(pc - 1).location;
+                        stack.push(builtin::call(location, builtin::BUILTINS[i as usize], &args)?);
+                    }
+                    Op::Return => return Ok(stack.pop().unwrap()),
+                    Op::Calc(verb) => {
+                        let rhs = stack.pop().unwrap();
+                        let lhs = stack.pop().unwrap();
+                        stack.push(util::calc(&proto.debug_info(pc - 1).location, verb, lhs, rhs)?);
+                    }
+                    Op::Compare(c) => {
+                        let o = stack.pop().unwrap();
+                        let e = stack.pop().unwrap();
+                        stack.push(util::compare(&proto.debug_info(pc - 1).location, &e, c, &o)?);
+                    }
+                    Op::Not => {
+                        let val = stack.pop().unwrap();
+                        stack.push(OranValue::Boolean(!bool::from(val)));
+                    }
+                    Op::Negate => {
+                        let val = stack.pop().unwrap();
+                        stack.push(util::negate(&proto.debug_info(pc - 1).location, val)?);
+                    }
+                    Op::UnaryPlus => {
+                        let val = stack.pop().unwrap();
+                        stack.push(util::unary_plus(&proto.debug_info(pc - 1).location, val)?);
+                    }
+                    Op::BitNot => {
+                        let val = stack.pop().unwrap();
+                        stack.push(util::bit_not(&proto.debug_info(pc - 1).location, val)?);
+                    }
+                    Op::InRange(is_inclusive) => {
+                        let last = stack.pop().unwrap();
+                        let first = stack.pop().unwrap();
+                        let val = stack.pop().unwrap();
+                        let is_in_range = util::in_range(&proto.debug_info(pc - 1).location, &val, &first, &last, is_inclusive)?;
+                        stack.push(OranValue::Boolean(is_in_range));
+                    }
+                    Op::Concat(n) => {
+                        let strs = stack.split_off(stack.len() - n as usize);
+                        stack.push(OranValue::Str(builtin::concat(&strs).into()));
+                    }
+                    Op::List(n) => {
+                        let items = stack.split_off(stack.len() - n as usize);
+                        stack.push(OranValue::List(Rc::new(RefCell::new(items))));
+                    }
+                    Op::Map(n) => {
+                        let entries = stack.split_off(stack.len() - 2 * n as usize);
+                        let location = &proto.debug_info(pc - 1).location;
+                        let mut map = IndexMap::with_capacity(n as usize);
+                        let mut entries = entries.into_iter();
+                        while let (Some(key), Some(val)) = (entries.next(), entries.next()) {
+                            map.insert(util::map_key(location, &key)?, val);
+                        }
+                        stack.push(OranValue::Map(Rc::new(RefCell::new(map))));
+                    }
+                    Op::Index => {
+                        let index = stack.pop().unwrap();
+                        let target = stack.pop().unwrap();
+                        stack.push(util::get_index(&proto.debug_info(pc - 1).location, &target, &index)?);
+                    }
+                    Op::SetIndex => {
+                        let val = stack.pop().unwrap();
+                        let index = stack.pop().unwrap();
+                        let target = stack.pop().unwrap();
+                        util::set_index(&proto.debug_info(pc - 1).location, &target, &index, val)?;
+                    }
+                    Op::Struct(n) => {
+                        let define = stack.pop().unwrap();
+                        let entries = stack.split_off(stack.len() - 2 * n as usize);
+                        let mut fields = Vec::with_capacity(n as usize);
+                        let mut entries = entries.into_iter();
+                        while let (Some(field), Some(val)) = (entries.next(), entries.next()) {
+                            fields.push((String::from(field), val));
+                        }
+                        let info = proto.debug_info(pc - 1);
+                        stack.push(util::make_struct(&info.location, &info.detail, Some(define), fields)?);
+                    }
+                    Op::GetField => {
+                        let target = stack.pop().unwrap();
+                        let info = proto.debug_info(pc - 1);
+                        stack.push(util::get_field(&info.location, &target, &info.detail)?);
+                    }
+                    Op::SetField => {
+                        let val = stack.pop().unwrap();
+                        let target = stack.pop().unwrap();
+                        let info = proto.debug_info(pc - 1);
+                        util::set_field(&info.location, &target, &info.detail, val)?;
+                    }
+                    Op::Variant(n) => {
+                        let define = stack.pop().unwrap();
+                        let values = stack.split_off(stack.len() - n as usize);
+                        let info = proto.debug_info(pc - 1);
+                        let (enum_name, variant) = info.detail.split_once("::").unwrap();
+                        let define = util::enum_define(&info.location, enum_name, Some(define))?;
+                        stack.push(util::make_variant(&info.location, define, variant, values)?);
+                    }
+                    Op::MatchVariant(bindings) => {
+                        let define = stack.pop().unwrap();
+                        let val = stack.pop().unwrap();
+                        let info = proto.debug_info(pc - 1);
+                        let (enum_name, variant) = info.detail.split_once("::").unwrap();
+                        let define = util::enum_define(&info.location, enum_name, Some(define))?;
+                        let values = util::match_variant(&info.location, &val, &define, variant, bindings.map(|n| n as usize))?;
+                        stack.push(OranValue::Boolean(values.is_some()));
+                    }
+                    Op::Payload(i) => {
+                        let val = match stack.pop().unwrap() {
+                            OranValue::Enum(val) => val.values[i as usize].clone(),
+                            _ => unreachable!(),
+                        };
+                        stack.push(val);
+                    }
+                    Op::Import => {
+                        let info = proto.debug_info(pc - 1);
+                        // A module runs on its own VM, and its globals are its members.
+                        let modules = frame.modules();
+                        let module = modules.import(&info.location, &info.detail, |ast| {
+                            let mut vm = Vm::new(modules.clone());
+                            for native in modules.natives() {
+                                let name = String::from(&native.name);
+                                vm.define_global(FunctionOrValueType::Function, &name, VarType::Constant, OranValue::Native(native));
+                            }
+                            vm.run(ast)?;
+                            Ok(ModuleGlobals::Vm(Box::new(vm)))
+                        })?;
+                        stack.push(OranValue::Module(module));
+                    }
+                    Op::DefineMethod => {
+                        let method = stack.pop().unwrap();
+                        let define = stack.pop().unwrap();
+                        let method_name = match method {
+                            OranValue::VmFunction(ref func) => func.proto.name.clone(),
+                            _ => unreachable!(),
+                        };
+                        let info = proto.debug_info(pc - 1);
+                        util::define_method(&info.location, &info.detail, Some(define), &method_name, method)?;
+                    }
+                    Op::Jump(target) => pc = target as usize,
+                    Op::JumpIfFalse(target) => {
+                        if !bool::from(stack.pop().unwrap()) {
+                            pc = target as usize;
+                        }
+                    }
+                    Op::JumpIfTrue(target) => {
+                        if bool::from(stack.pop().unwrap()) {
+                            pc = target as usize;
+                        }
+                    }
+                    Op::RangeInit(counter, is_inclusive) => {
+                        let last = range_bound(proto, pc - 1, stack.pop().unwrap())?;
+                        let first = range_bound(proto, pc - 1, stack.pop().unwrap())?;
+                        // The last number is kept, not the end, so that a range can end at i64::MAX.
+                        let last = if is_inclusive { Some(last) } else { last.checked_sub(1) };
+                        let mut slots = frame.slots.borrow_mut();
+                        slots[counter as usize] = Some(match last {
+                            Some(last) if first <= last => OranValue::Int(first),
+                            _ => OranValue::Null,
+                        });
+                        slots[counter as usize + 1] = Some(OranValue::Int(last.unwrap_or(i64::MIN)));
+                    }
+                    Op::RangeNext(counter, var, exit) => {
+                        let mut slots = frame.slots.borrow_mut();
+                        match (&slots[counter as usize], &slots[counter as usize + 1]) {
+                            (Some(OranValue::Int(num)), Some(OranValue::Int(last))) => {
+                                let (num, last) = (*num, *last);
+                                slots[var as usize] = Some(OranValue::Int(num));
+                                // The counter is null after the last number.
+                                slots[counter as usize] = Some(if num < last { OranValue::Int(num + 1) } else { OranValue::Null });
+                            }
+                            _ => pc = exit as usize,
+                        }
+                    }
+                    Op::IterInit(with_second, mutable) => {
+                        let iterable = stack.pop().unwrap();
+                        let items = util::loop_items(&proto.debug_info(pc - 1).location, &iterable, with_second, mutable)?;
+                        iters.push((items, 0));
+                    }
+                    Op::IterNext(first, exit) => {
+                        let (items, position) = iters.last_mut().unwrap();
+                        match items.get(*position) {
+                            Some((first_val, second_val)) => {
+                                let mut slots = frame.slots.borrow_mut();
+                                slots[first as usize] = Some(first_val.clone());
+                                if let Some(second_val) = second_val {
+                                    slots[first as usize + 1] = Some(second_val.clone());
+                                }
+                                *position += 1;
+                            }
+                            None => pc = exit as usize,
+                        }
+                    }
+                    Op::IterPop => { iters.pop(); }
+                    Op::EnterFrame(slot_count) => {
+                        frame = Rc::new(Frame {
+                            slots: RefCell::new(vec![None; slot_count as usize]),
+                            parent: Some(frame.clone()),
+                            constant_receiver: false,
+                            modules: None,
+                        });
+                    }
+                    Op::LeaveFrame => {
+                        let parent = frame.parent.clone().unwrap();
+                        frame = parent;
+                    }
+                    Op::Try(catch) => {
+                        handlers.push(Handler { catch: catch as usize, frame: frame.clone(), stack_len: stack.len(), iters_len: iters.len() });
+                    }
+                    Op::EndTry => { handlers.pop(); }
+                    Op::Throw => {
+                        let val = util::unwrap_variable(stack.pop().unwrap());
+                        return Err(Interrupt::Throw(util::thrown_error(&proto.debug_info(pc - 1).location, val)));
+                    }
+                    Op::Fail(kind) => {
+                        let info = proto.debug_info(pc - 1);
+                        return Err(OranError::located(kind, &info.location, &info.detail).into());
+                    }
+                }
+            }
+        })();
+        let interrupt = match result {
+            Ok(val) => return Ok(val),
+            Err(interrupt) => interrupt,
+        };
+        let handler = match handlers.pop() {
+            Some(handler) => handler,
+            None => return Err(interrupt),
+        };
+        let error = match interrupt {
+            Interrupt::Error(e) => util::caught_error(&e),
+            Interrupt::Throw(val) => val,
+            _ => unreachable!(),
+        };
+        frame = handler.frame;
+        stack.truncate(handler.stack_len);
+        iters.truncate(handler.iters_len);
+        stack.push(error);
+        pc = handler.catch;
+    }
+}
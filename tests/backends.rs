@@ -0,0 +1,27 @@
+use std::process::Command;
+
+/// Runs an example on both backends and checks that they print the same thing.
+fn assert_same_output(example: &str) {
+    let outputs: Vec<String> = ["tree", "vm"].iter().map(|backend| {
+        let output = Command::new(env!("CARGO_BIN_EXE_oran"))
+            .arg("-b")
+            .arg(backend)
+            .arg("-f")
+            .arg(format!("{}/examples/{}", env!("CARGO_MANIFEST_DIR"), example))
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{} failed on {}", example, backend);
+        String::from_utf8(output.stdout).unwrap()
+    }).collect();
+    assert_eq!(outputs[0], outputs[1]);
+}
+
+#[test]
+fn example() {
+    assert_same_output("example.orn");
+}
+
+#[test]
+fn hello() {
+    assert_same_output("hello.orn");
+}
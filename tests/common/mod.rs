@@ -0,0 +1,60 @@
+//! Helpers shared by the integration tests, which run scripts with the `oran` binary.
+// Each test file uses only some of them.
+#![allow(dead_code)]
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static TEMP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// A path in the temporary directory that no other test uses.
+pub fn temp_path(name: &str) -> PathBuf {
+    let mut path: PathBuf = std::env::temp_dir();
+    let count = TEMP_COUNT.fetch_add(1, Ordering::SeqCst);
+    path.push(format!("oran_{}_{}_{}", name, std::process::id(), count));
+    path
+}
+
+/// Runs `oran` with the arguments on both backends.
+/// The tree-walking interpreter and the VM must print the same and exit with the same code.
+pub fn run_args<S: AsRef<OsStr>>(args: &[S]) -> Output {
+    let outputs: Vec<Output> = ["tree", "vm"].iter().map(|backend| {
+        Command::new(env!("CARGO_BIN_EXE_oran"))
+            .arg("-b")
+            .arg(backend)
+            .args(args)
+            .output()
+            .unwrap()
+    }).collect();
+    assert_eq!(String::from_utf8_lossy(&outputs[0].stdout), String::from_utf8_lossy(&outputs[1].stdout));
+    assert_eq!(String::from_utf8_lossy(&outputs[0].stderr), String::from_utf8_lossy(&outputs[1].stderr));
+    assert_eq!(outputs[0].status.code(), outputs[1].status.code());
+    outputs.into_iter().next().unwrap()
+}
+
+/// Writes the script to a temporary file and runs it on both backends.
+pub fn run(name: &str, script: &str) -> Output {
+    let mut path = temp_path(name);
+    path.set_extension("orn");
+    fs::write(&path, script).unwrap();
+    let output = run_args(&[OsStr::new("-f"), path.as_os_str()]);
+    fs::remove_file(&path).unwrap();
+    output
+}
+
+/// What a script that must succeed prints.
+pub fn stdout_of(name: &str, script: &str) -> String {
+    let output = run(name, script);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// The error that a script that must fail reports.
+pub fn error_of(name: &str, script: &str) -> String {
+    let output = run(name, script);
+    assert!(!output.status.success());
+    String::from_utf8(output.stderr).unwrap()
+}
@@ -0,0 +1,179 @@
+use std::cell::RefCell;
+use std::fs;
+use std::rc::Rc;
+use oran::{Engine, Backend, OranValue, OranError, ErrorKind};
+
+/// Runs the check with an engine of each backend.
+fn with_engines<F: Fn(Engine)>(check: F) {
+    check(Engine::with_backend(Backend::Tree));
+    check(Engine::with_backend(Backend::Vm));
+}
+
+#[test]
+fn eval_returns_trailing_expression() {
+    with_engines(|mut engine| {
+        assert_eq!(engine.eval("1 + 2").unwrap(), OranValue::Float(3.0));
+        assert_eq!(engine.eval("1 + 2;").unwrap(), OranValue::Null);
+        assert_eq!(engine.eval("let a = 'x';").unwrap(), OranValue::Null);
+        assert_eq!(engine.eval("a << 'y'").unwrap(), OranValue::Str("xy".into()));
+    });
+}
+
+#[test]
+fn globals_persist_and_can_be_read_and_written() {
+    with_engines(|mut engine| {
+        engine.set_global("count", OranValue::Float(1.0));
+        engine.eval("count = count + 1; let doubled = count * 2;").unwrap();
+        assert_eq!(engine.get_global("count"), Some(OranValue::Float(2.0)));
+        assert_eq!(engine.get_global("doubled"), Some(OranValue::Float(4.0)));
+        assert_eq!(engine.get_global("missing"), None);
+        let names: Vec<String> = engine.globals().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["count", "doubled"]);
+    });
+}
+
+#[test]
+fn registered_function_is_callable() {
+    with_engines(|mut engine| {
+        let calls = Rc::new(RefCell::new(vec![]));
+        let log = calls.clone();
+        engine.register_fn("record", move |args| {
+            log.borrow_mut().push(args.len());
+            Ok(OranValue::Float(args.len() as f64))
+        }).unwrap();
+        engine.eval("let xs = [1, 2]; fn twice() { return record(xs, 1) * 2; }").unwrap();
+        assert_eq!(engine.eval("0 + twice() + record()").unwrap(), OranValue::Float(4.0));
+        assert_eq!(*calls.borrow(), vec![2, 0]);
+        assert!(engine.functions().contains(&"record".to_owned()));
+    });
+}
+
+#[test]
+fn registered_function_error_is_located_at_the_call() {
+    with_engines(|mut engine| {
+        engine.register_fn("fail", |_args| Err(OranError::new(ErrorKind::Type, "failed"))).unwrap();
+        let e = engine.eval("let a = 1;\nfail();").unwrap_err();
+        assert_eq!(e.kind, ErrorKind::Type);
+        assert_eq!(e.message, "failed");
+        assert_eq!(e.location, Some(("<eval>".to_owned(), 2, 1)));
+    });
+}
+
+#[test]
+fn builtin_functions_cannot_be_registered() {
+    with_engines(|mut engine| {
+        let e = engine.register_fn("len", |_args| Ok(OranValue::Null)).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::Syntax);
+        assert_eq!(engine.eval("len([1, 2])").unwrap(), OranValue::Int(2));
+    });
+}
+
+#[test]
+fn reset_keeps_registered_functions() {
+    with_engines(|mut engine| {
+        engine.register_fn("one", |_args| Ok(OranValue::Float(1.0))).unwrap();
+        engine.eval("let a = 1;").unwrap();
+        engine.reset();
+        assert_eq!(engine.get_global("a"), None);
+        assert_eq!(engine.eval("one()").unwrap(), OranValue::Float(1.0));
+    });
+}
+
+#[test]
+fn eval_file_locates_errors_in_the_file() {
+    let mut path = std::env::temp_dir();
+    path.push(format!("oran_engine_{}.orn", std::process::id()));
+    fs::write(&path, "let a = 1;\nprintln(b);\n").unwrap();
+    with_engines(|mut engine| {
+        let e = engine.eval_file(&path).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::UndefinedVariable);
+        assert_eq!(e.location, Some((path.to_string_lossy().into_owned(), 2, 9)));
+        assert_eq!(engine.get_global("a"), Some(OranValue::Float(1.0)));
+    });
+    fs::remove_file(&path).unwrap();
+    let e = Engine::new().eval_file(&path).unwrap_err();
+    assert_eq!(e.kind, ErrorKind::Io);
+}
+
+#[test]
+fn modules_are_found_in_the_search_path_of_the_engine() {
+    let mut dir = std::env::temp_dir();
+    dir.push(format!("oran_engine_modules_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("greet.orn"), "fn hello(name) { 'hello ' ++ name }\n").unwrap();
+    with_engines(|mut engine| {
+        let e = engine.eval("import greet;").unwrap_err();
+        assert_eq!(e.kind, ErrorKind::Import);
+        engine.add_search_path(&dir);
+        assert_eq!(engine.eval("import greet;\ngreet.hello('oran')").unwrap(), OranValue::Str("hello oran".into()));
+        // Other engines have their own search paths.
+        let mut other = Engine::with_backend(engine.backend());
+        assert_eq!(other.eval("import greet;").unwrap_err().kind, ErrorKind::Import);
+    });
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn modules_call_registered_functions_and_run_again_after_reset() {
+    let mut dir = std::env::temp_dir();
+    dir.push(format!("oran_engine_natives_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("twice.orn"), "record();\nfn of(x) { host_double(x) }\n").unwrap();
+    with_engines(|mut engine| {
+        let runs = Rc::new(RefCell::new(0));
+        let counter = runs.clone();
+        engine.register_fn("record", move |_args| {
+            *counter.borrow_mut() += 1;
+            Ok(OranValue::Null)
+        }).unwrap();
+        engine.register_fn("host_double", |args| match args.first() {
+            Some(OranValue::Int(x)) => Ok(OranValue::Int(x * 2)),
+            _ => Ok(OranValue::Null),
+        }).unwrap();
+        engine.add_search_path(&dir);
+        assert_eq!(engine.eval("import twice;\ntwice.of(21)").unwrap(), OranValue::Int(42));
+        engine.eval("import twice;").unwrap();
+        assert_eq!(*runs.borrow(), 1);
+        // The search path and the registered functions are kept, but the module runs again.
+        engine.reset();
+        assert_eq!(engine.eval("import twice;\ntwice.of(2)").unwrap(), OranValue::Int(4));
+        assert_eq!(*runs.borrow(), 2);
+    });
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn uncaught_throw_is_an_error_of_thrown_kind() {
+    with_engines(|mut engine| {
+        let e = engine.eval("let a = 1;\nthrow 'no ' ++ a;").unwrap_err();
+        assert_eq!(e.kind, ErrorKind::Thrown);
+        assert_eq!(e.message, "no 1");
+        assert_eq!(e.location, Some(("<eval>".to_owned(), 2, 1)));
+        let caught = engine.eval("let mut kind = '';\ntry { a = 2; } catch e { kind = e.kind; }\nkind").unwrap();
+        assert_eq!(caught, OranValue::Str("ConstantAssignment".into()));
+    });
+}
+
+#[test]
+fn syntax_error_and_incomplete_input() {
+    with_engines(|mut engine| {
+        assert_eq!(engine.eval("let = ;").unwrap_err().kind, ErrorKind::Syntax);
+        assert!(Engine::is_incomplete("fn f(x) {\n    return x;\n"));
+        assert!(!Engine::is_incomplete("fn f(x) {\n    return x;\n}\n"));
+    });
+}
+
+#[test]
+fn match_is_a_trailing_expression_and_warnings_are_kept() {
+    with_engines(|mut engine| {
+        assert_eq!(engine.eval("match 2 { 1 => 'a', _ => 'b' }").unwrap(), OranValue::Str("b".into()));
+        assert!(engine.warnings().is_empty());
+        assert_eq!(engine.eval("match 1 > 2 { false => 'no' }").unwrap(), OranValue::Str("no".into()));
+        let warnings = engine.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].location, ("<eval>".to_owned(), 1, 1));
+        assert!(warnings[0].message.contains("does not cover true"));
+        assert!(engine.eval("match true { true => 1 }; 1 / 0;").is_err());
+        assert_eq!(engine.warnings().len(), 1);
+    });
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use common::{run, stdout_of};
+
+#[test]
+fn variants_are_made_and_destructured_by_match() {
+    let out = stdout_of("match", r#"
+enum Shape { Circle(r), Rect(w, h), Empty }
+fn area(s) {
+    match s {
+        Shape::Circle(r) => 3 * r * r,
+        Shape::Rect | Shape::Empty => {
+            let w = match s { Shape::Rect(w, h) => w * h, _ => 0 };
+            w
+        }
+    }
+}
+let shapes = [Shape::Circle(2), Shape::Rect(3, 4), Shape::Empty];
+for s in shapes {
+    println(s, ' ', area(s));
+}
+println(Shape, ' ', Shape::Rect(1, 2) == Shape::Rect(1, 2), ' ', Shape::Rect(1, 2) == Shape::Rect(2, 1));
+"#);
+    assert_eq!(out, "Circle(2) 12\nRect(3, 4) 12\nEmpty 0\n<enum Shape> true false\n");
+}
+
+#[test]
+fn if_let_runs_the_block_when_the_value_matches() {
+    let out = stdout_of("if_let", r#"
+let xs = [10, 20];
+fn describe(i) {
+    if let Some(x) = get(xs, i) {
+        return "found " ++ x;
+    } else if i < 0 {
+        return "negative";
+    } else if let Ok(n) = parse_number("" ++ i) {
+        let x = n * 2;
+        return "missing " ++ x;
+    }
+    "unreachable"
+}
+println(describe(1), ', ', describe(-1), ', ', describe(5));
+let x = 'outer';
+if let Some(x) = Some('inner') {
+    println(x);
+}
+println(x);
+"#);
+    assert_eq!(out, "found 20, negative, missing 10\ninner\nouter\n");
+}
+
+#[test]
+fn builtins_return_options_and_results() {
+    let out = stdout_of("prelude", r#"
+let m = { "a": 1 };
+println(get([1, 2], 0), ' ', get([1, 2], 2), ' ', get(m, "a"), ' ', get(m, "b"));
+println(parse_number(" 42 "), ' ', parse_number("2.5"), ' ', parse_number("abc"));
+println(get(m, "b").is_none(), ' ', get(m, "a").is_some(), ' ', parse_number("x").is_err(), ' ', Ok(1).is_ok());
+println(get(m, "b").unwrap_or(0), ' ', parse_number("7").unwrap_or(0), ' ', Err("e").unwrap_or(-1));
+match parse_number("abc") {
+    Ok(n) => println(n),
+    Err(e) => println("error: " ++ e),
+}
+"#);
+    assert_eq!(out, "Some(1) None Some(1) None\nOk(42) Ok(2.5) Err(\"Not a number: abc\")\ntrue true true true\n0 7 -1\nerror: Not a number: abc\n");
+}
+
+#[test]
+fn variants_of_other_enums_and_nested_values() {
+    let out = stdout_of("edges", r#"
+enum A { X(a, b), Y }
+enum B { X(a, b), Y }
+fn which(v) {
+    match v {
+        A::X(_, b) => "A " ++ b,
+        B::X(a, _) => "B " ++ a,
+        A::Y => "A::Y",
+        _ => "other",
+    }
+}
+println(which(A::X(1, 2)), ', ', which(B::X(3, 4)), ', ', which(A::Y), ', ', which(B::Y));
+let v = Some(None);
+println(v, ' ', v.is_some(), ' ', v.unwrap_or(1), ' ', A::X(Ok([1]), B::Y));
+"#);
+    assert_eq!(out, "A 2, B 3, A::Y, other\nSome(None) true None X(Ok([1]), Y)\n");
+}
+
+#[test]
+fn variants_take_as_many_values_as_declared() {
+    for (script, expected) in [
+        ("enum A { X(a) }\nlet a = A::X();\n", "Line number: 2, column number:9: The number of values of A::X is 1, but 0 are given."),
+        ("enum A { X(a) }\nlet a = A::X(1, 2);\n", "Line number: 2, column number:9: The number of values of A::X is 1, but 2 are given."),
+        ("enum A { X(a) }\nprintln(A::X);\n", "Line number: 2, column number:9: The number of values of A::X is 1, but 0 are given."),
+        ("match Some(1) {\n    Some(a, b) => a,\n    _ => 0,\n}\n", "Line number: 1, column number:1: The number of values of Option::Some is 1, but the pattern has 2."),
+        ("enum A { Y }\nmatch A::Y { A::Y(v) => 1, _ => 0 }\n", "Line number: 2, column number:1: The number of values of A::Y is 0, but the pattern has 1."),
+    ].iter() {
+        let output = run("values", script);
+        assert_eq!(output.status.code(), Some(1), "{}", script);
+        assert!(String::from_utf8(output.stderr).unwrap().contains(expected), "{}", script);
+    }
+}
+
+#[test]
+fn only_enums_have_variants() {
+    let output = run("no_variant", "enum A { X(a) }\nlet a = A::Y(1);\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 2, column number:9: A has no variant \"Y\"."));
+    let output = run("no_enum", "let a = Shape::Circle(1);\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 1, column number:9: The enum \"Shape\" is not defined."));
+    let output = run("not_an_enum", "struct P { x }\nlet a = P::X;\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 2, column number:9: \"P\" is not an enum."));
+    let output = run("not_an_option", "println(1.is_some());\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 1, column number:10: \"is_some\" is a method of options: 1"));
+}
+
+#[test]
+fn enum_declarations_and_patterns_are_checked_before_running() {
+    let output = run("twice", "println('not reached');\nenum A { X, X }\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stdout).unwrap().is_empty());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("The variant \"X\" is declared twice."));
+    let output = run("joined", "match None { Some(a) | None => 1 }\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("A pattern that binds values cannot be joined with |."));
+}
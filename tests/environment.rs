@@ -0,0 +1,271 @@
+mod common;
+
+use common::{run, stdout_of, error_of};
+
+#[test]
+fn nested_function_sees_enclosing_locals_and_arguments() {
+    let out = stdout_of("closure", r#"
+fn outer(a) {
+    let b = 10;
+    fn inner(c) {
+        return a + b + c;
+    }
+    return inner(100);
+}
+println(outer(1));
+"#);
+    assert_eq!(out, "111\n");
+}
+
+#[test]
+fn global_is_visible_in_function() {
+    let out = stdout_of("global", r#"
+let greeting = 'hello';
+fn greet() {
+    println(greeting);
+}
+greet();
+"#);
+    assert_eq!(out, "hello\n");
+}
+
+#[test]
+fn recursion_keeps_locals_per_call() {
+    let out = stdout_of("recursion", r#"
+fn fib(n) {
+    if n < 2 {
+        return n;
+    }
+    return fib(n - 1) + fib(n - 2);
+}
+fn countdown(n) {
+    let here = n;
+    if n > 0 {
+        countdown(n - 1);
+    }
+    println(here);
+}
+println(fib(15));
+countdown(2);
+"#);
+    assert_eq!(out, "610\n0\n1\n2\n");
+}
+
+#[test]
+fn caller_locals_are_invisible_to_callee() {
+    let out = error_of("dynamic", r#"
+fn callee() {
+    println(secret);
+}
+fn caller() {
+    let secret = 1;
+    callee();
+}
+caller();
+"#);
+    assert!(out.contains("The variable \"secret\" is not defined."));
+}
+
+#[test]
+fn same_names_in_caller_and_callee_do_not_collide() {
+    let out = stdout_of("collision", r#"
+fn callee(x) {
+    let mut tmp = x * 2;
+    tmp = tmp + 1;
+    return tmp;
+}
+fn caller() {
+    let tmp = 'caller';
+    let x = callee(3);
+    println(tmp << ' ' << x);
+}
+caller();
+"#);
+    assert_eq!(out, "caller 7\n");
+}
+
+#[test]
+fn block_shadows_and_reassigns_outer_variable() {
+    let out = stdout_of("shadow", r#"
+let a = 'outer';
+let mut b = 1;
+if true {
+    let a = 'inner';
+    b = 2;
+    println(a);
+}
+println(a);
+println(b);
+"#);
+    assert_eq!(out, "inner\nouter\n2\n");
+}
+
+#[test]
+fn loop_variable_does_not_leak() {
+    let out = error_of("loop_var", r#"
+for i in 0..3 {
+    let inside = i;
+}
+println(i);
+"#);
+    assert!(out.contains("The variable \"i\" is not defined."));
+}
+
+#[test]
+fn nested_function_is_not_visible_outside() {
+    let output = run("nested_fn", r#"
+fn outer() {
+    fn inner() {
+        println('inner');
+    }
+    inner();
+}
+outer();
+inner();
+"#);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "inner\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Function \"inner\" is not defined."));
+}
+
+#[test]
+fn constant_cannot_be_assigned_from_nested_function() {
+    let out = error_of("constant", r#"
+let a = 1;
+fn change() {
+    a = 2;
+}
+change();
+"#);
+    assert!(out.contains("You can't assign value twice to a constant variable."));
+}
+
+#[test]
+fn functions_are_values() {
+    let out = stdout_of("first_class", r#"
+fn map(xs, f) {
+    let mut out = [];
+    for x in xs {
+        push(out, f(x));
+    }
+    return out;
+}
+fn filter(xs, keep) {
+    let mut out = [];
+    for x in xs {
+        if keep(x) == true {
+            push(out, x);
+        }
+    }
+    return out;
+}
+fn reduce(xs, f, init) {
+    let mut acc = init;
+    for x in xs {
+        acc = f(acc, x);
+    }
+    return acc;
+}
+fn is_odd(x) {
+    if x % 2 == 1 {
+        return true;
+    }
+    return false;
+}
+let double = fn(x) { x * 2 };
+let xs = [1, 2, 3, 4];
+println(map(xs, double));
+println(filter(xs, is_odd));
+println(reduce(xs, fn(acc, x) { acc + x }, 0));
+println(double);
+println(is_odd);
+"#);
+    assert_eq!(out, "[2, 4, 6, 8]\n[1, 3]\n10\n<function>\n<function is_odd>\n");
+}
+
+#[test]
+fn returned_function_keeps_its_environment() {
+    let out = stdout_of("returned", r#"
+fn make_adder(n) {
+    return fn(x) { x + n };
+}
+fn make_counter() {
+    let mut count = 0;
+    return fn() {
+        count = count + 1;
+        return count;
+    };
+}
+let add3 = make_adder(3);
+let first = make_counter();
+let second = make_counter();
+first();
+first();
+println(add3(4) << ' ' << first() << ' ' << second());
+"#);
+    assert_eq!(out, "7 3 1\n");
+}
+
+#[test]
+fn functions_made_in_loop_capture_their_own_iteration() {
+    let out = stdout_of("loop_closures", r#"
+let mut fs = [];
+for i in 0..3 {
+    let tens = i * 10;
+    push(fs, fn() { tens + i });
+    if i == 1 {
+        continue;
+    }
+}
+for f in fs {
+    print(f() << ' ');
+}
+"#);
+    assert_eq!(out, "0 11 22 ");
+}
+
+#[test]
+fn functions_from_expressions_can_be_called() {
+    let out = stdout_of("call_values", r#"
+fn make_adder(n) {
+    fn(x) { x + n }
+}
+let fs = [fn(x) { x * 2 }, make_adder(10)];
+let handlers = {'greet': fn(name) { 'hi ' ++ name }};
+println(make_adder(1)(2), ' ', fs[0](3), ' ', fs[1](fs[0](4)), ' ', handlers['greet']('oran'));
+fn curry(a) { fn(b) { fn(c) { a + b + c } } }
+println(curry(1)(2)(3), ' ', make_adder(5)(1) * 2, ' ', [make_adder(-3)][0](1).abs());
+"#);
+    assert_eq!(out, "3 6 18 hi oran\n6 12 2\n");
+}
+
+#[test]
+fn variables_take_the_place_of_builtin_functions() {
+    let out = stdout_of("shadow_builtins", r#"
+let len = fn(x) { 'mine' };
+println(len([1, 2]));
+fn count(push) {
+    push(1)
+}
+println(count(fn(x) { x + 1 }), ' ', count(fn(x) { x * 10 }));
+if true {
+    let mut pop = [1];
+    println(pop);
+}
+println(pop([3, 4]));
+"#);
+    assert_eq!(out, "mine\n2 10\n[1]\n4\n");
+}
+
+#[test]
+fn calling_a_non_function_is_an_error() {
+    let out = error_of("not_callable", r#"
+let x = 5;
+x(1);
+"#);
+    assert!(out.contains("Line number: 3, column number:1: The variable \"x\" is not a function."));
+    let out = error_of("value_not_callable", "let xs = [1];\nxs[0](2);\n");
+    assert!(out.contains("Line number: 2, column number:6: This value is not a function: 1"));
+    // Arguments are separated with commas, so `f()(3)` is not read as `f(), (3)`.
+    let output = run("no_comma", "fn f() { 1 }\nprintln(f() (3));\n");
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn syntax_error_exits_with_2() {
+    let output = run("syntax", "let a = 1\nprintln(a;\n");
+    assert_eq!(output.status.code(), Some(2));
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains(" --> 2:1"));
+}
+
+#[test]
+fn runtime_error_is_located_and_exits_with_1() {
+    let output = run("undefined", "println('ok');\nlet b = 1 + nope;\nprintln('not reached');\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains("Line number: 2, column number:13: The variable \"nope\" is not defined."));
+}
+
+#[test]
+fn calculation_with_non_number_does_not_panic() {
+    let output = run("not_a_number", "let s = 'abc';\nprintln(s * 2);\n");
+    assert_eq!(output.status.code(), Some(1));
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains("Line number: 2, column number:11: This \"abc\" is not a number."));
+}
+
+#[test]
+fn error_inside_function_stops_the_program() {
+    let output = run("in_function", r#"
+fn first(xs) {
+    return xs[5];
+}
+first([1, 2]);
+println('not reached');
+"#);
+    assert_eq!(output.status.code(), Some(1));
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains("The index is out of range"));
+    assert!(!out.contains("not reached"));
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use common::{run, stdout_of};
+
+#[test]
+fn thrown_values_are_caught_with_their_location() {
+    let out = stdout_of("throw", r#"
+fn check(x) {
+    if x > 1 {
+        throw 'too big: ' ++ x;
+    }
+    x
+}
+try {
+    println(check(1));
+    println(check(5));
+    println('not reached');
+} catch e {
+    println(e.kind, ' ', e.message, ' ', e.line, ':', e.column);
+}
+try {
+    throw Some(3);
+} catch e {
+    if let Some(v) = e.value {
+        println('value ', v);
+    }
+}
+"#);
+    assert_eq!(out, "1\nThrown too big: 5 4:9\nvalue 3\n");
+}
+
+#[test]
+fn runtime_errors_are_caught() {
+    let out = stdout_of("runtime", r#"
+fn pair(a, b) { [a, b] }
+let limit = 1;
+let attempts = [
+    fn() { println(missing); },
+    fn() { pair(1); },
+    fn() { 'a' < 1; },
+    fn() { limit = 2; },
+];
+for attempt in attempts {
+    try {
+        attempt();
+    } catch e {
+        println(e.kind, ' at ', e.line, ': ', e.message);
+    }
+}
+"#);
+    assert_eq!(out, concat!(
+        "UndefinedVariable at 5: The variable \"missing\" is not defined.\n",
+        "MissingArgument at 6: Argument is necessary but not supplied.\n",
+        "Type at 7: One of these are not number: a, 1\n",
+        "ConstantAssignment at 8: You can't assign value twice to a constant variable.\n",
+    ));
+}
+
+#[test]
+fn finally_runs_however_the_blocks_end() {
+    let out = stdout_of("finally", r#"
+fn early() {
+    try {
+        return 'returned';
+    } finally {
+        println('finally after return');
+    }
+}
+println(early());
+for i in 0..4 {
+    try {
+        if i == 1 { continue; }
+        if i == 2 { break; }
+        println('body ', i);
+    } finally {
+        println('finally ', i);
+    }
+}
+try {
+    try {
+        throw 'inner';
+    } catch e {
+        throw e.message ++ ' again';
+    } finally {
+        println('finally before the outer catch');
+    }
+} catch e {
+    println('caught ', e.message);
+}
+fn overridden() {
+    try { return 1; } finally { return 2; }
+}
+println(overridden());
+"#);
+    assert_eq!(out, concat!(
+        "finally after return\nreturned\n",
+        "body 0\nfinally 0\nfinally 1\nfinally 2\n",
+        "finally before the outer catch\ncaught inner again\n",
+        "2\n",
+    ));
+}
+
+#[test]
+fn errors_leave_calls_and_loops_and_the_catch_variable_is_local() {
+    let out = stdout_of("unwind", r#"
+fn deep(n) {
+    if n == 0 { throw 'bottom'; }
+    deep(n - 1)
+}
+let mut reached = [];
+try {
+    for i in 0..3 {
+        push(reached, i);
+        if i == 1 { deep(5); }
+    }
+} catch e {
+    println(reached, ' ', e.message, ' ', e.line);
+}
+let e = 'outer';
+try { throw 1; } catch e { println(e.value + 1); }
+println(e);
+let mut n = 0;
+while n < 3 {
+    n += 1;
+    try {
+        if n == 2 { throw 'skip'; }
+        println('n ', n);
+    } catch err {
+        println('caught ', err.message);
+        continue;
+    }
+}
+"#);
+    assert_eq!(out, "[0, 1] bottom 3\n2\nouter\nn 1\ncaught skip\nn 3\n");
+}
+
+#[test]
+fn finally_replaces_the_error_it_ends_with() {
+    let out = stdout_of("replaced", r#"
+fn swallow() {
+    try { throw 'a'; } finally { return 'finally wins'; }
+}
+println(swallow());
+try {
+    try { throw 'first'; } finally { throw 'second'; }
+} catch e {
+    println(e.message);
+}
+"#);
+    assert_eq!(out, "finally wins\nsecond\n");
+}
+
+#[test]
+fn uncaught_errors_stop_the_program() {
+    let output = run("uncaught", "println('start');\nthrow 'stop';\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "start\n");
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains("Line number: 2, column number:1: stop"), "{}", out);
+    // A caught error that is thrown again is reported where it first happened.
+    let output = run("rethrown", "try {\n    println(x);\n} catch e {\n    throw e;\n}\n");
+    assert_eq!(output.status.code(), Some(1));
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains("Line number: 2, column number:13: The variable \"x\" is not defined."), "{}", out);
+    let output = run("no_catch", "try {\n    println(1);\n}\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("expected catch or finally"));
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use common::{run, stdout_of, error_of};
+use oran::{Engine, Backend, OranValue};
+
+#[test]
+fn lists_are_copied_when_they_are_stored() {
+    let out = stdout_of("lists", r#"
+let mut xs = [1, [2, 3]];
+let mut ys = xs;
+xs[1][0] = 20;
+push(ys, 'four');
+ys.push([]);
+println(xs, ' ', ys, ' ', len(ys), ' ', xs[1][1]);
+let mut zs = [xs[1]];
+zs[0][1] = 30;
+push(xs, xs);
+println(xs, ' ', zs, ' ', pop(ys), ' ', ys.pop(), ' ', ys);
+for mut x in xs {
+    if x == 1 { continue; }
+    push(x, 'loop');
+}
+println(xs);
+let m = {'a': [1]};
+let mut n = m;
+push(n['a'], 2);
+n['self'] = n;
+println(m, ' ', n);
+struct Bag { items }
+let mut b = Bag { items: 0 };
+b.items = xs[1];
+push(xs[1], 4);
+println(b.items, ' ', xs[1]);
+"#);
+    assert_eq!(out, concat!(
+        "[1, [20, 3]] [1, [2, 3], \"four\", []] 4 3\n",
+        "[1, [20, 3], [1, [20, 3]]] [[20, 30]] [] four [1, [2, 3]]\n",
+        "[1, [20, 3], [1, [20, 3]]]\n",
+        "{\"a\": [1]} {\"a\": [1, 2], \"self\": {\"a\": [1, 2]}}\n",
+        "[20, 3] [20, 3, 4]\n",
+    ));
+}
+
+#[test]
+fn indexes_out_of_range_are_errors() {
+    for (script, expected) in [
+        ("let xs = [1, 2];\nprintln(xs[2]);\n", "Line number: 2, column number:9: The index is out of range: the length is 2 but the index is 2."),
+        ("let xs = [1, 2];\nprintln(xs[-1]);\n", "Line number: 2, column number:9: The index is out of range: the length is 2 but the index is -1."),
+        ("let mut xs = [1, 2];\nxs[5] = 1;\n", "Line number: 2, column number:1: The index is out of range: the length is 2 but the index is 5."),
+        ("let xs = [[1], [2, 3]];\nprintln(xs[1][1], ' ', xs[0][1]);\n", "Line number: 2, column number:24: The index is out of range: the length is 1 but the index is 1."),
+        ("let xs = [1, 2];\nprintln(xs['a']);\n", "Line number: 2, column number:9: The index of a list must be a number: a"),
+    ].iter() {
+        assert!(error_of("out_of_range", script).contains(expected), "{}", script);
+    }
+}
+
+#[test]
+fn constant_lists_and_maps_cannot_be_changed() {
+    for (script, expected) in [
+        ("let xs = [1];\nxs[0] = 2;\n", "Line number: 2, column number:1: You can't change an element of a constant variable."),
+        ("let xs = [1];\npush(xs, 2);\n", "Line number: 2, column number:1: You can't change an element of a constant variable."),
+        ("let xs = [1];\nprintln(xs.pop());\n", "Line number: 2, column number:11: You can't change an element of a constant variable."),
+        ("let m = {'a': 1};\nremove(m, 'a');\n", "Line number: 2, column number:1: You can't change an element of a constant variable."),
+        ("fn add(xs) {\n    push(xs, 1);\n}\nlet mut ys = [];\nadd(ys);\n", "Line number: 2, column number:5: You can't change an element of a constant variable."),
+        ("struct Bag { items }\nimpl Bag {\n    fn add(self, x) { self.items.push(x); }\n}\nlet b = Bag { items: [] };\nb.add(1);\n", "Line number: 3, column number:33: You can't change an element of a constant variable."),
+    ].iter() {
+        let output = run("constant", script);
+        assert_eq!(output.status.code(), Some(1), "{}", script);
+        assert!(String::from_utf8(output.stderr).unwrap().contains(expected), "{}", script);
+    }
+    // A variable holds its own copy, so changing it leaves the constant as it was.
+    let out = stdout_of("through_variable", "let xs = [1, 2];\nlet mut ys = xs;\npush(ys, 3);\nlet mut a = [1];\nlet b = a;\npush(a, 2);\nprintln(xs, ' ', ys, ' ', a, ' ', b);\n");
+    assert_eq!(out, "[1, 2] [1, 2, 3] [1, 2] [1]\n");
+}
+
+#[test]
+fn lists_and_maps_that_contain_themselves() {
+    // Storing a list or an instance in itself stores a copy, so only the host can make a list that contains itself.
+    let out = stdout_of("cycles", r#"
+let mut xs = [1];
+push(xs, xs);
+let mut m = {"a": 1};
+m["self"] = m;
+println(xs, ' ', m, ' ', len(xs[1]));
+struct Node { next }
+let mut n = Node { next: 0 };
+n.next = n;
+println(n, ' ', [n, n]);
+"#);
+    assert_eq!(out, concat!(
+        "[1, [1]] {\"a\": 1, \"self\": {\"a\": 1}} 1\n",
+        "Node { next: Node { next: 0 } } [Node { next: Node { next: 0 } }, Node { next: Node { next: 0 } }]\n",
+    ));
+    for backend in [Backend::Tree, Backend::Vm].iter() {
+        let mut engine = Engine::with_backend(*backend);
+        engine.register_fn("cycle", |args| {
+            let list = Rc::new(RefCell::new(args.to_vec()));
+            list.borrow_mut().push(OranValue::List(list.clone()));
+            Ok(OranValue::List(list))
+        }).unwrap();
+        engine.eval("let a = cycle(1);\nlet b = cycle(1);\nlet mut c = cycle(2);\npush(c, c);").unwrap();
+        let (a, b, c) = (engine.eval("a").unwrap(), engine.eval("b").unwrap(), engine.eval("c").unwrap());
+        assert_eq!(a.to_string(), "[1, [...]]");
+        assert_eq!(c.to_string(), "[2, [...], [2, [...]]]");
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(engine.eval("a == b").unwrap(), OranValue::Boolean(true));
+    }
+}
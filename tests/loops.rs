@@ -0,0 +1,76 @@
+mod common;
+
+use common::{run, stdout_of};
+
+#[test]
+fn break_and_continue_leave_only_the_innermost_loop() {
+    let out = stdout_of("nested", r#"
+let mut i = 0;
+let mut out = [];
+while i < 4 {
+    i += 1;
+    if i == 2 { continue; }
+    let mut j = 0;
+    while true {
+        j += 1;
+        if j == 2 { continue; }
+        if j > 3 { break; }
+        for k in 0..10 {
+            if k == 1 { break; }
+            push(out, [i, j, k]);
+        }
+    }
+    if i == 3 { break; }
+}
+println(out, ' ', i);
+fn first_even(xs) {
+    for x in xs {
+        while true {
+            if x % 2 == 0 { return x; }
+            break;
+        }
+    }
+    -1
+}
+println(first_even([1, 3, 4, 6]), ' ', first_even([1]));
+"#);
+    assert_eq!(out, "[[1, 1, 0], [1, 3, 0], [3, 1, 0], [3, 3, 0]] 3\n4 -1\n");
+}
+
+#[test]
+fn break_and_continue_outside_of_loops_are_errors() {
+    for (script, expected) in [
+        ("break;\n", "Line number: 1, column number:1: \"break\" and \"continue\" can be used only inside of loops."),
+        ("fn f() { break; }\nwhile true { f(); }\n", "Line number: 1, column number:10: \"break\" and \"continue\" can be used only inside of loops."),
+        ("while true {\n    let f = fn() { continue; };\n    f();\n}\n", "Line number: 2, column number:20: \"break\" and \"continue\" can be used only inside of loops."),
+    ].iter() {
+        let output = run("outside", script);
+        assert_eq!(output.status.code(), Some(1), "{}", script);
+        assert!(String::from_utf8(output.stderr).unwrap().contains(expected), "{}", script);
+    }
+}
+
+#[test]
+fn ranges_reach_the_bounds_of_integers() {
+    let out = stdout_of("bounds", r#"
+let max = 9223372036854775807;
+let below_max = max - 1;
+let min = -max - 1;
+for i in below_max..=max {
+    println(i);
+}
+for i in below_max..max {
+    println(i);
+}
+for i in min..=min {
+    println(i);
+}
+for i in min..min {
+    println('empty');
+}
+for i in 3..=2 {
+    println('empty');
+}
+"#);
+    assert_eq!(out, "9223372036854775806\n9223372036854775807\n9223372036854775806\n-9223372036854775808\n");
+}
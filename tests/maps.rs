@@ -0,0 +1,61 @@
+mod common;
+
+use common::{stdout_of, error_of};
+
+#[test]
+fn maps_keep_the_order_their_keys_were_inserted_in() {
+    let out = stdout_of("order", r#"
+let mut m = {"b": 2, "a": 1};
+m["c"] = 3;
+m["b"] = 20;
+remove(m, "a");
+m["a"] = 10;
+println(m, ' ', keys(m), ' ', values(m));
+for k, v in m {
+    println(k, '=', v);
+}
+for k in m {
+    println(k);
+}
+"#);
+    assert_eq!(out, "{\"b\": 20, \"c\": 3, \"a\": 10} [\"b\", \"c\", \"a\"] [20, 3, 10]\nb=20\nc=3\na=10\nb\nc\na\n");
+}
+
+#[test]
+fn keys_keep_their_types() {
+    let out = stdout_of("typed_keys", r#"
+let mut m = {1: 'int', '1': 'string', true: 'bool', 2.5: 'float', 3n: 'bigint', 0.5d: 'decimal'};
+m[-0.0] = 'zero';
+m[0.0] = 'zero again';
+println(m);
+println(keys(m));
+println(m[1], ' ', m['1'], ' ', has_key(m, 2.5), ' ', has_key(m, '2.5'), ' ', len(m));
+for k, v in m {
+    if v == 'int' || v == 'string' {
+        println([k, k + 1]);
+    }
+}
+"#);
+    assert_eq!(out, concat!(
+        "{1: \"int\", \"1\": \"string\", true: \"bool\", 2.5: \"float\", 3: \"bigint\", 0.5: \"decimal\", 0: \"zero again\"}\n",
+        "[1, \"1\", true, 2.5, 3, 0.5, 0]\n",
+        "int string true false 7\n",
+        "[1, 2]\n",
+        "[\"1\", 2]\n",
+    ));
+}
+
+#[test]
+fn missing_keys_are_errors() {
+    for (script, expected) in [
+        ("let m = {'a': 1};\nprintln(m['b']);\n", "Line number: 2, column number:9: The key \"b\" is not found in the map."),
+        ("let m = {'1': 1};\nprintln(m[1]);\n", "Line number: 2, column number:9: The key 1 is not found in the map."),
+        ("let m = {'a': {'b': 1}};\nprintln(m['a']['c']);\n", "Line number: 2, column number:9: The key \"c\" is not found in the map."),
+        ("let m = {[1]: 2};\n", "Line number: 1, column number:9: This value cannot be used as a key of a map: [1]"),
+        ("let mut m = {};\nm[{}] = 2;\n", "Line number: 2, column number:1: This value cannot be used as a key of a map: {}"),
+    ].iter() {
+        assert!(error_of("missing", script).contains(expected), "{}", script);
+    }
+    let out = stdout_of("get", "let mut m = {'a': 1};\nprintln(get(m, 'b'), ' ', remove(m, 'b'), ' ', m);\n");
+    assert_eq!(out, "None  {\"a\": 1}\n");
+}
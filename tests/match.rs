@@ -0,0 +1,107 @@
+mod common;
+
+use common::{run, stdout_of};
+
+#[test]
+fn match_dispatches_on_literals_ranges_and_wildcard() {
+    let out = stdout_of("dispatch", r#"
+fn describe(n) {
+    match n {
+        1 => 'one',
+        2..=4 => 'a few',
+        -2 | -1 => 'negative',
+        0.5..1 => 'a half',
+        "x" | 'y' => 'letter',
+        _ => 'many'
+    }
+}
+for i in 0..6 {
+    print(describe(i), ' ');
+}
+println(describe(-1), ' ', describe(0.75), ' ', describe('y'), ' ', describe([1]));
+"#);
+    assert_eq!(out, "many one a few a few a few many negative a half letter many\n");
+}
+
+#[test]
+fn match_arms_yield_values() {
+    let out = stdout_of("values", r#"
+let mut count = 0;
+fn next() {
+    count += 1;
+    return count;
+}
+let label = match next() {
+    1 => {
+        let word = 'first';
+        word ++ '!'
+    }
+    _ => 'later',
+};
+println(label, ' ', count);
+println(match 'z' { 'a' => 1 });
+match count {
+    1 => println('statement'),
+}
+fn sign(x) {
+    let s = match x {
+        0 => 0,
+        _ => {
+            if x > 0 {
+                return 1;
+            }
+            -1
+        }
+    };
+    s
+}
+println([sign(5), sign(0), sign(-4)]);
+"#);
+    assert_eq!(out, "first! 1\n\nstatement\n[1, 0, -1]\n");
+}
+
+#[test]
+fn the_first_matching_arm_wins() {
+    let out = stdout_of("first_arm", r#"
+fn pick(n) {
+    match n {
+        1..3 => 'below three',
+        3 => 'three',
+        2..=5 => 'up to five',
+        5 => 'unreachable',
+    }
+}
+println(pick(2), ', ', pick(3), ', ', pick(5), ', ', pick(6));
+println(match 1.0 { 1 => 'one', _ => 'other' }, ' ', match 2.5 { 2..3 => 'between' }, ' ', match 'one' { 1 => 'number', _ => 'string' });
+"#);
+    assert_eq!(out, "below three, three, up to five, \none between string\n");
+}
+
+#[test]
+fn patterns_must_be_literals() {
+    let output = run("not_literal", "let a = 1;\nmatch 1 {\n    -a => 1,\n}\n");
+    assert_eq!(output.status.code(), Some(2));
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains("3:5"), "{}", out);
+    assert!(out.contains("A pattern must be a number, a string, a boolean, a range of numbers or _."), "{}", out);
+
+    let output = run("string_range", "match 1 {\n    'a'..'z' => 1,\n}\n");
+    assert_eq!(output.status.code(), Some(2));
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains("A range in a pattern must be between numbers."), "{}", out);
+}
+
+#[test]
+fn match_on_booleans_without_every_arm_warns() {
+    let output = run("booleans", r#"
+let ok = 1 < 2;
+println(match ok { true => 'yes' });
+println(match ok { true => 'yes', false => 'no' });
+println(match ok { false => 'no', _ => 'yes' });
+"#);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "yes\nyes\nyes\n");
+    let err = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(err.matches("Warning!").count(), 1, "{}", err);
+    assert!(err.contains("Line number: 3, column number:9: This match on booleans does not cover false."), "{}", err);
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use common::{run, stdout_of};
+
+#[test]
+fn methods_are_called_with_self() {
+    let out = stdout_of("basics", r#"
+struct Point { x, y }
+impl Point {
+    fn len2(self) {
+        self.x * self.x + self.y * self.y
+    }
+    fn moved(self, dx, dy) {
+        Point { x: self.x + dx, y: self.y + dy }
+    }
+    fn shift(self, dx) {
+        self.x = self.x + dx;
+        self
+    }
+}
+let mut p = Point { x: 1, y: 2 };
+println(p.len2(), ' ', p.moved(2, 2).len2());
+p.shift(10);
+println(p, ' ', p.shift(1).shift(1).x);
+fn origin() { Point { x: 0, y: 0 } }
+println(origin().len2(), ' ', [p][0].moved(0, 0).y);
+"#);
+    assert_eq!(out, "5 25\nPoint { x: 13, y: 2 } 13\n0 2\n");
+}
+
+#[test]
+fn methods_can_be_added_by_later_impl_blocks() {
+    let out = stdout_of("later", r#"
+struct Counter { n }
+impl Counter {
+    fn len(self) { self.n }
+}
+let c = Counter { n: 3 };
+impl Counter {
+    fn fact(self, k) {
+        if k < 2 {
+            return 1;
+        }
+        k * self.fact(k - 1)
+    }
+}
+println(c.len(), ' ', c.fact(c.len() + 2));
+"#);
+    assert_eq!(out, "3 120\n");
+}
+
+#[test]
+fn strings_numbers_lists_and_maps_have_builtin_methods() {
+    let out = stdout_of("builtin", r#"
+println("  Abc ".trim().upper(), ' ', "ÄB".lower(), ' ', "héllo".len());
+let x = -2.5;
+println(x.round(), ' ', x.floor(), ' ', x.ceil(), ' ', x.abs());
+println(7.abs(), ' ', (-7).abs(), ' ', 2.5.round(), ' ', 1.25d.round(), ' ', (-12n).abs(), ' ', (1 + 2).abs());
+let mut xs = [1, 2];
+xs.push(3);
+let m = { "a": 1 };
+println(xs.len(), ' ', xs.pop(), ' ', xs, ' ', m.keys(), ' ', m.has_key("a"));
+"#);
+    assert_eq!(out, "ABC äb 5\n-3 -3 -2 2.5\n7 7 3 1 12 3\n3 3 [1, 2] [\"a\"] true\n");
+}
+
+#[test]
+fn self_is_as_changeable_as_the_receiver() {
+    let out = stdout_of("receiver", r#"
+struct Point { x }
+impl Point {
+    fn shift(self, dx) {
+        self.x = self.x + dx;
+        self
+    }
+    fn bump(self) { self.shift(1) }
+    fn reset_later(self) { fn() { self.x = 0; } }
+    fn replaced(self) {
+        self = Point { x: 9 };
+        self
+    }
+}
+let mut m = Point { x: 1 };
+m.bump();
+println(m, ' ', Point { x: 1 }.shift(2), ' ', m.replaced(), ' ', m);
+let c = Point { x: 1 };
+let cs = [c];
+let attempts = [
+    fn() { c.shift(1); },
+    fn() { c.bump(); },
+    fn() { cs[0].shift(1); },
+    fn() { let reset = c.reset_later(); reset(); },
+    fn() { c.replaced(); },
+];
+for attempt in attempts {
+    try {
+        attempt();
+    } catch e {
+        println(e.line, ': ', e.message);
+    }
+}
+"#);
+    assert_eq!(out, concat!(
+        "Point { x: 2 } Point { x: 3 } Point { x: 9 } Point { x: 2 }\n",
+        "5: You can't change a field of a constant variable.\n",
+        "5: You can't change a field of a constant variable.\n",
+        "5: You can't change a field of a constant variable.\n",
+        "9: You can't change a field of a constant variable.\n",
+        "11: You can't assign value twice to a constant variable.\n",
+    ));
+}
+
+#[test]
+fn methods_are_looked_up_by_the_type_of_the_receiver() {
+    let output = run("missing", "struct P { x }\nlet p = P { x: 1 };\np.y();\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 3, column number:2: P has no method \"y\"."));
+    let output = run("not_a_string", "println(5.upper());\n");
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 1, column number:10: \"upper\" is a method of strings: 5"));
+    let output = run("undefined", "let xs = [1];\nxs.upper_all();\n");
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 2, column number:3: The method \"upper_all\" is not defined for this value: [1]"));
+    let output = run("overflow", "let x = -9223372036854775807 - 1;\nx.abs();\n");
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 2, column number:2: Integer overflow: abs(-9223372036854775808)"));
+}
+
+#[test]
+fn impl_needs_a_struct_and_self() {
+    let output = run("no_struct", "impl Q {\n    fn a(self) { 1 }\n}\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 1, column number:1: The struct \"Q\" is not defined."));
+    let output = run("without_self", "struct P { x }\nimpl P {\n    fn a(x) { x }\n}\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("The first argument of a method must be self."));
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Output;
+use common::{temp_path, run_args};
+
+/// Writes the files to a temporary directory and runs `main.orn` in it on both backends,
+/// with `lib` in the search path.
+fn run(name: &str, files: &[(&str, &str)]) -> (Output, PathBuf) {
+    let dir = temp_path(name);
+    for (file, script) in files {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, script).unwrap();
+    }
+    let output = run_args(&[
+        PathBuf::from("-I"), dir.join("lib"),
+        PathBuf::from("-f"), dir.join("main.orn"),
+    ]);
+    fs::remove_dir_all(&dir).unwrap();
+    (output, dir)
+}
+
+fn stdout_of(name: &str, files: &[(&str, &str)]) -> String {
+    let (output, _) = run(name, files);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+const UTIL: &str = r#"
+let NAME = 'util';
+let mut count = 0;
+fn double(x) { x * 2 }
+fn counter() {
+    count = count + 1;
+    count
+}
+"#;
+
+#[test]
+fn modules_are_imported_with_their_names() {
+    let out = stdout_of("names", &[
+        ("main.orn", r#"
+import util;
+import "sub/shapes.orn" as s;
+println(util.double(21), ' ', util.NAME, ' ', s.area(s.rect(2, 3)), ' ', s);
+let double = util.double;
+println(double(4));
+"#),
+        ("util.orn", UTIL),
+        ("sub/shapes.orn", r#"
+enum Shape { Rect(w, h) }
+fn rect(w, h) { Shape::Rect(w, h) }
+fn area(shape) {
+    match shape {
+        Shape::Rect(w, h) => w * h,
+    }
+}
+"#),
+    ]);
+    assert_eq!(out, "42 util 6 <module shapes>\n8\n");
+}
+
+#[test]
+fn modules_run_once_and_are_searched_in_the_search_path() {
+    let out = stdout_of("cache", &[
+        ("main.orn", r#"
+import util;
+import strs;
+println(util.counter(), ' ', strs.shout('hi'), ' ', util.counter());
+fn again() {
+    import "util.orn" as u;
+    u.counter()
+}
+println(again());
+"#),
+        ("util.orn", UTIL),
+        ("lib/strs.orn", r#"
+import "../util.orn";
+println('loading strs');
+fn shout(s) { s.upper() ++ util.counter() }
+"#),
+    ]);
+    assert_eq!(out, "loading strs\n1 HI2 3\n4\n");
+}
+
+#[test]
+fn members_are_what_the_globals_of_the_module_are_now() {
+    let out = stdout_of("live", &[
+        ("main.orn", r#"
+import counter;
+println(counter.count);
+counter.inc();
+counter.inc();
+println(counter.count, ' ', counter.items);
+let mut items = counter.items;
+push(items, 'main');
+println(counter.items);
+"#),
+        ("counter.orn", r#"
+let mut count = 0;
+let mut items = [];
+fn inc() {
+    count += 1;
+    push(items, count);
+    count
+}
+"#),
+    ]);
+    assert_eq!(out, "0\n2 [1, 2]\n[1, 2]\n");
+}
+
+#[test]
+fn modules_that_import_each_other_are_errors() {
+    let (output, dir) = run("cycle", &[
+        ("main.orn", "println('start');\nimport a;\n"),
+        ("a.orn", "import b;\n"),
+        ("b.orn", "fn f() { 1 }\nimport a;\n"),
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "start\n");
+    let out = String::from_utf8(output.stderr).unwrap();
+    let a = dir.join("a.orn").to_string_lossy().into_owned();
+    let b = dir.join("b.orn").to_string_lossy().into_owned();
+    let expected = format!("{}\nLine number: 2, column number:1: The modules import each other: {} -> {} -> {}", b, a, b, a);
+    assert!(out.contains(&expected), "{}", out);
+}
+
+#[test]
+fn modules_see_only_their_own_globals() {
+    let out = stdout_of("own_globals", &[
+        ("main.orn", r#"
+let NAME = 'main';
+let secret = 1;
+import util;
+import "util.orn" as again;
+println(util.name(), ' ', again == util, ' ', again);
+try {
+    util.peek();
+} catch e {
+    println(e.kind, ' ', e.message);
+}
+try {
+    import bad;
+} catch e {
+    println(e.kind, ' ', e.line, ' ', e.message);
+}
+"#),
+        ("util.orn", "let NAME = 'util';\nfn name() { NAME }\nfn peek() { secret }\n"),
+        ("bad.orn", "println('bad start');\nthrow 'bad module';\n"),
+    ]);
+    assert_eq!(out, "util true <module util>\nUndefinedVariable The variable \"secret\" is not defined.\nbad start\nThrown 2 bad module\n");
+}
+
+#[test]
+fn missing_modules_and_members_are_errors() {
+    let (output, dir) = run("not_found", &[("main.orn", "import nothing;\n")]);
+    assert_eq!(output.status.code(), Some(1));
+    let expected = format!("{}\nLine number: 1, column number:1: The module \"nothing.orn\" is not found.", dir.join("main.orn").to_string_lossy());
+    assert!(String::from_utf8(output.stderr).unwrap().contains(&expected));
+    let (output, _) = run("no_function", &[("main.orn", "import util;\nutil.triple(1);\n"), ("util.orn", UTIL)]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 2, column number:5: The module util has no function \"triple\"."));
+    let (output, _) = run("no_variable", &[("main.orn", "import util;\nprintln(util.name);\n"), ("util.orn", UTIL)]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 2, column number:13: The module util has no variable \"name\"."));
+}
+
+#[test]
+fn errors_in_modules_are_located_in_their_files() {
+    let (output, dir) = run("runtime", &[("main.orn", "import util;\nutil.double('a');\n"), ("util.orn", UTIL)]);
+    assert_eq!(output.status.code(), Some(1));
+    let expected = format!("{}\nLine number: 4, column number:18: This \"a\" is not a number.", dir.join("util.orn").to_string_lossy());
+    assert!(String::from_utf8(output.stderr).unwrap().contains(&expected));
+    let (output, dir) = run("syntax", &[("main.orn", "import util;\n"), ("util.orn", "let x = ;\n")]);
+    assert_eq!(output.status.code(), Some(2));
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains(&format!("{} --> 1:9", dir.join("util.orn").to_string_lossy())), "{}", out);
+}
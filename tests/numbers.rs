@@ -0,0 +1,141 @@
+mod common;
+
+use common::{run, stdout_of};
+use oran::{Engine, Backend, OranValue, ErrorKind, BigInt, Decimal};
+
+#[test]
+fn integer_and_float_arithmetic() {
+    let out = stdout_of("promotion", r#"
+println(7 / 2);
+println(7.0 / 2);
+println((-7 / 2) << ' ' << (-7 % 3));
+println((2 ^ 62) << ' ' << (2 ^ -1));
+println(9007199254740993 + 0);
+println(1 + 0.5);
+"#);
+    assert_eq!(out, "3\n3.5\n-3 -1\n4611686018427387904 0.5\n9007199254740993\n1.5\n");
+}
+
+#[test]
+fn integers_and_floats_compare_by_value() {
+    let out = stdout_of("compare", r#"
+if 1 == 1.0 {
+    println('equal');
+}
+if 9007199254740993 > 9007199254740992 {
+    println('exact');
+}
+"#);
+    assert_eq!(out, "equal\nexact\n");
+}
+
+#[test]
+fn overflow_and_division_by_zero_are_errors() {
+    let output = run("overflow", "let max = 9223372036854775807;\nprintln(max - 1);\nprintln(max + 1);\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "9223372036854775806\n");
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains("Line number: 3, column number:13: Integer overflow: 9223372036854775807 + 1"));
+
+    let output = run("zero", "println(1.0 / 0);\nprintln(1 % 0);\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "inf\n");
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 2, column number:11: Division by zero."));
+}
+
+#[test]
+fn out_of_range_integer_literal_is_a_syntax_error() {
+    let output = run("literal", "let big = 9223372036854775808;\n");
+    assert_eq!(output.status.code(), Some(2));
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains(" --> 1:11"));
+    assert!(out.contains("This integer is out of range: 9223372036854775808."));
+}
+
+#[test]
+fn literals_in_other_forms() {
+    let out = stdout_of("literal_forms", r#"
+println(0xFF, ' ', 0o17, ' ', 0b1010, ' ', -0x10, ' ', 1_000_000, ' ', 0xFF_FF);
+println(1e9, ' ', 2.5e-3, ' ', 1E+2, ' ', 1_000.5);
+println(0xFFFF_FFFF_FFFF_FFFFn, ' ', 1_000n, ' ', 1_000.25d, ' ', -0x8000000000000000);
+for i in 0..0b11 {
+    println(i);
+}
+"#);
+    assert_eq!(out, "255 15 10 -16 1000000 65535\n1000000000 0.0025 100 1000.5\n18446744073709551615 1000 1000.25 -9223372036854775808\n0\n1\n2\n");
+}
+
+#[test]
+fn out_of_range_literals_in_other_forms_are_syntax_errors() {
+    for (script, position, message) in [
+        ("let big = 0x8000000000000000;\n", " --> 1:11", "This integer is out of range: 0x8000000000000000."),
+        ("let big = 1_000_000_000_000_000_000_000;\n", " --> 1:11", "This integer is out of range: 1_000_000_000_000_000_000_000."),
+        ("let big = 1e400;\n", " --> 1:11", "This float is out of range: 1e400."),
+    ].iter() {
+        let output = run("literal_range", script);
+        assert_eq!(output.status.code(), Some(2));
+        let out = String::from_utf8(output.stderr).unwrap();
+        assert!(out.contains(position), "{}", out);
+        assert!(out.contains(message), "{}", out);
+    }
+    assert_eq!(run("literal_digit", "let b = 0b102;\n").status.code(), Some(2));
+}
+
+#[test]
+fn bigints_do_not_overflow() {
+    let out = stdout_of("bigint", r#"
+println(9223372036854775807 + 1n);
+println(2n ^ 100);
+println((-7n / 2n) << ' ' << (-7n % 3n));
+println((10 * bigint("123456789012345678901234567890")) << ' ' << bigint(3.0));
+println(5n + 0.5);
+if 10n > 9 {
+    println('compared');
+}
+"#);
+    assert_eq!(out, "9223372036854775808\n1267650600228229401496703205376\n-3 -1\n1234567890123456789012345678900 3\n5.5\ncompared\n");
+}
+
+#[test]
+fn decimals_are_exact() {
+    let out = stdout_of("decimal", r#"
+println(0.10d + 0.20d);
+if 0.1d + 0.2d == 0.3d {
+    println('exact');
+}
+println((19.99d * 3) << ' ' << (10.25d % 3) << ' ' << (1.10d ^ 2));
+println(1d / 3);
+println(decimal("0.10") + decimal(0.2) + 1n);
+"#);
+    assert_eq!(out, "0.30\nexact\n59.97 1.25 1.2100\n0.3333333333333333333333333333\n1.30\n");
+}
+
+#[test]
+fn decimals_and_floats_do_not_mix() {
+    let output = run("decimal_float", "let price = 1.50d;\nprintln(price * 2);\nprintln(price * 0.5);\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3.00\n");
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains("Line number: 3, column number:15: A decimal and a float cannot be calculated together: 1.50 * 0.5."));
+
+    let output = run("decimal_literal", "let d = 123456789012345678901234567890d;\n");
+    assert_eq!(output.status.code(), Some(2));
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains(" --> 1:9"));
+    assert!(out.contains("This decimal is out of range: 123456789012345678901234567890d."));
+
+    let out = String::from_utf8(run("bigint_fraction", "println(bigint(1.5));\n").stderr).unwrap();
+    assert!(out.contains("This \"1.5\" is not an integer."));
+}
+
+#[test]
+fn engine_returns_integers() {
+    for backend in [Backend::Tree, Backend::Vm].iter() {
+        let mut engine = Engine::with_backend(*backend);
+        assert!(matches!(engine.eval("7 / 2").unwrap(), OranValue::Int(3)));
+        assert!(matches!(engine.eval("7 / 2.0").unwrap(), OranValue::Float(f) if f == 3.5));
+        assert!(matches!(engine.eval("let xs = [1, 2]; 0 + len(xs)").unwrap(), OranValue::Int(2)));
+        assert_eq!(engine.eval("1 / 0").unwrap_err().kind, ErrorKind::Arithmetic);
+        assert_eq!(engine.eval("2n ^ 64").unwrap(), OranValue::BigInt(BigInt::from(u64::MAX) + 1));
+        assert!(matches!(engine.eval("1.5d * 2").unwrap(), OranValue::Decimal(d) if d == Decimal::new(300, 2)));
+    }
+}
@@ -0,0 +1,231 @@
+mod common;
+
+use common::{run, stdout_of};
+use oran::{Engine, Backend, OranValue};
+
+#[test]
+fn comparisons_are_values() {
+    let out = stdout_of("comparisons", r#"
+let a = 1;
+let b = 2;
+let ok = a != b;
+println(ok, ' ', a == b, ' ', a <= b, ' ', a >= b, ' ', 'x' != "x");
+fn is_even(n) { return n % 2 == 0; }
+println([is_even(4), is_even(3), len([1]) != 1]);
+println({"ok": (a < b)});
+"#);
+    assert_eq!(out, "true false true false false\n[true, false, false]\n{\"ok\": true}\n");
+}
+
+#[test]
+fn deprecated_comparisons_still_work() {
+    let out = stdout_of("deprecated", r#"
+if 2 => 2 {
+    println('=>');
+}
+if 2 =< 1 {
+    println('=<');
+}
+"#);
+    assert_eq!(out, "=>\n");
+}
+
+#[test]
+fn not_negates_truthiness() {
+    let out = stdout_of("not", r#"
+let done = false;
+println(!done, ' ', !!done, ' ', !(1 == 1), ' ', !0, ' ', !'', ' ', ![1]);
+let mut n = 0;
+while !(n >= 3) {
+    n = n + 1;
+}
+if n != 0 && !done {
+    println(n);
+}
+"#);
+    assert_eq!(out, "true false false true true false\n3\n");
+}
+
+#[test]
+fn engine_returns_booleans() {
+    for backend in [Backend::Tree, Backend::Vm].iter() {
+        let mut engine = Engine::with_backend(*backend);
+        assert_eq!(engine.eval("1 != 2").unwrap(), OranValue::Boolean(true));
+        assert_eq!(engine.eval("!(3 <= 2)").unwrap(), OranValue::Boolean(true));
+    }
+}
+
+#[test]
+fn deeply_nested_calls_parse() {
+    // Parsing must not try every kind of operation again at each level of nesting.
+    let depth = 20;
+    let script = format!("fn f(x) {{ x + 1 }} {}0{} == {}", "f(".repeat(depth), ")".repeat(depth), depth);
+    for backend in [Backend::Tree, Backend::Vm].iter() {
+        let mut engine = Engine::with_backend(*backend);
+        assert_eq!(engine.eval(&script).unwrap(), OranValue::Boolean(true));
+    }
+}
+
+#[test]
+fn deeply_nested_parentheses_parse() {
+    let depth = 20;
+    let nested = format!("{}1 == 1 && 2 > 1{}", "(".repeat(depth), ")".repeat(depth));
+    let script = format!("\"${{len([{}, {}])}}\" ++ !{}", nested, nested, nested);
+    for backend in [Backend::Tree, Backend::Vm].iter() {
+        let mut engine = Engine::with_backend(*backend);
+        assert_eq!(engine.eval(&script).unwrap(), OranValue::Str("2false".into()));
+    }
+}
+
+#[test]
+fn and_or_short_circuit() {
+    let out = stdout_of("short_circuit", r#"
+fn loud(v) {
+    println('called');
+    return v;
+}
+let x = 0;
+if x != 0 && 10 / x > 1 {
+    println('divided');
+}
+println(false && loud(true), ' ', true || loud(false));
+println(true && loud(false));
+"#);
+    assert_eq!(out, "false true\ncalled\nfalse\n");
+}
+
+#[test]
+fn and_or_are_values() {
+    let out = stdout_of("and_or_values", r#"
+let a = 1;
+let b = [];
+let ok = a > 0 && b;
+println(ok, ' ', ok || 'x', ' ', (false || true) && 1);
+println((a + 1 == 2), ' ', !(a > 0 && a < 2));
+println([a == 1 || a == 2]);
+"#);
+    assert_eq!(out, "false true true\ntrue false\n[true]\n");
+}
+
+#[test]
+fn bitwise_operators_on_integers() {
+    let out = stdout_of("bitwise", r#"
+let flags = 12;
+println(flags & 10, ' ', flags | 3, ' ', flags xor 5, ' ', ~flags, ' ', 1 << 4, ' ', -17 >> 2);
+println(1 + 2 << 3, ' ', 1 | 2 & 3, ' ', 6 & 3 == 2, ' ', 1 >> 100);
+println(1n << 100, ' ', ~5n, ' ', 3n & 5);
+"#);
+    assert_eq!(out, "8 15 9 -13 16 -5\n24 3 true 0\n1267650600228229401496703205376 -6 1\n");
+}
+
+#[test]
+fn concatenation_with_plus_plus_and_shift() {
+    let out = stdout_of("concat", r#"
+let s = 'total: ';
+println(s ++ 2 * 3, ' ', 'a' ++ [1] ++ true);
+println(s << 2 * 3, ' ', "x" << 1 << 2, ' ', 1 << 2);
+"#);
+    assert_eq!(out, "total: 6 a[1]true\ntotal: 6 x12 4\n");
+}
+
+#[test]
+fn bitwise_errors_are_located() {
+    for (script, message) in [
+        ("println(1.5 & 1);\n", "column number:13: Bitwise operators need integers: 1.5 & 1"),
+        ("println(1 << 63);\n", "column number:11: Integer overflow: 1 << 63"),
+        ("println(1 >> -1);\n", "column number:11: Cannot shift by a negative amount: 1 >> -1"),
+    ].iter() {
+        let output = run("bitwise_errors", script);
+        assert_eq!(output.status.code(), Some(1));
+        assert!(String::from_utf8_lossy(&output.stderr).contains(message), "{}", script);
+    }
+}
+
+#[test]
+fn unary_minus_and_plus() {
+    let out = stdout_of("unary", r#"
+let x = 3;
+fn twice(n) { return n * 2; }
+println(-x, ' ', -(x + 1), ' ', - twice(x), ' ', - -x, ' ', +x, ' ', +'5', ' ', 1 - -x);
+println(-x ^ 2, ' ', (-x) ^ 2, ' ', -2 ^ 2, ' ', 2 ^ -1, ' ', -x * 2);
+println(-9223372036854775808, ' ', -5n, ' ', -0.5d, ' ', [-1, -x]);
+for i in -x..-1 {
+    println(i);
+}
+"#);
+    assert_eq!(out, "-3 -4 -6 3 3 5 4\n-9 9 -4 0.5 -6\n-9223372036854775808 -5 -0.5 [-1, -3]\n-3\n-2\n");
+}
+
+#[test]
+fn unary_errors_are_located() {
+    for (script, message) in [
+        ("let min = -9223372036854775808;\nprintln(-min);\n", "Line number: 2, column number:9: Integer overflow: -(-9223372036854775808)"),
+        ("println(+'abc');\n", "Line number: 1, column number:9: This \"abc\" is not a number."),
+    ].iter() {
+        let output = run("unary_errors", script);
+        assert_eq!(output.status.code(), Some(1));
+        assert!(String::from_utf8_lossy(&output.stderr).contains(message), "{}", script);
+    }
+}
+
+#[test]
+fn compound_assignment() {
+    let out = stdout_of("compound", r#"
+let mut s = 0;
+for i in 1..=4 {
+    s += i;
+}
+let mut f = 10;
+f -= 1;
+f *= 2 + 1;
+f /= 2;
+f %= 5;
+f ^= 3;
+let mut t = 'a';
+t <<= 'b';
+t <<= 1 + 2;
+let mut bits = 1;
+bits <<= 4;
+println(s, ' ', f, ' ', t, ' ', bits);
+"#);
+    assert_eq!(out, "10 27 ab3 16\n");
+}
+
+#[test]
+fn compound_assignment_to_elements_and_fields() {
+    let out = stdout_of("compound_elements", r#"
+let mut xs = [1, [2, 3]];
+let mut m = {"a": [10], "s": "x"};
+struct P { x }
+let mut p = P { x: 1 };
+let mut calls = [];
+fn at(i) {
+    push(calls, i);
+    i
+}
+xs[at(0)] += 5;
+xs[1][at(1)] *= 10;
+m["a"][0] -= 1;
+m["s"] <<= "y";
+p.x += 41;
+p.x <<= 1;
+println(xs, ' ', m, ' ', p.x, ' ', calls);
+"#);
+    assert_eq!(out, "[6, [2, 30]] {\"a\": [9], \"s\": \"xy\"} 84 [0, 1]\n");
+}
+
+#[test]
+fn compound_assignment_errors_are_located() {
+    for (script, message) in [
+        ("let c = 1;\nc += 1;\n", "Line number: 2, column number:1: You can't assign value twice to a constant variable."),
+        ("fn f() {\n    let k = 2;\n    k *= 2;\n}\nf();\n", "Line number: 3, column number:5: You can't assign value twice to a constant variable."),
+        ("let mut z = 'a';\nz -= 1;\n", "Line number: 2, column number:3: This \"a\" is not a number."),
+        ("let xs = [1];\nxs[0] += 1;\n", "Line number: 2, column number:1: You can't change an element of a constant variable."),
+        ("let mut m = {};\nm['a'] += 1;\n", "Line number: 2, column number:1: The key \"a\" is not found in the map."),
+        ("let mut xs = ['a'];\nxs[0] -= 1;\n", "Line number: 2, column number:7: This \"a\" is not a number."),
+    ].iter() {
+        let output = run("compound_errors", script);
+        assert_eq!(output.status.code(), Some(1));
+        assert!(String::from_utf8_lossy(&output.stderr).contains(message), "{}", script);
+    }
+}
@@ -0,0 +1,60 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Feeds the lines to the REPL on both backends and returns what it printed to stdout.
+/// Prompts go to stderr, so stdout has only the output of the entries.
+fn repl(input: &str) -> String {
+    repl_output(input).0
+}
+
+/// What the REPL printed to stdout and to stderr, where the prompts and the errors go.
+fn repl_output(input: &str) -> (String, String) {
+    let outputs: Vec<(String, String)> = ["tree", "vm"].iter().map(|backend| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_oran"))
+            .arg("-b")
+            .arg(backend)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success());
+        (String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+    }).collect();
+    // The banner on stderr names the backend.
+    assert_eq!(outputs[0].0, outputs[1].0);
+    outputs.into_iter().next().unwrap()
+}
+
+#[test]
+fn environment_persists_across_entries() {
+    let out = repl("let a = 1;\nlet mut b = a + 1;\nb = b * 10;\nprintln(a << ' ' << b);\n");
+    assert_eq!(out, "1 20\n");
+}
+
+#[test]
+fn trailing_expression_is_echoed() {
+    let out = repl("let xs = [1, 2];\nxs\n1 + len(xs)\nlen(xs);\nprintln('printed')\n");
+    assert_eq!(out, "[1, 2]\n3\nprinted\n");
+}
+
+#[test]
+fn unfinished_blocks_continue_on_next_line() {
+    let out = repl("fn add(x, y) {\n    return x + y;\n}\nif add(1, 2) == 3 {\n    println('three');\n}\nadd(3, 4)\n");
+    assert_eq!(out, "three\n7\n");
+}
+
+#[test]
+fn error_does_not_end_the_session() {
+    let (out, err) = repl_output("let a = 1;\nprintln(nope);\na\n");
+    assert!(err.contains("The variable \"nope\" is not defined."));
+    assert_eq!(out, "1\n");
+}
+
+#[test]
+fn meta_commands() {
+    let out = repl("let a = 1;\nfn f(x) { return x; }\n:env\n:reset\n:env\nlet b = 2;\n:env\n:quit\nprintln('not reached');\n");
+    assert_eq!(out, "a = 1\nfn f\nb = 2\n");
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::{run, stdout_of};
+
+#[test]
+fn escape_sequences() {
+    let out = stdout_of("escapes", r#"
+println("tab\there\\n" << 'it\'s "quoted"' << "\"" << '\0' << "\u{41}\u{1F600}");
+println(len("\r\n"));
+"#);
+    assert_eq!(out, "tab\there\\nit's \"quoted\"\"\0A\u{1F600}\n2\n");
+}
+
+#[test]
+fn raw_strings_keep_backslashes() {
+    let out = stdout_of("raw", r###"
+println(r"C:\new\table");
+println(r#"say "hi" \n"#);
+"###);
+    assert_eq!(out, "C:\\new\\table\nsay \"hi\" \\n\n");
+}
+
+#[test]
+fn multi_line_string_strips_common_indentation() {
+    let out = stdout_of("multi_line", r#"
+fn usage() {
+    return """
+        usage:
+            oran -f <file>\tRun a file
+
+        Options can be given in any order.
+        """;
+}
+print(usage());
+print("|" << """single""" << "|");
+"#);
+    assert_eq!(out, "usage:\n    oran -f <file>\tRun a file\n\nOptions can be given in any order.|single|");
+}
+
+#[test]
+fn unknown_escape_is_a_located_syntax_error() {
+    let output = run("unknown_escape", "println('ok');\nlet path = \"C:\\dir\";\n");
+    assert_eq!(output.status.code(), Some(2));
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains(" --> 2:15"));
+    assert!(out.contains("Unknown escape sequence: \\d"));
+    assert!(!out.contains("ok\n"));
+}
+
+#[test]
+fn double_quoted_strings_interpolate_expressions() {
+    let out = stdout_of("interpolation", r#"
+let price = 250.12;
+let xs = [1, 2];
+fn double(x) { return x * 2; }
+println("price: ${price}, count: ${ 1 + len(xs) }, ${double(xs[1])}, ${"inner ${xs[0]}"}");
+println('literal ${price}' << " \${price} costs $5");
+"#);
+    assert_eq!(out, "price: 250.12, count: 3, 4, inner 1\nliteral ${price} ${price} costs $5\n");
+}
+
+#[test]
+fn error_in_interpolation_is_located_inside_the_string() {
+    let output = run("interpolation_error", "let a = 1;\nprintln(\"a is ${a}, b is ${b}\");\n");
+    assert_eq!(output.status.code(), Some(1));
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains("Line number: 2, column number:28: The variable \"b\" is not defined."));
+}
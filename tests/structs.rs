@@ -0,0 +1,101 @@
+mod common;
+
+use common::{run, stdout_of};
+
+#[test]
+fn structs_are_declared_built_and_read() {
+    let out = stdout_of("basics", r#"
+struct Point { x, y }
+struct Line {
+    from,
+    to,
+}
+let p = Point { x: 1, y: 2 };
+let line = Line { from: p, to: Point { y: 4, x: 3 } };
+println(p);
+println(line.to.x - line.from.x, ' ', [p][0].y);
+fn len2(l) {
+    let dx = l.to.x - l.from.x;
+    let dy = l.to.y - l.from.y;
+    dx * dx + dy * dy
+}
+println(len2(line));
+println(Point { x: 1, y: 2 } == p, ' ', Point { x: 2, y: 2 } == p, ' ', Point);
+"#);
+    assert_eq!(out, "Point { x: 1, y: 2 }\n2 2\n8\ntrue false <struct Point>\n");
+}
+
+#[test]
+fn fields_of_mutable_variables_can_be_assigned() {
+    let out = stdout_of("assign", r#"
+struct Point { x, y }
+let mut p = Point { x: 1, y: 2 };
+p.x = 10;
+let mut copy = p;
+copy.y = 20;
+println(p, ' ', copy);
+let mut points = [Point { x: 0, y: 0 }];
+points[0].x = 5;
+let mut nested = { "p": p };
+nested["p"].y = 7;
+println(points, ' ', p.y, ' ', nested["p"].y);
+"#);
+    assert_eq!(out, "Point { x: 10, y: 2 } Point { x: 10, y: 20 }\n[Point { x: 5, y: 0 }] 2 7\n");
+}
+
+#[test]
+fn fields_of_constants_cannot_be_assigned() {
+    let output = run("constant", "struct Point { x, y }\nlet p = Point { x: 1, y: 2 };\nprintln('before');\np.x = 3;\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "before\n");
+    let out = String::from_utf8(output.stderr).unwrap();
+    assert!(out.contains("Line number: 4, column number:1: You can't change a field of a constant variable."), "{}", out);
+    // A variable holds its own copy, so changing it leaves the constant as it was.
+    let out = stdout_of("through_variable", "struct P { x }\nlet p = P { x: 1 };\nlet mut q = p;\nq.x = 5;\nprintln(p.x, ' ', q.x);\n");
+    assert_eq!(out, "1 5\n");
+}
+
+#[test]
+fn structs_without_fields_and_of_other_structs() {
+    let out = stdout_of("edges", r#"
+struct Unit {}
+struct A { x }
+struct B { x }
+let u = Unit {};
+println(u, ' ', Unit {} == u, ' ', A { x: 1 } == B { x: 1 }, ' ', A { x: [1] } == A { x: [1] });
+let mut outer = A { x: A { x: 1 } };
+outer.x.x = 2;
+println(outer);
+"#);
+    assert_eq!(out, "Unit {} true false true\nA { x: A { x: 2 } }\n");
+}
+
+#[test]
+fn struct_literals_give_each_field_once() {
+    let output = run("unknown_field", "struct P { x }\nlet a = P { x: 1, y: 2 };\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 2, column number:9: P has no field \"y\"."));
+    let output = run("missing_field", "struct P { x, y }\nlet a = P { y: 2 };\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 2, column number:9: The field \"x\" of P is not given."));
+    // Giving a field twice and declaring it twice are found before the program runs.
+    let output = run("given_twice", "struct P { x }\nlet a = P { x: 1, x: 2 };\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("The field \"x\" is given twice."));
+    let output = run("declared_twice", "struct P { x, x }\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("The field \"x\" is declared twice."));
+}
+
+#[test]
+fn only_structs_have_fields() {
+    let output = run("read", "println(1.x);\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 1, column number:10: This value has no fields: 1"));
+    let output = run("nested", "struct P { x }\nlet mut m = { \"p\": P { x: 1 } };\nm[\"p\"].x.y = 2;\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 3, column number:1: This value has no fields: 1"));
+    let output = run("undefined", "let a = Q { x: 1 };\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Line number: 1, column number:9: The struct \"Q\" is not defined."));
+}